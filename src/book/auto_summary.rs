@@ -0,0 +1,304 @@
+//! Generating a `SUMMARY.md` from the files in a book's source directory, for
+//! books which don't have one.
+
+use std::cmp::Ordering;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::config::AutoSummarySort;
+use crate::errors::*;
+
+/// Chapter files which are used for their directory rather than listed
+/// alongside the other files in it.
+const INDEX_FILES: &[&str] = &["README.md", "index.md"];
+
+/// Build the text of a `SUMMARY.md` listing every markdown file in `src_dir`.
+///
+/// A top-level `README.md` or `index.md` becomes the introduction. Each
+/// subdirectory becomes a chapter with the files inside it nested underneath,
+/// using its own `README.md` or `index.md` as the chapter's page, or a draft
+/// chapter without one. Chapters are named after their first `# ` heading,
+/// falling back to the file name.
+pub(crate) fn generate_summary(src_dir: &Path, sort: AutoSummarySort) -> Result<String> {
+    let mut summary = String::from("# Summary\n\n");
+    if let Some(index) = index_file(src_dir) {
+        let name = chapter_name(&index)?.unwrap_or_else(|| String::from("Introduction"));
+        let link = link_target(Path::new(index.file_name().unwrap()));
+        summary.push_str(&format!("[{}]({})\n\n", escape_link_text(&name), link));
+    }
+    write_entries(src_dir, src_dir, sort, 0, &mut summary)?;
+    Ok(summary)
+}
+
+fn write_entries(
+    src_dir: &Path,
+    dir: &Path,
+    sort: AutoSummarySort,
+    depth: usize,
+    summary: &mut String,
+) -> Result<()> {
+    for entry in sorted_entries(dir, dir == src_dir, sort)? {
+        let relative = entry
+            .strip_prefix(src_dir)
+            .expect("entries are inside the source directory");
+        let indent = "    ".repeat(depth);
+
+        if entry.is_dir() {
+            let index = index_file(&entry);
+            let name = entry
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+
+            match index {
+                Some(index) => {
+                    let name = chapter_name(&index)?.unwrap_or(name);
+                    let link = link_target(&relative.join(index.file_name().unwrap()));
+                    summary.push_str(&format!(
+                        "{}- [{}]({})\n",
+                        indent,
+                        escape_link_text(&name),
+                        link
+                    ));
+                }
+                None => summary.push_str(&format!("{}- [{}]()\n", indent, escape_link_text(&name))),
+            }
+
+            write_entries(src_dir, &entry, sort, depth + 1, summary)?;
+        } else {
+            let name = match chapter_name(&entry)? {
+                Some(name) => name,
+                None => entry
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+            };
+            summary.push_str(&format!(
+                "{}- [{}]({})\n",
+                indent,
+                escape_link_text(&name),
+                link_target(relative)
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// The markdown files and subdirectories of `dir` which should be listed, in
+/// the requested order.
+fn sorted_entries(dir: &Path, is_top_level: bool, sort: AutoSummarySort) -> Result<Vec<PathBuf>> {
+    let mut entries = Vec::new();
+
+    for entry in fs::read_dir(dir).with_context(|| format!("Unable to read {}", dir.display()))? {
+        let path = entry?.path();
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        if file_name.starts_with('.') {
+            continue;
+        }
+
+        let is_listed = if path.is_dir() {
+            contains_markdown(&path)?
+        } else {
+            path.extension().map(|ext| ext == "md").unwrap_or(false)
+                && !(is_top_level && file_name == "SUMMARY.md")
+                && !INDEX_FILES.contains(&file_name.as_str())
+        };
+        if is_listed {
+            entries.push(path);
+        }
+    }
+
+    let mut modified = Vec::with_capacity(entries.len());
+    for path in &entries {
+        let time = match sort {
+            AutoSummarySort::Modified => fs::metadata(path)
+                .and_then(|meta| meta.modified())
+                .with_context(|| format!("Unable to read the timestamp of {}", path.display()))?,
+            _ => SystemTime::UNIX_EPOCH,
+        };
+        modified.push((time, path.clone()));
+    }
+
+    modified.sort_by(|(a_time, a), (b_time, b)| {
+        let (a_name, b_name) = (a.file_name(), b.file_name());
+        let by_name = || match sort {
+            AutoSummarySort::Natural => natural_cmp(
+                &a_name.unwrap_or_default().to_string_lossy(),
+                &b_name.unwrap_or_default().to_string_lossy(),
+            ),
+            _ => a_name.cmp(&b_name),
+        };
+        a_time.cmp(b_time).then_with(by_name)
+    });
+
+    Ok(modified.into_iter().map(|(_, path)| path).collect())
+}
+
+/// The file used as `dir`'s own page, warning when there is more than one
+/// candidate since only the first of them ends up in the book.
+fn index_file(dir: &Path) -> Option<PathBuf> {
+    let mut candidates = INDEX_FILES
+        .iter()
+        .map(|name| dir.join(name))
+        .filter(|index| index.is_file());
+    let index = candidates.next()?;
+    for ignored in candidates {
+        warn!(
+            "{} is ignored because {} is used as the index of {}",
+            ignored.display(),
+            index.display(),
+            dir.display()
+        );
+    }
+    Some(index)
+}
+
+fn contains_markdown(dir: &Path) -> Result<bool> {
+    for entry in fs::read_dir(dir).with_context(|| format!("Unable to read {}", dir.display()))? {
+        let path = entry?.path();
+        let is_markdown = if path.is_dir() {
+            contains_markdown(&path)?
+        } else {
+            path.extension().map(|ext| ext == "md").unwrap_or(false)
+        };
+        if is_markdown {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+/// Compare two strings, treating runs of ASCII digits as numbers.
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a, b);
+
+    loop {
+        let (a_digits, b_digits) = (leading_digits(a), leading_digits(b));
+        if !a_digits.is_empty() && !b_digits.is_empty() {
+            let (a_num, b_num) = (
+                a_digits.trim_start_matches('0'),
+                b_digits.trim_start_matches('0'),
+            );
+            let ordering = a_num
+                .len()
+                .cmp(&b_num.len())
+                .then_with(|| a_num.cmp(b_num))
+                .then_with(|| a_digits.len().cmp(&b_digits.len()));
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+            a = &a[a_digits.len()..];
+            b = &b[b_digits.len()..];
+            continue;
+        }
+
+        let mut a_chars = a.chars();
+        let mut b_chars = b.chars();
+        match (a_chars.next(), b_chars.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x != y => return x.cmp(&y),
+            _ => {
+                a = a_chars.as_str();
+                b = b_chars.as_str();
+            }
+        }
+    }
+}
+
+fn leading_digits(text: &str) -> &str {
+    let end = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    &text[..end]
+}
+
+/// The first level-one heading of a chapter file, if it has one.
+fn chapter_name(path: &Path) -> Result<Option<String>> {
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Unable to read {}", path.display()))?;
+
+    Ok(content
+        .lines()
+        .find_map(|line| line.strip_prefix("# "))
+        .map(|heading| heading.trim().to_string())
+        .filter(|heading| !heading.is_empty()))
+}
+
+/// Escape the characters which would end or nest a `SUMMARY.md` link's text.
+fn escape_link_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c == '[' || c == ']' || c == '\\' {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// A path as a `SUMMARY.md` link target, using `/` separators and angle
+/// brackets for paths with spaces.
+fn link_target(path: &Path) -> String {
+    let target = path
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+    if target.contains(' ') {
+        format!("<{}>", target)
+    } else {
+        target
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::Builder as TempFileBuilder;
+
+    #[test]
+    fn natural_sort_compares_numbers_by_value() {
+        assert_eq!(natural_cmp("2.md", "10.md"), Ordering::Less);
+        assert_eq!(natural_cmp("chapter-10", "chapter-9"), Ordering::Greater);
+        assert_eq!(natural_cmp("a1b2", "a1b10"), Ordering::Less);
+        assert_eq!(natural_cmp("same", "same"), Ordering::Equal);
+        assert_eq!(natural_cmp("01", "1"), Ordering::Greater);
+    }
+
+    #[test]
+    fn generated_summary_follows_the_sort_order() {
+        let temp = TempFileBuilder::new().prefix("book").tempdir().unwrap();
+        let src = temp.path();
+        fs::write(src.join("SUMMARY.md"), "").unwrap();
+        fs::write(src.join("README.md"), "# My Book\n").unwrap();
+        fs::write(src.join("2.md"), "# Two\n").unwrap();
+        fs::write(src.join("10.md"), "Ten, without a heading\n").unwrap();
+        fs::create_dir(src.join("guide")).unwrap();
+        fs::write(src.join("guide/README.md"), "# The Guide\n").unwrap();
+        fs::write(src.join("guide/start here.md"), "# Start\n").unwrap();
+        fs::create_dir(src.join("images")).unwrap();
+        fs::write(src.join("images/logo.png"), "").unwrap();
+
+        let alpha = generate_summary(src, AutoSummarySort::Alpha).unwrap();
+        assert_eq!(
+            alpha,
+            "# Summary\n\n\
+             [My Book](README.md)\n\n\
+             - [10](10.md)\n\
+             - [Two](2.md)\n\
+             - [The Guide](guide/README.md)\n    \
+             - [Start](<guide/start here.md>)\n"
+        );
+
+        let natural = generate_summary(src, AutoSummarySort::Natural).unwrap();
+        assert!(natural.contains("- [Two](2.md)\n- [10](10.md)\n"));
+    }
+}
@@ -1,12 +1,18 @@
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::fmt::{self, Display, Formatter};
 use std::fs::{self, File};
 use std::io::{Read, Write};
+use std::iter::Peekable;
 use std::path::{Path, PathBuf};
 
+use super::auto_summary::generate_summary;
+use super::front_matter::split_front_matter;
+use super::source_map::SourceMap;
 use super::summary::{parse_summary, Link, SectionNumber, Summary, SummaryItem};
-use crate::config::BuildConfig;
+use crate::config::{BuildConfig, EmptyChapter};
 use crate::errors::*;
+use crate::utils::{self, AnchorStyle};
+use pulldown_cmark::{CodeBlockKind, Event, Tag};
 
 /// Load a book into memory from its `src/` directory.
 pub fn load_book<P: AsRef<Path>>(src_dir: P, cfg: &BuildConfig) -> Result<Book> {
@@ -14,9 +20,14 @@ pub fn load_book<P: AsRef<Path>>(src_dir: P, cfg: &BuildConfig) -> Result<Book>
     let summary_md = src_dir.join("SUMMARY.md");
 
     let mut summary_content = String::new();
-    File::open(&summary_md)
-        .with_context(|| format!("Couldn't open SUMMARY.md in {:?} directory", src_dir))?
-        .read_to_string(&mut summary_content)?;
+    if cfg.auto_summary && !summary_md.exists() {
+        debug!("Generating the summary from {}", src_dir.display());
+        summary_content = generate_summary(src_dir, cfg.auto_summary_sort)?;
+    } else {
+        File::open(&summary_md)
+            .with_context(|| format!("Couldn't open SUMMARY.md in {:?} directory", src_dir))?
+            .read_to_string(&mut summary_content)?;
+    }
 
     let summary = parse_summary(&summary_content)
         .with_context(|| format!("Summary parsing failed for file={:?}", summary_md))?;
@@ -25,7 +36,142 @@ pub fn load_book<P: AsRef<Path>>(src_dir: P, cfg: &BuildConfig) -> Result<Book>
         create_missing(&src_dir, &summary).with_context(|| "Unable to create missing chapters")?;
     }
 
-    load_book_from_disk(&summary, src_dir)
+    let mut book = load_book_from_disk(&summary, src_dir)?;
+    if !cfg.include_drafts {
+        remove_draft_chapters(&mut book);
+    }
+    handle_empty_chapters(&mut book, cfg)?;
+
+    Ok(book)
+}
+
+/// Leave draft chapters, and everything nested under them, out of the book
+/// for `build.include-drafts = false`, warning about any chapter with a file
+/// which goes with them.
+pub(crate) fn remove_draft_chapters(book: &mut Book) {
+    warn_about_chapters_under_drafts(&book.sections, None);
+    book.retain_chapters(|ch| !ch.is_draft_chapter());
+}
+
+fn warn_about_chapters_under_drafts(items: &[BookItem], draft: Option<&str>) {
+    for item in items {
+        if let BookItem::Chapter(ch) = item {
+            match draft {
+                Some(draft) if !ch.is_draft_chapter() => warn!(
+                    "\"{}\" is left out of the book because it is nested under the \
+                     draft chapter \"{}\" and `build.include-drafts` is false",
+                    ch.name, draft
+                ),
+                None if ch.is_draft_chapter() => {
+                    warn_about_chapters_under_drafts(&ch.sub_items, Some(&ch.name));
+                    continue;
+                }
+                _ => {}
+            }
+            warn_about_chapters_under_drafts(&ch.sub_items, draft);
+        }
+    }
+}
+
+/// The notice put in empty chapters when `build.empty-chapter` is
+/// `placeholder` and `build.empty-chapter-placeholder` isn't set.
+const DEFAULT_EMPTY_CHAPTER_PLACEHOLDER: &str = "*This page is intentionally empty.*";
+
+/// Deal with chapters whose file is empty (or only whitespace), as
+/// `build.empty-chapter` says.
+pub(crate) fn handle_empty_chapters(book: &mut Book, cfg: &BuildConfig) -> Result<()> {
+    let is_empty = |ch: &Chapter| ch.path.is_some() && ch.content.trim().is_empty();
+
+    match cfg.empty_chapter {
+        EmptyChapter::Blank => {}
+        EmptyChapter::Placeholder => {
+            let placeholder = cfg
+                .empty_chapter_placeholder
+                .as_deref()
+                .unwrap_or(DEFAULT_EMPTY_CHAPTER_PLACEHOLDER);
+            book.for_each_mut(|item| {
+                if let BookItem::Chapter(ch) = item {
+                    if is_empty(ch) {
+                        ch.set_content(format!("{}\n", placeholder));
+                    }
+                }
+            });
+        }
+        EmptyChapter::Error => {
+            for item in book.iter() {
+                if let BookItem::Chapter(ch) = item {
+                    if is_empty(ch) {
+                        let path = ch.source_path.as_ref().or(ch.path.as_ref());
+                        bail!(
+                            "\"{}\" ({}) is empty, which isn't allowed when \
+                             `build.empty-chapter` is \"error\"",
+                            ch.name,
+                            path.expect("checked above").display()
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The path of the page rendered for `chapter`, relative to the output
+/// directory. This is the chapter's path with an `.html` extension, except
+/// that files whose name (without the extension) is in `rewrite_to_dir` become
+/// their directory's `index.html`, the way the `index` preprocessor treats
+/// `README.md`. Names are compared case-insensitively.
+///
+/// Returns `None` for draft chapters, which don't have a page.
+pub fn output_path_for(chapter: &Chapter, rewrite_to_dir: &[String]) -> Option<PathBuf> {
+    let path = chapter.path.as_ref()?;
+    Some(output_path_for_source(path, rewrite_to_dir))
+}
+
+/// [`output_path_for`] a chapter whose source file is `path`, for code which
+/// only has the path, such as the handlebars helpers.
+pub(crate) fn output_path_for_source(path: &Path, rewrite_to_dir: &[String]) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+
+    if rewrite_to_dir
+        .iter()
+        .any(|name| name.eq_ignore_ascii_case(&stem))
+    {
+        path.with_file_name("index.html")
+    } else {
+        path.with_extension("html")
+    }
+}
+
+/// Load each language of a multilingual book from its own subdirectory of
+/// `src/` (e.g. `src/en`, `src/fr`), keyed by language.
+pub fn load_translations<P: AsRef<Path>>(
+    src_dir: P,
+    languages: &[String],
+    cfg: &BuildConfig,
+) -> Result<BTreeMap<String, Book>> {
+    let src_dir = src_dir.as_ref();
+    let mut books = BTreeMap::new();
+
+    for language in languages {
+        let book = load_book(src_dir.join(language), cfg)
+            .with_context(|| format!("Unable to load the \"{}\" translation", language))?;
+        books.insert(language.clone(), book);
+    }
+
+    Ok(books)
+}
+
+/// The URL of a `SUMMARY.md` link which points to another site, rather than
+/// a file in the book.
+fn external_url(link: &Link) -> Option<String> {
+    let location = link.location.as_ref()?.to_string_lossy();
+    if location.starts_with("http://") || location.starts_with("https://") {
+        Some(location.into_owned())
+    } else {
+        None
+    }
 }
 
 fn create_missing(src_dir: &Path, summary: &Summary) -> Result<()> {
@@ -40,7 +186,7 @@ fn create_missing(src_dir: &Path, summary: &Summary) -> Result<()> {
         let next = items.pop().expect("already checked");
 
         if let SummaryItem::Link(ref link) = *next {
-            if let Some(ref location) = link.location {
+            if let (Some(ref location), None) = (&link.location, external_url(link)) {
                 let filename = src_dir.join(location);
                 if !filename.exists() {
                     if let Some(parent) = filename.parent() {
@@ -71,8 +217,16 @@ fn create_missing(src_dir: &Path, summary: &Summary) -> Result<()> {
 /// recursively applying a closure to each section to mutate the chapters, using
 /// [`for_each_mut()`].
 ///
+/// `sections` is in the same order as `SUMMARY.md`: first the prefix
+/// chapters, which have no `number`, then the numbered chapters along with
+/// any part titles and separators, then the suffix chapters, which again have
+/// no `number`. Use [`prepend_item()`] to add a prefix chapter and
+/// [`push_item()`] to add a suffix chapter.
+///
 /// [`iter()`]: #method.iter
 /// [`for_each_mut()`]: #method.for_each_mut
+/// [`prepend_item()`]: #method.prepend_item
+/// [`push_item()`]: #method.push_item
 #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct Book {
     /// The sections in this book.
@@ -108,11 +262,382 @@ impl Book {
         for_each_mut(&mut func, &mut self.sections);
     }
 
-    /// Append a `BookItem` to the `Book`.
+    /// Recursively apply a closure to each chapter in the book, along with
+    /// the items before and after it at the same level, e.g. to generate a
+    /// list of related pages.
+    ///
+    /// Like `for_each_mut()`, a chapter's sub-chapters are visited before the
+    /// chapter itself.
+    pub fn for_each_with_siblings<F>(&mut self, mut func: F)
+    where
+        F: FnMut(&mut Chapter, &[BookItem], &[BookItem]),
+    {
+        for_each_with_siblings(&mut func, &mut self.sections);
+    }
+
+    /// Recursively remove every chapter (along with its sub-chapters) for which
+    /// `predicate` returns `false`, keeping the remaining items in order.
+    ///
+    /// Draft chapters left without any sub-chapters are removed as well, as
+    /// are separators which no longer separate anything.
+    pub fn retain_chapters<F>(&mut self, mut predicate: F)
+    where
+        F: FnMut(&Chapter) -> bool,
+    {
+        retain_chapters(&mut predicate, &mut self.sections);
+    }
+
+    /// Consume the book and build a new one by passing every chapter, at
+    /// every depth, through `func`. Separators, part titles and draft
+    /// chapters keep their place in the tree.
+    ///
+    /// As with `for_each_mut()`, a chapter's sub-chapters are mapped before
+    /// the chapter itself.
+    pub fn map_chapters<F>(self, func: F) -> Book
+    where
+        F: Fn(Chapter) -> Chapter,
+    {
+        Book {
+            sections: map_chapters(&func, self.sections),
+            ..self
+        }
+    }
+
+    /// Append a `BookItem` to the `Book`, after every other item. A chapter
+    /// without a `number` added this way is a suffix chapter.
     pub fn push_item<I: Into<BookItem>>(&mut self, item: I) -> &mut Self {
         self.sections.push(item.into());
         self
     }
+
+    /// Insert a `BookItem` at the start of the `Book`, before every other
+    /// item. A chapter without a `number` added this way is a prefix chapter,
+    /// e.g. a generated cover page.
+    pub fn prepend_item<I: Into<BookItem>>(&mut self, item: I) -> &mut Self {
+        self.sections.insert(0, item.into());
+        self
+    }
+
+    /// Check whether two books have the same shape, i.e. the same items with
+    /// the same names, paths, numbers and nesting, ignoring what each
+    /// chapter's `content` is.
+    ///
+    /// Unlike `==`, this is unaffected by edits to a book's prose, which is
+    /// handy when comparing table of contents layouts.
+    pub fn structurally_eq(&self, other: &Book) -> bool {
+        structurally_eq(&self.sections, &other.sections)
+    }
+
+    /// Compare this book against a newer version of it, listing the chapters
+    /// which were added, removed, renamed, moved or edited.
+    ///
+    /// Chapters are matched up by their `path`, so draft chapters (which don't
+    /// have one) are ignored.
+    pub fn diff(&self, other: &Book) -> BookDiff {
+        let old = chapter_locations(&self.sections);
+        let new = chapter_locations(&other.sections);
+
+        let mut diff = BookDiff::default();
+
+        for (path, _) in &old {
+            if !new.iter().any(|(p, _)| p == path) {
+                diff.removed.push(path.to_path_buf());
+            }
+        }
+
+        for (path, new_location) in &new {
+            let old_location = match old.iter().find(|(p, _)| p == path) {
+                Some((_, location)) => location,
+                None => {
+                    diff.added.push(path.to_path_buf());
+                    continue;
+                }
+            };
+
+            if old_location.chapter.name != new_location.chapter.name {
+                diff.renamed.push((
+                    path.to_path_buf(),
+                    old_location.chapter.name.clone(),
+                    new_location.chapter.name.clone(),
+                ));
+            }
+            if old_location.parent != new_location.parent {
+                diff.moved.push(path.to_path_buf());
+            }
+            diff.content_changed.push((
+                path.to_path_buf(),
+                old_location.chapter.content != new_location.chapter.content,
+            ));
+        }
+
+        diff
+    }
+
+    /// The chapters above `chapter` in the book's hierarchy, starting with
+    /// the top-level one, e.g. for breadcrumb navigation.
+    ///
+    /// The ancestors are found by following the chapter's `parent_names`
+    /// down from the top of the book. If several sibling chapters share a
+    /// name, the one which actually contains `chapter` is used. An empty
+    /// `Vec` is returned for top-level chapters and for chapters which
+    /// aren't part of this book.
+    pub fn breadcrumbs(&self, chapter: &Chapter) -> Vec<&Chapter> {
+        let mut ancestors = Vec::new();
+        if find_ancestors(&self.sections, chapter, &mut ancestors) {
+            ancestors
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Check that every chapter's `parent_names` match the chapters it is
+    /// actually nested under.
+    ///
+    /// Code which walks up the tree using `parent_names` (e.g.
+    /// [`Book::breadcrumbs()`]) relies on this, so tools which move chapters
+    /// around should call it before handing the book on.
+    pub fn validate_hierarchy(&self) -> Result<()> {
+        validate_hierarchy(&self.sections, &mut Vec::new())
+    }
+
+    /// Give every chapter a `slug_path` built from its name and the names of
+    /// the chapters above it, e.g. `getting-started/installation.md`, for
+    /// output schemes which shouldn't depend on the source file names.
+    ///
+    /// When two chapters would end up with the same slug path, the later one
+    /// gets a `-1`, `-2`, etc. suffix.
+    pub fn assign_slugs(&mut self) {
+        let mut used = HashSet::new();
+        assign_slugs(&mut self.sections, Path::new(""), &mut used);
+    }
+
+    /// Reorder the chapters at every level of the book by their `weight`,
+    /// lowest first.
+    ///
+    /// Chapters without a weight come after the weighted ones, and chapters
+    /// with the same weight keep their relative order. Separators and part
+    /// titles stay where they are, so only chapters between them are
+    /// reordered.
+    pub fn sort_by_weight(&mut self) {
+        sort_by_weight(&mut self.sections);
+    }
+
+    /// Write a summary of each chapter as newline-delimited JSON, one object
+    /// per line in reading order, so tools can process a book's chapters one
+    /// at a time.
+    ///
+    /// Each object has the chapter's `name`, `path` (`null` for draft
+    /// chapters), `depth` (`0` for top-level chapters) and whether it
+    /// `has_children`.
+    pub fn write_ndjson<W: Write>(&self, mut writer: W) -> Result<()> {
+        write_ndjson(&self.sections, 0, &mut writer)
+    }
+}
+
+/// Search `items` for `target`, only descending into chapters named by its
+/// `parent_names`, and leaving the chapters along the way in `ancestors`.
+fn find_ancestors<'a>(
+    items: &'a [BookItem],
+    target: &Chapter,
+    ancestors: &mut Vec<&'a Chapter>,
+) -> bool {
+    let depth = ancestors.len();
+
+    for item in items {
+        let ch = match item {
+            BookItem::Chapter(ch) => ch,
+            _ => continue,
+        };
+
+        let is_target = std::ptr::eq(ch, target)
+            || match target.path {
+                Some(ref path) => ch.path.as_ref() == Some(path),
+                None => ch.name == target.name && ch.parent_names == target.parent_names,
+            };
+        if is_target && depth == target.parent_names.len() {
+            return true;
+        }
+
+        if target.parent_names.get(depth) == Some(&ch.name) {
+            ancestors.push(ch);
+            if find_ancestors(&ch.sub_items, target, ancestors) {
+                return true;
+            }
+            ancestors.pop();
+        }
+    }
+
+    false
+}
+
+fn validate_hierarchy<'a>(items: &'a [BookItem], ancestors: &mut Vec<&'a str>) -> Result<()> {
+    for item in items {
+        let ch = match item {
+            BookItem::Chapter(ch) => ch,
+            _ => continue,
+        };
+
+        if ch.parent_names.iter().any(|name| name == &ch.name) && !ancestors.contains(&&*ch.name) {
+            bail!(
+                "The chapter \"{}\" lists itself as one of its parents",
+                ch.name
+            );
+        }
+        if ch
+            .parent_names
+            .iter()
+            .map(String::as_str)
+            .ne(ancestors.iter().copied())
+        {
+            bail!(
+                "The chapter \"{}\" has the parent names {:?}, but is nested under {:?}",
+                ch.name,
+                ch.parent_names,
+                ancestors
+            );
+        }
+
+        ancestors.push(&ch.name);
+        validate_hierarchy(&ch.sub_items, ancestors)?;
+        ancestors.pop();
+    }
+
+    Ok(())
+}
+
+fn assign_slugs(items: &mut [BookItem], parent_dir: &Path, used: &mut HashSet<PathBuf>) {
+    for item in items {
+        let ch = match item {
+            BookItem::Chapter(ch) => ch,
+            _ => continue,
+        };
+
+        let mut slug = utils::normalize_id(&ch.display_name());
+        if slug.is_empty() {
+            slug = String::from("chapter");
+        }
+
+        let mut unique_slug = slug.clone();
+        let mut counter = 0;
+        while !used.insert(parent_dir.join(format!("{}.md", unique_slug))) {
+            counter += 1;
+            unique_slug = format!("{}-{}", slug, counter);
+        }
+
+        ch.slug_path = parent_dir.join(format!("{}.md", unique_slug));
+        assign_slugs(&mut ch.sub_items, &parent_dir.join(&unique_slug), used);
+    }
+}
+
+fn sort_by_weight(items: &mut [BookItem]) {
+    for run in items.split_mut(|item| !matches!(item, BookItem::Chapter(_))) {
+        run.sort_by_key(|item| match item {
+            BookItem::Chapter(ch) => (ch.weight.is_none(), ch.weight),
+            _ => unreachable!("runs only contain chapters"),
+        });
+    }
+
+    for item in items {
+        if let BookItem::Chapter(ch) = item {
+            sort_by_weight(&mut ch.sub_items);
+        }
+    }
+}
+
+fn write_ndjson<W: Write>(items: &[BookItem], depth: usize, writer: &mut W) -> Result<()> {
+    for item in items {
+        let ch = match item {
+            BookItem::Chapter(ch) => ch,
+            _ => continue,
+        };
+
+        let record = ChapterRecord {
+            name: &ch.name,
+            path: ch.path.as_deref(),
+            depth,
+            has_children: ch
+                .sub_items
+                .iter()
+                .any(|item| matches!(item, BookItem::Chapter(_))),
+        };
+        serde_json::to_writer(&mut *writer, &record)?;
+        writer.write_all(b"\n")?;
+
+        write_ndjson(&ch.sub_items, depth + 1, writer)?;
+    }
+
+    Ok(())
+}
+
+/// A chapter as written by [`Book::write_ndjson()`].
+#[derive(Serialize)]
+struct ChapterRecord<'a> {
+    name: &'a str,
+    path: Option<&'a Path>,
+    depth: usize,
+    has_children: bool,
+}
+
+/// The structural changes between two versions of a [`Book`], as returned by
+/// [`Book::diff()`].
+///
+/// Every chapter is identified by its `path`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BookDiff {
+    /// Chapters which only exist in the new book.
+    pub added: Vec<PathBuf>,
+    /// Chapters which only exist in the old book.
+    pub removed: Vec<PathBuf>,
+    /// Chapters whose name changed, along with the old and new names.
+    pub renamed: Vec<(PathBuf, String, String)>,
+    /// Chapters which now have a different parent chapter.
+    pub moved: Vec<PathBuf>,
+    /// Every chapter in both books, and whether its content changed.
+    pub content_changed: Vec<(PathBuf, bool)>,
+}
+
+impl BookDiff {
+    /// Are the two books structurally the same, with no edited chapters?
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.renamed.is_empty()
+            && self.moved.is_empty()
+            && self.content_changed.iter().all(|(_, changed)| !changed)
+    }
+}
+
+/// A non-draft chapter, along with the path of the chapter it is nested under.
+struct ChapterLocation<'a> {
+    chapter: &'a Chapter,
+    parent: Option<&'a Path>,
+}
+
+fn chapter_locations(items: &[BookItem]) -> Vec<(&Path, ChapterLocation<'_>)> {
+    fn walk<'a>(
+        items: &'a [BookItem],
+        parent: Option<&'a Path>,
+        locations: &mut Vec<(&'a Path, ChapterLocation<'a>)>,
+    ) {
+        for item in items {
+            if let BookItem::Chapter(ch) = item {
+                if let Some(path) = ch.path.as_deref() {
+                    locations.push((
+                        path,
+                        ChapterLocation {
+                            chapter: ch,
+                            parent,
+                        },
+                    ));
+                }
+                walk(&ch.sub_items, ch.path.as_deref().or(parent), locations);
+            }
+        }
+    }
+
+    let mut locations = Vec::new();
+    walk(items, None, &mut locations);
+    locations
 }
 
 pub fn for_each_mut<'a, F, I>(func: &mut F, items: I)
@@ -129,7 +654,132 @@ where
     }
 }
 
+fn for_each_with_siblings<F>(func: &mut F, items: &mut [BookItem])
+where
+    F: FnMut(&mut Chapter, &[BookItem], &[BookItem]),
+{
+    for i in 0..items.len() {
+        let (before, rest) = items.split_at_mut(i);
+        let (item, after) = rest.split_first_mut().expect("i is in bounds");
+
+        if let BookItem::Chapter(ch) = item {
+            for_each_with_siblings(func, &mut ch.sub_items);
+            func(ch, before, after);
+        }
+    }
+}
+
+fn map_chapters<F>(func: &F, items: Vec<BookItem>) -> Vec<BookItem>
+where
+    F: Fn(Chapter) -> Chapter,
+{
+    items
+        .into_iter()
+        .map(|item| match item {
+            BookItem::Chapter(mut ch) => {
+                ch.sub_items = map_chapters(func, std::mem::take(&mut ch.sub_items));
+                BookItem::Chapter(func(ch))
+            }
+            other => other,
+        })
+        .collect()
+}
+
+fn structurally_eq(left: &[BookItem], right: &[BookItem]) -> bool {
+    left.len() == right.len()
+        && left.iter().zip(right).all(|pair| match pair {
+            (BookItem::Chapter(a), BookItem::Chapter(b)) => {
+                // Destructured so that new fields aren't forgotten here.
+                let Chapter {
+                    name,
+                    content: _,
+                    number,
+                    sub_items,
+                    path,
+                    source_path,
+                    parent_names,
+                    meta,
+                    slug_path,
+                    weight,
+                    external_url,
+                    source_map: _,
+                    __non_exhaustive: (),
+                } = a;
+
+                *name == b.name
+                    && *number == b.number
+                    && *path == b.path
+                    && *source_path == b.source_path
+                    && *parent_names == b.parent_names
+                    && same_meta(meta, &b.meta)
+                    && *slug_path == b.slug_path
+                    && *weight == b.weight
+                    && *external_url == b.external_url
+                    && structurally_eq(sub_items, &b.sub_items)
+            }
+            (a, b) => a == b,
+        })
+}
+
+/// Whether two chapters' front matter gives the same settings, however many
+/// lines it took.
+fn same_meta(a: &ChapterMeta, b: &ChapterMeta) -> bool {
+    let ChapterMeta {
+        no_index,
+        template,
+        description,
+        classes,
+        no_playground,
+        front_matter_lines: _,
+        __non_exhaustive: (),
+    } = a;
+
+    *no_index == b.no_index
+        && *template == b.template
+        && *description == b.description
+        && *classes == b.classes
+        && *no_playground == b.no_playground
+}
+
+fn retain_chapters<F>(predicate: &mut F, items: &mut Vec<BookItem>)
+where
+    F: FnMut(&Chapter) -> bool,
+{
+    let mut kept: Vec<BookItem> = Vec::with_capacity(items.len());
+
+    for item in items.drain(..) {
+        match item {
+            BookItem::Chapter(mut ch) => {
+                if !predicate(&ch) {
+                    continue;
+                }
+
+                let had_sub_items = !ch.sub_items.is_empty();
+                retain_chapters(predicate, &mut ch.sub_items);
+                if ch.is_draft_chapter() && had_sub_items && ch.sub_items.is_empty() {
+                    continue;
+                }
+
+                kept.push(BookItem::Chapter(ch));
+            }
+            BookItem::Separator => match kept.last() {
+                None | Some(BookItem::Separator) => {}
+                Some(_) => kept.push(BookItem::Separator),
+            },
+            other => kept.push(other),
+        }
+    }
+
+    if let Some(BookItem::Separator) = kept.last() {
+        kept.pop();
+    }
+
+    *items = kept;
+}
+
 /// Enum representing any type of item which can be added to a book.
+// Boxing the `Chapter` would break every preprocessor which matches on it.
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum BookItem {
     /// A nested chapter.
@@ -164,6 +814,60 @@ pub struct Chapter {
     pub source_path: Option<PathBuf>,
     /// An ordered list of the names of each chapter above this one in the hierarchy.
     pub parent_names: Vec<String>,
+    /// Settings from the chapter's front matter.
+    #[serde(default)]
+    pub meta: ChapterMeta,
+    /// A path derived from the names of the chapter and its parents, relative
+    /// to the `SUMMARY.md` file. This is empty until
+    /// [`Book::assign_slugs()`] is called.
+    #[serde(default)]
+    pub slug_path: PathBuf,
+    /// A sort key used by [`Book::sort_by_weight()`], for tools which build
+    /// books programmatically. Lower weights come first.
+    #[serde(default)]
+    pub weight: Option<i32>,
+    /// For a `SUMMARY.md` entry which links to another site, the URL it
+    /// links to. Such chapters have no `path` or content.
+    #[serde(default)]
+    pub external_url: Option<String>,
+    /// Where the lines of `content` came from, if the transforms which
+    /// changed it kept track. See [`SourceMapBuilder`].
+    ///
+    /// [`SourceMapBuilder`]: struct.SourceMapBuilder.html
+    #[serde(default)]
+    pub source_map: Option<SourceMap>,
+    #[serde(skip)]
+    __non_exhaustive: (),
+}
+
+/// The settings a chapter's source file gives in its front matter, as used by
+/// the HTML renderer.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ChapterMeta {
+    /// Whether search engines should be asked not to index this chapter.
+    #[serde(default)]
+    pub no_index: bool,
+    /// The name of the theme template to render this chapter with, instead of
+    /// the default `index.hbs`.
+    #[serde(default)]
+    pub template: Option<String>,
+    /// A short summary of the chapter, used for link previews.
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Extra CSS classes for the element wrapping the chapter's rendered
+    /// content, from the `class` setting.
+    #[serde(default)]
+    pub classes: Vec<String>,
+    /// Whether the HTML renderer leaves out the playground for this chapter's
+    /// Rust code blocks, from the `playground` setting.
+    #[serde(default)]
+    pub no_playground: bool,
+    /// How many lines of front matter were taken off the top of the source
+    /// file, so the chapter's `content` starts at the line after them.
+    #[serde(default)]
+    pub front_matter_lines: usize,
+    #[serde(skip)]
+    __non_exhaustive: (),
 }
 
 impl Chapter {
@@ -198,61 +902,376 @@ impl Chapter {
         }
     }
 
-    /// Check if the chapter is a draft chapter, meaning it has no path to a source markdown file.
+    /// Create a chapter which links to another site instead of a page of the
+    /// book.
+    pub fn new_external(name: &str, url: String, parent_names: Vec<String>) -> Self {
+        Chapter {
+            name: name.to_string(),
+            external_url: Some(url),
+            parent_names,
+            ..Default::default()
+        }
+    }
+
+    /// Check if the chapter is a draft chapter, meaning it has no path to a
+    /// source markdown file and doesn't link to another site either.
     pub fn is_draft_chapter(&self) -> bool {
-        self.path.is_none()
+        self.path.is_none() && self.external_url.is_none()
     }
-}
 
-/// Use the provided `Summary` to load a `Book` from disk.
-///
-/// You need to pass in the book's source directory because all the links in
-/// `SUMMARY.md` give the chapter locations relative to it.
-pub(crate) fn load_book_from_disk<P: AsRef<Path>>(summary: &Summary, src_dir: P) -> Result<Book> {
-    debug!("Loading the book from disk");
-    let src_dir = src_dir.as_ref();
+    /// The chapter's name as plain text, without any inline markdown such as
+    /// code spans or emphasis.
+    pub fn display_name(&self) -> String {
+        utils::strip_inline_markdown(&self.name)
+    }
 
-    let prefix = summary.prefix_chapters.iter();
-    let numbered = summary.numbered_chapters.iter();
-    let suffix = summary.suffix_chapters.iter();
+    /// The relative link from this chapter's rendered page to `other`'s, e.g.
+    /// `../c.html` from `a/b.md` to `c.md`. Pages are named the way
+    /// [`output_path_for`] names them, so `index_file_names` (see
+    /// `Config::index_file_names`) become `index.html`.
+    ///
+    /// This is an `Option` rather than a `String` because there is nothing to
+    /// link to, or from, if either chapter is a draft: drafts don't have a
+    /// page. It is never `None` for two chapters with a `path`.
+    pub fn relative_link_to(&self, other: &Chapter, index_file_names: &[String]) -> Option<String> {
+        let from = output_path_for(self, index_file_names)?;
+        let to = output_path_for(other, index_file_names)?;
+
+        let from_dirs: Vec<_> = from.parent()?.components().collect();
+        let to_components: Vec<_> = to.components().collect();
+        let (to_file, to_dirs) = to_components.split_last()?;
+        let common = from_dirs
+            .iter()
+            .zip(to_dirs)
+            .take_while(|(a, b)| a == b)
+            .count();
+
+        let mut link = "../".repeat(from_dirs.len() - common);
+        for component in to_dirs[common..].iter().chain(Some(to_file)) {
+            link.push_str(&component.as_os_str().to_string_lossy());
+            link.push('/');
+        }
+        link.pop();
+        Some(link)
+    }
 
-    let summary_items = prefix.chain(numbered).chain(suffix);
+    /// Replace the chapter's contents.
+    ///
+    /// Preprocessors should prefer this over assigning to `content` directly,
+    /// so anything derived from the contents is kept up to date.
+    pub fn set_content(&mut self, content: String) {
+        self.content = content;
+    }
 
-    let mut chapters = Vec::new();
+    /// The number of words in the chapter's text, ignoring markdown syntax.
+    pub fn word_count(&self) -> usize {
+        self.plain_text().split_whitespace().count()
+    }
 
-    for summary_item in summary_items {
-        let chapter = load_summary_item(summary_item, src_dir, Vec::new())?;
-        chapters.push(chapter);
+    /// The chapter's text without any markdown syntax or HTML, with each
+    /// paragraph, heading, list item, etc. on its own line.
+    pub fn plain_text(&self) -> String {
+        let mut text = String::with_capacity(self.content.len());
+        for event in utils::new_cmark_parser(&self.content) {
+            match event {
+                Event::Text(t) | Event::Code(t) => text.push_str(&t),
+                Event::SoftBreak | Event::End(Tag::TableCell) => text.push(' '),
+                Event::HardBreak
+                | Event::End(Tag::Paragraph)
+                | Event::End(Tag::Heading(_))
+                | Event::End(Tag::Item)
+                | Event::End(Tag::CodeBlock(_))
+                | Event::End(Tag::TableHead)
+                | Event::End(Tag::TableRow)
+                    if !text.ends_with('\n') =>
+                {
+                    text.push('\n')
+                }
+                _ => {}
+            }
+        }
+        text.trim_end().to_string()
     }
 
-    Ok(Book {
-        sections: chapters,
-        __non_exhaustive: (),
-    })
-}
+    /// The chapter's headings, in document order, down to (and including)
+    /// `max_level`. Each heading gets the same `id` the HTML renderer gives
+    /// its anchor.
+    pub fn headings(&self, max_level: u8) -> Vec<Heading> {
+        self.headings_with_style(max_level, AnchorStyle::default())
+    }
 
-fn load_summary_item<P: AsRef<Path> + Clone>(
-    item: &SummaryItem,
-    src_dir: P,
-    parent_names: Vec<String>,
-) -> Result<BookItem> {
-    match item {
-        SummaryItem::Separator => Ok(BookItem::Separator),
-        SummaryItem::Link(ref link) => {
-            load_chapter(link, src_dir, parent_names).map(BookItem::Chapter)
+    /// Like [`Chapter::headings`], for books which configure a different
+    /// [`AnchorStyle`].
+    pub fn headings_with_style(&self, max_level: u8, style: AnchorStyle) -> Vec<Heading> {
+        let mut headings = Vec::new();
+        let mut id_counter = HashMap::new();
+        let mut current: Option<(u32, String)> = None;
+
+        for event in utils::new_cmark_parser(&self.content) {
+            match event {
+                Event::Start(Tag::Heading(level)) => current = Some((level, String::new())),
+                Event::Text(t) | Event::Code(t) => {
+                    if let Some((_, ref mut text)) = current {
+                        text.push_str(&t);
+                    }
+                }
+                Event::End(Tag::Heading(_)) => {
+                    let (level, text) = match current.take() {
+                        Some(heading) => heading,
+                        None => continue,
+                    };
+                    let raw_id = utils::normalize_id_with_style(text.trim(), style);
+                    let count = id_counter.entry(raw_id.clone()).or_insert(0);
+                    let id = match *count {
+                        0 => raw_id,
+                        other => format!("{}-{}", raw_id, other),
+                    };
+                    *count += 1;
+
+                    if level <= u32::from(max_level) {
+                        headings.push(Heading { level, text, id });
+                    }
+                }
+                _ => {}
+            }
         }
-        SummaryItem::PartTitle(title) => Ok(BookItem::PartTitle(title.clone())),
+
+        headings
     }
-}
 
-fn load_chapter<P: AsRef<Path>>(
-    link: &Link,
+    /// The chapter's headings, down to (and including) `max_level`, nested
+    /// under the headings they belong to, e.g. for an outline of the page.
+    ///
+    /// A heading becomes a child of the closest heading before it with a
+    /// lower level, so a `####` straight after a `##` is still nested under
+    /// it.
+    pub fn headings_tree(&self, max_level: u8) -> Vec<HeadingNode> {
+        let mut headings = self.headings(max_level).into_iter().peekable();
+        HeadingNode::nest(&mut headings, 0)
+    }
+
+    /// The chapter's fenced code blocks, in document order, e.g. for tools
+    /// which test the code samples in a book. Indented code blocks aren't
+    /// included.
+    pub fn code_blocks(&self) -> Vec<CodeBlock> {
+        let mut blocks = Vec::new();
+        let mut current: Option<CodeBlock> = None;
+
+        for event in utils::new_cmark_parser(&self.content) {
+            match event {
+                Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info))) => {
+                    let language = info
+                        .split(|c: char| c == ',' || c.is_whitespace())
+                        .next()
+                        .filter(|lang| !lang.is_empty())
+                        .map(String::from);
+                    current = Some(CodeBlock {
+                        language,
+                        code: String::new(),
+                    });
+                }
+                Event::Text(text) => {
+                    if let Some(ref mut block) = current {
+                        block.code.push_str(&text);
+                    }
+                }
+                Event::End(Tag::CodeBlock(_)) => blocks.extend(current.take()),
+                _ => {}
+            }
+        }
+
+        blocks
+    }
+}
+
+/// A fenced code block inside a chapter, as returned by
+/// [`Chapter::code_blocks`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CodeBlock {
+    /// The first word of the fence's info string, e.g. `rust` for
+    /// `` ```rust,ignore ``, or `None` if it doesn't have one.
+    pub language: Option<String>,
+    /// The block's contents, exactly as written (including any lines which
+    /// are hidden when rendered).
+    pub code: String,
+}
+
+/// A heading inside a chapter, as returned by [`Chapter::headings`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Heading {
+    /// The heading's level, from 1 (`#`) to 6 (`######`).
+    pub level: u32,
+    /// The heading's text, without any markdown syntax.
+    pub text: String,
+    /// The `id` of the heading's anchor on the rendered page.
+    pub id: String,
+}
+
+/// A heading and the headings nested under it, as returned by
+/// [`Chapter::headings_tree`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HeadingNode {
+    /// The heading's level, from 1 (`#`) to 6 (`######`).
+    pub level: u32,
+    /// The heading's text, without any markdown syntax.
+    pub text: String,
+    /// The `id` of the heading's anchor on the rendered page.
+    pub id: String,
+    /// The headings between this one and the next heading at the same or a
+    /// lower level.
+    pub children: Vec<HeadingNode>,
+}
+
+impl HeadingNode {
+    /// Take the headings deeper than `parent_level` off the front of
+    /// `headings`, nesting them as they go.
+    fn nest<I>(headings: &mut Peekable<I>, parent_level: u32) -> Vec<HeadingNode>
+    where
+        I: Iterator<Item = Heading>,
+    {
+        let mut nodes = Vec::new();
+        while headings.peek().map(|h| h.level > parent_level) == Some(true) {
+            let heading = headings.next().unwrap();
+            let children = HeadingNode::nest(headings, heading.level);
+            nodes.push(HeadingNode {
+                level: heading.level,
+                text: heading.text,
+                id: heading.id,
+                children,
+            });
+        }
+        nodes
+    }
+}
+
+/// Use the provided `Summary` to load a `Book` from disk.
+///
+/// You need to pass in the book's source directory because all the links in
+/// `SUMMARY.md` give the chapter locations relative to it.
+pub(crate) fn load_book_from_disk<P: AsRef<Path>>(summary: &Summary, src_dir: P) -> Result<Book> {
+    load_book_collect_errors(summary, src_dir).map_err(Error::from)
+}
+
+/// Like [`load_book()`], but using an already parsed `Summary`, and trying
+/// to load every chapter instead of stopping at the first one which fails.
+///
+/// If any chapters couldn't be loaded, the error lists all of them, and has
+/// the rest of the book in [`BookLoadErrors::partial_book`].
+pub fn load_book_collect_errors<P: AsRef<Path>>(
+    summary: &Summary,
     src_dir: P,
-    parent_names: Vec<String>,
-) -> Result<Chapter> {
+) -> std::result::Result<Book, BookLoadErrors> {
+    debug!("Loading the book from disk");
     let src_dir = src_dir.as_ref();
 
-    let mut ch = if let Some(ref link_location) = link.location {
+    let prefix = summary.prefix_chapters.iter();
+    let numbered = summary.numbered_chapters.iter();
+    let suffix = summary.suffix_chapters.iter();
+
+    let summary_items = prefix.chain(numbered).chain(suffix);
+
+    let mut errors = Vec::new();
+    let chapters = summary_items
+        .filter_map(|item| load_summary_item(item, src_dir, Vec::new(), &mut errors))
+        .collect();
+
+    let book = Book {
+        sections: chapters,
+        __non_exhaustive: (),
+    };
+    if errors.is_empty() {
+        Ok(book)
+    } else {
+        Err(BookLoadErrors {
+            partial_book: book,
+            errors,
+        })
+    }
+}
+
+/// The error from [`load_book_collect_errors()`] when some of the chapters
+/// couldn't be loaded.
+#[derive(Debug)]
+pub struct BookLoadErrors {
+    /// The chapters which were loaded. A chapter which couldn't be loaded is
+    /// left out, along with everything nested under it.
+    pub partial_book: Book,
+    /// Why each of the chapters which couldn't be loaded failed, in the order
+    /// they appear in `SUMMARY.md`.
+    pub errors: Vec<Error>,
+}
+
+impl Display for BookLoadErrors {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.errors.as_slice() {
+            [error] => write!(f, "{:#}", error),
+            errors => {
+                write!(f, "{} chapters couldn't be loaded:", errors.len())?;
+                for error in errors {
+                    write!(f, "\n- {:#}", error)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for BookLoadErrors {}
+
+/// Load a `SUMMARY.md` entry, adding the reason to `errors` if it (or
+/// anything nested under it) couldn't be loaded.
+fn load_summary_item(
+    item: &SummaryItem,
+    src_dir: &Path,
+    parent_names: Vec<String>,
+    errors: &mut Vec<Error>,
+) -> Option<BookItem> {
+    match item {
+        SummaryItem::Separator => Some(BookItem::Separator),
+        SummaryItem::Link(ref link) => {
+            load_chapter(link, src_dir, parent_names, errors).map(BookItem::Chapter)
+        }
+        SummaryItem::PartTitle(title) => Some(BookItem::PartTitle(title.clone())),
+    }
+}
+
+/// Load a chapter and everything nested under it. The nested chapters are
+/// still tried if the chapter itself can't be loaded, so their errors are
+/// reported too.
+fn load_chapter(
+    link: &Link,
+    src_dir: &Path,
+    parent_names: Vec<String>,
+    errors: &mut Vec<Error>,
+) -> Option<Chapter> {
+    let ch = match read_chapter(link, src_dir, parent_names.clone()) {
+        Ok(ch) => Some(ch),
+        Err(e) => {
+            errors.push(e);
+            None
+        }
+    };
+
+    let mut sub_item_parents = parent_names;
+    sub_item_parents.push(link.name.clone());
+    let sub_items = link
+        .nested_items
+        .iter()
+        .filter_map(|i| load_summary_item(i, src_dir, sub_item_parents.clone(), errors))
+        .collect();
+
+    ch.map(|mut ch| {
+        ch.sub_items = sub_items;
+        ch
+    })
+}
+
+/// Load a chapter's own file, without anything nested under it.
+fn read_chapter(link: &Link, src_dir: &Path, parent_names: Vec<String>) -> Result<Chapter> {
+    let mut ch = if let Some(url) = external_url(link) {
+        debug!("Linking {} to {}", link.name, url);
+        Chapter::new_external(&link.name, url, parent_names)
+    } else if let Some(ref link_location) = link.location {
         debug!("Loading {} ({})", link.name, link_location.display());
 
         let location = if link_location.is_absolute() {
@@ -264,37 +1283,45 @@ fn load_chapter<P: AsRef<Path>>(
         let mut f = File::open(&location)
             .with_context(|| format!("Chapter file not found, {}", link_location.display()))?;
 
-        let mut content = String::new();
-        f.read_to_string(&mut content).with_context(|| {
+        let mut bytes = Vec::new();
+        f.read_to_end(&mut bytes).with_context(|| {
             format!("Unable to read \"{}\" ({})", link.name, location.display())
         })?;
 
+        let mut content = String::from_utf8(bytes).map_err(|e| {
+            anyhow::anyhow!(
+                "\"{}\" ({}) is not valid UTF-8 (invalid byte at offset {}), \
+                 mdBook requires chapters to be UTF-8 encoded",
+                link.name,
+                location.display(),
+                e.utf8_error().valid_up_to()
+            )
+        })?;
+
         if content.as_bytes().starts_with(b"\xef\xbb\xbf") {
             content.replace_range(..3, "");
         }
 
+        let (front_matter, body) = split_front_matter(&content).with_context(|| {
+            format!("Unable to load \"{}\" ({})", link.name, location.display())
+        })?;
+        let front_matter = front_matter.unwrap_or_default();
+        let front_matter_lines = content[..content.len() - body.len()].matches('\n').count();
+        let content = body.to_string();
+
         let stripped = location
             .strip_prefix(&src_dir)
             .expect("Chapters are always inside a book");
 
-        Chapter::new(&link.name, content, stripped, parent_names.clone())
+        let mut ch = Chapter::new(&link.name, content, stripped, parent_names);
+        front_matter.apply(&mut ch);
+        ch.meta.front_matter_lines = front_matter_lines;
+        ch
     } else {
-        Chapter::new_draft(&link.name, parent_names.clone())
+        Chapter::new_draft(&link.name, parent_names)
     };
-
-    let mut sub_item_parents = parent_names;
-
     ch.number = link.number.clone();
 
-    sub_item_parents.push(link.name.clone());
-    let sub_items = link
-        .nested_items
-        .iter()
-        .map(|i| load_summary_item(i, src_dir, sub_item_parents.clone()))
-        .collect::<Result<Vec<_>>>()?;
-
-    ch.sub_items = sub_items;
-
     Ok(ch)
 }
 
@@ -338,6 +1365,7 @@ impl Display for Chapter {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::diagnostics;
     use std::io::Write;
     use tempfile::{Builder as TempFileBuilder, TempDir};
 
@@ -386,6 +1414,42 @@ And here is some \
         (root, temp_dir)
     }
 
+    /// An empty chapter at `path`.
+    fn chapter_at(path: &str) -> Chapter {
+        Chapter::new("Chapter", String::new(), path, vec![])
+    }
+
+    /// An empty chapter called `name`, in `<name>.md`.
+    fn named_chapter(name: &str) -> Chapter {
+        Chapter::new(name, String::new(), format!("{}.md", name), vec![])
+    }
+
+    /// A chapter called `name`, in `<name>.md`, containing `sub_items`.
+    fn chapter_item(name: &str, sub_items: Vec<BookItem>) -> BookItem {
+        let mut ch = named_chapter(name);
+        ch.sub_items = sub_items;
+        BookItem::Chapter(ch)
+    }
+
+    /// A draft chapter called `name`, containing `sub_items`.
+    fn draft_item(name: &str, sub_items: Vec<BookItem>) -> BookItem {
+        let mut ch = Chapter::new_draft(name, vec![]);
+        ch.sub_items = sub_items;
+        BookItem::Chapter(ch)
+    }
+
+    /// The names of the chapters in `items`, with any other item shown as
+    /// its `Debug` output.
+    fn item_names<'a, I: IntoIterator<Item = &'a BookItem>>(items: I) -> Vec<String> {
+        items
+            .into_iter()
+            .map(|item| match item {
+                BookItem::Chapter(ch) => ch.name.clone(),
+                other => format!("{:?}", other),
+            })
+            .collect()
+    }
+
     #[test]
     fn load_a_single_chapter_from_disk() {
         let (link, temp_dir) = dummy_link();
@@ -396,7 +1460,7 @@ And here is some \
             Vec::new(),
         );
 
-        let got = load_chapter(&link, temp_dir.path(), Vec::new()).unwrap();
+        let got = load_chapter(&link, temp_dir.path(), Vec::new(), &mut Vec::new()).unwrap();
         assert_eq!(got, should_be);
     }
 
@@ -419,16 +1483,124 @@ And here is some \
             Vec::new(),
         );
 
-        let got = load_chapter(&link, temp_dir.path(), Vec::new()).unwrap();
+        let got = load_chapter(&link, temp_dir.path(), Vec::new(), &mut Vec::new()).unwrap();
         assert_eq!(got, should_be);
     }
 
+    #[test]
+    fn load_a_single_chapter_with_front_matter() {
+        let temp_dir = TempFileBuilder::new().prefix("book").tempdir().unwrap();
+
+        let chapter_path = temp_dir.path().join("chapter_1.md");
+        File::create(&chapter_path)
+            .unwrap()
+            .write_all(("+++\nno-index = true\n+++\n".to_owned() + DUMMY_SRC).as_bytes())
+            .unwrap();
+
+        let link = Link::new("Chapter 1", chapter_path);
+
+        let mut should_be = Chapter::new(
+            "Chapter 1",
+            DUMMY_SRC.to_string(),
+            "chapter_1.md",
+            Vec::new(),
+        );
+        should_be.meta.no_index = true;
+        should_be.meta.front_matter_lines = 3;
+
+        let got = load_chapter(&link, temp_dir.path(), Vec::new(), &mut Vec::new()).unwrap();
+        assert_eq!(got, should_be);
+    }
+
+    #[test]
+    fn loading_a_non_utf8_chapter_is_an_encoding_error() {
+        let temp_dir = TempFileBuilder::new().prefix("book").tempdir().unwrap();
+
+        let chapter_path = temp_dir.path().join("chapter_1.md");
+        File::create(&chapter_path)
+            .unwrap()
+            .write_all(b"# Caf\xe9\n")
+            .unwrap();
+
+        let link = Link::new("Chapter 1", chapter_path);
+
+        let mut errors = Vec::new();
+        assert!(load_chapter(&link, temp_dir.path(), Vec::new(), &mut errors).is_none());
+        let got = errors[0].to_string();
+        assert!(got.contains("\"Chapter 1\""), "{}", got);
+        assert!(
+            got.contains("is not valid UTF-8 (invalid byte at offset 5)"),
+            "{}",
+            got
+        );
+    }
+
+    #[test]
+    fn external_links_are_not_loaded_from_disk() {
+        let mut link = Link::new("API Reference", "https://docs.rs/mdbook");
+        link.nested_items = vec![SummaryItem::Link(Link::new(
+            "Nested",
+            "http://example.com/nested",
+        ))];
+
+        // The source directory doesn't exist, so any attempt to read a file
+        // would fail.
+        let got = load_chapter(
+            &link,
+            Path::new("/does/not/exist"),
+            Vec::new(),
+            &mut Vec::new(),
+        )
+        .unwrap();
+
+        let mut should_be = Chapter::new_external(
+            "API Reference",
+            String::from("https://docs.rs/mdbook"),
+            Vec::new(),
+        );
+        should_be.sub_items = vec![BookItem::Chapter(Chapter::new_external(
+            "Nested",
+            String::from("http://example.com/nested"),
+            vec![String::from("API Reference")],
+        ))];
+        assert_eq!(got, should_be);
+        assert!(got.path.is_none());
+    }
+
+    #[test]
+    fn output_paths_of_chapters() {
+        let readme = vec![String::from("README")];
+
+        assert_eq!(
+            output_path_for(&chapter_at("guide/README.md"), &readme),
+            Some(PathBuf::from("guide/index.html"))
+        );
+        assert_eq!(
+            output_path_for(&chapter_at("readme.md"), &readme),
+            Some(PathBuf::from("index.html"))
+        );
+        assert_eq!(
+            output_path_for(&chapter_at("guide/README.md"), &[]),
+            Some(PathBuf::from("guide/README.html"))
+        );
+        assert_eq!(
+            output_path_for(&chapter_at("a/b.md"), &readme),
+            Some(PathBuf::from("a/b.html"))
+        );
+        assert_eq!(
+            output_path_for(&Chapter::new_draft("Draft", vec![]), &readme),
+            None
+        );
+    }
+
     #[test]
     fn cant_load_a_nonexistent_chapter() {
         let link = Link::new("Chapter 1", "/foo/bar/baz.md");
 
-        let got = load_chapter(&link, "", Vec::new());
-        assert!(got.is_err());
+        let mut errors = Vec::new();
+        let got = load_chapter(&link, Path::new(""), Vec::new(), &mut errors);
+        assert!(got.is_none());
+        assert_eq!(errors.len(), 1);
     }
 
     #[test]
@@ -443,6 +1615,7 @@ And here is some \
             source_path: Some(PathBuf::from("second.md")),
             parent_names: vec![String::from("Chapter 1")],
             sub_items: Vec::new(),
+            ..Default::default()
         };
         let should_be = BookItem::Chapter(Chapter {
             name: String::from("Chapter 1"),
@@ -456,9 +1629,16 @@ And here is some \
                 BookItem::Separator,
                 BookItem::Chapter(nested.clone()),
             ],
+            ..Default::default()
         });
 
-        let got = load_summary_item(&SummaryItem::Link(root), temp.path(), Vec::new()).unwrap();
+        let got = load_summary_item(
+            &SummaryItem::Link(root),
+            temp.path(),
+            Vec::new(),
+            &mut Vec::new(),
+        )
+        .unwrap();
         assert_eq!(got, should_be);
     }
 
@@ -485,6 +1665,169 @@ And here is some \
         assert_eq!(got, should_be);
     }
 
+    #[test]
+    fn empty_chapters_are_handled_as_configured() {
+        let temp = TempFileBuilder::new().prefix("book").tempdir().unwrap();
+        fs::write(
+            temp.path().join("SUMMARY.md"),
+            "# Summary\n\n- [Full](full.md)\n- [Empty](empty.md)\n- [Draft]()\n",
+        )
+        .unwrap();
+        fs::write(temp.path().join("full.md"), "# Full\n").unwrap();
+        fs::write(temp.path().join("empty.md"), " \n").unwrap();
+        let contents = |book: &Book| -> Vec<String> {
+            book.iter()
+                .filter_map(|item| match item {
+                    BookItem::Chapter(ch) => Some(ch.content.clone()),
+                    _ => None,
+                })
+                .collect()
+        };
+
+        let book = load_book(temp.path(), &BuildConfig::default()).unwrap();
+        assert_eq!(contents(&book), ["# Full\n", " \n", ""]);
+
+        let cfg = BuildConfig {
+            empty_chapter: EmptyChapter::Placeholder,
+            ..Default::default()
+        };
+        let book = load_book(temp.path(), &cfg).unwrap();
+        assert_eq!(
+            contents(&book),
+            ["# Full\n", "*This page is intentionally empty.*\n", ""]
+        );
+
+        let cfg = BuildConfig {
+            empty_chapter: EmptyChapter::Placeholder,
+            empty_chapter_placeholder: Some(String::from("Coming soon!")),
+            ..Default::default()
+        };
+        let book = load_book(temp.path(), &cfg).unwrap();
+        assert_eq!(contents(&book)[1], "Coming soon!\n");
+
+        let cfg = BuildConfig {
+            empty_chapter: EmptyChapter::Error,
+            ..Default::default()
+        };
+        let err = load_book(temp.path(), &cfg).unwrap_err();
+        assert!(
+            err.to_string().contains("\"Empty\" (empty.md) is empty"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn generated_summary_keeps_brackets_in_chapter_names() {
+        let temp = TempFileBuilder::new().prefix("book").tempdir().unwrap();
+        fs::write(temp.path().join("README.md"), "# Intro\n").unwrap();
+        fs::write(temp.path().join("index.md"), "# Ignored\n").unwrap();
+        fs::write(temp.path().join("arrays.md"), "# Arrays: [T; N] and \\\n").unwrap();
+        let cfg = BuildConfig {
+            auto_summary: true,
+            ..Default::default()
+        };
+
+        let book = load_book(temp.path(), &cfg).unwrap();
+        let chapters: Vec<_> = book
+            .iter()
+            .filter_map(|item| match item {
+                BookItem::Chapter(ch) => Some((ch.name.clone(), ch.path.clone().unwrap())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            chapters,
+            [
+                (String::from("Intro"), PathBuf::from("README.md")),
+                (
+                    String::from("Arrays: [T; N] and \\"),
+                    PathBuf::from("arrays.md")
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn drafts_can_be_left_out_of_the_book() {
+        let temp = TempFileBuilder::new().prefix("book").tempdir().unwrap();
+        fs::write(
+            temp.path().join("SUMMARY.md"),
+            "# Summary\n\n- [First](first.md)\n    - [Unwritten]()\n- [Later]()\n- [Last](last.md)\n",
+        )
+        .unwrap();
+        let book = load_book(temp.path(), &BuildConfig::default()).unwrap();
+        assert_eq!(
+            item_names(book.iter()),
+            ["First", "Unwritten", "Later", "Last"]
+        );
+
+        let cfg = BuildConfig {
+            include_drafts: false,
+            ..Default::default()
+        };
+        let book = load_book(temp.path(), &cfg).unwrap();
+        assert_eq!(item_names(book.iter()), ["First", "Last"]);
+        assert!(book.iter().all(|item| match item {
+            BookItem::Chapter(ch) => !ch.is_draft_chapter(),
+            _ => true,
+        }));
+    }
+
+    #[test]
+    fn chapters_nested_under_left_out_drafts_are_warned_about() {
+        let temp = TempFileBuilder::new().prefix("book").tempdir().unwrap();
+        fs::write(
+            temp.path().join("SUMMARY.md"),
+            "# Summary\n\n- [First](first.md)\n- [Unwritten]()\n    - [Written](written.md)\n",
+        )
+        .unwrap();
+        let cfg = BuildConfig {
+            include_drafts: false,
+            ..Default::default()
+        };
+
+        let start = diagnostics::tests::start_collecting();
+        let book = load_book(temp.path(), &cfg).unwrap();
+
+        assert_eq!(item_names(book.iter()), ["First"]);
+        let warned = diagnostics::since(start).into_iter().any(|d| {
+            d.message.contains("\"Written\" is left out") && d.message.contains("\"Unwritten\"")
+        });
+        assert!(warned);
+    }
+
+    #[test]
+    fn unnumbered_chapters_keep_their_place_in_the_book() {
+        let temp = TempFileBuilder::new().prefix("book").tempdir().unwrap();
+        fs::write(
+            temp.path().join("SUMMARY.md"),
+            "# Summary\n\n- [First](first.md)\n    - [ ] [Aside](aside.md)\n    - [Nested](nested.md)\n",
+        )
+        .unwrap();
+
+        let book = load_book(temp.path(), &BuildConfig::default()).unwrap();
+
+        let got: Vec<_> = book
+            .iter()
+            .filter_map(|item| match item {
+                BookItem::Chapter(ch) => Some((
+                    ch.name.as_str(),
+                    ch.number.as_ref().map(ToString::to_string),
+                )),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            got,
+            vec![
+                ("First", Some(String::from("1."))),
+                ("Aside", None),
+                ("Nested", Some(String::from("1.1."))),
+            ]
+        );
+    }
+
     #[test]
     fn book_iter_iterates_over_sequential_items() {
         let book = Book {
@@ -532,6 +1875,7 @@ And here is some \
                             Vec::new(),
                         )),
                     ],
+                    ..Default::default()
                 }),
                 BookItem::Separator,
             ],
@@ -559,6 +1903,118 @@ And here is some \
         assert_eq!(chapter_names, should_be);
     }
 
+    #[test]
+    fn diff_reports_an_added_chapter() {
+        let old = Book {
+            sections: vec![
+                BookItem::Chapter(Chapter::new("Intro", String::new(), "intro.md", Vec::new())),
+                BookItem::Chapter(Chapter::new("Usage", String::new(), "usage.md", Vec::new())),
+            ],
+            ..Default::default()
+        };
+        let mut new = old.clone();
+        new.push_item(Chapter::new("FAQ", String::new(), "faq.md", Vec::new()));
+
+        let diff = old.diff(&new);
+
+        assert_eq!(diff.added, vec![PathBuf::from("faq.md")]);
+        assert!(diff.removed.is_empty());
+        assert!(diff.renamed.is_empty());
+        assert!(diff.moved.is_empty());
+        assert_eq!(
+            diff.content_changed,
+            vec![
+                (PathBuf::from("intro.md"), false),
+                (PathBuf::from("usage.md"), false)
+            ]
+        );
+        assert!(!diff.is_empty());
+        assert!(old.diff(&old).is_empty());
+    }
+
+    #[test]
+    fn diff_reports_removed_renamed_moved_and_edited_chapters() {
+        let mut nested = Chapter::new("Nested", String::new(), "nested.md", Vec::new());
+        let mut first = Chapter::new("First", String::new(), "first.md", Vec::new());
+        first.sub_items.push(BookItem::Chapter(nested.clone()));
+        let old = Book {
+            sections: vec![
+                BookItem::Chapter(first.clone()),
+                BookItem::Chapter(Chapter::new("Gone", String::new(), "gone.md", Vec::new())),
+            ],
+            ..Default::default()
+        };
+
+        first.sub_items.clear();
+        first.name = String::from("Renamed");
+        nested.content = String::from("edited");
+        let new = Book {
+            sections: vec![BookItem::Chapter(first), BookItem::Chapter(nested)],
+            ..Default::default()
+        };
+
+        let diff = old.diff(&new);
+
+        assert!(diff.added.is_empty());
+        assert_eq!(diff.removed, vec![PathBuf::from("gone.md")]);
+        assert_eq!(
+            diff.renamed,
+            vec![(
+                PathBuf::from("first.md"),
+                String::from("First"),
+                String::from("Renamed")
+            )]
+        );
+        assert_eq!(diff.moved, vec![PathBuf::from("nested.md")]);
+        assert_eq!(
+            diff.content_changed,
+            vec![
+                (PathBuf::from("first.md"), false),
+                (PathBuf::from("nested.md"), true)
+            ]
+        );
+    }
+
+    #[test]
+    fn retain_chapters_removes_chapters_and_their_children() {
+        let mut book = Book {
+            sections: vec![
+                chapter_item("Drop intro", vec![]),
+                BookItem::Separator,
+                chapter_item(
+                    "Keep 1",
+                    vec![
+                        chapter_item("Keep 1.1", vec![]),
+                        chapter_item("Drop 1.2", vec![chapter_item("Keep 1.2.1", vec![])]),
+                        chapter_item("Keep 1.3", vec![]),
+                    ],
+                ),
+                draft_item("Keep draft", vec![chapter_item("Drop 2.1", vec![])]),
+                BookItem::PartTitle(String::from("Part")),
+                chapter_item("Drop 3", vec![]),
+                chapter_item("Keep 4", vec![]),
+                BookItem::Separator,
+                chapter_item("Drop outro", vec![]),
+            ],
+            ..Default::default()
+        };
+
+        book.retain_chapters(|ch| ch.name.starts_with("Keep"));
+
+        let should_be = vec![
+            chapter_item(
+                "Keep 1",
+                vec![
+                    chapter_item("Keep 1.1", vec![]),
+                    chapter_item("Keep 1.3", vec![]),
+                ],
+            ),
+            BookItem::PartTitle(String::from("Part")),
+            chapter_item("Keep 4", vec![]),
+        ];
+        assert_eq!(book.sections, should_be);
+    }
+
     #[test]
     fn for_each_mut_visits_all_items() {
         let mut book = Book {
@@ -585,6 +2041,7 @@ And here is some \
                             Vec::new(),
                         )),
                     ],
+                    ..Default::default()
                 }),
                 BookItem::Separator,
             ],
@@ -616,6 +2073,47 @@ And here is some \
         assert!(got.is_err());
     }
 
+    #[test]
+    fn every_chapter_which_fails_to_load_is_reported() {
+        let (link, temp) = dummy_link();
+        let broken = |name: &str| {
+            SummaryItem::Link(Link::new(name, temp.path().join(format!("{}.md", name))))
+        };
+        let summary = Summary {
+            numbered_chapters: vec![
+                broken("missing-1"),
+                SummaryItem::Link(link),
+                broken("missing-2"),
+            ],
+            ..Default::default()
+        };
+
+        let err = load_book_collect_errors(&summary, temp.path()).unwrap_err();
+
+        assert_eq!(err.errors.len(), 2);
+        let message = err.to_string();
+        assert!(
+            message.starts_with("2 chapters couldn't be loaded:"),
+            "{}",
+            message
+        );
+        assert!(message.contains("missing-1.md"), "{}", message);
+        assert!(message.contains("missing-2.md"), "{}", message);
+
+        let loaded: Vec<_> = err
+            .partial_book
+            .iter()
+            .filter_map(|item| match item {
+                BookItem::Chapter(ch) => Some(ch.name.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(loaded, vec!["Chapter 1"]);
+
+        let err = load_book_from_disk(&summary, temp.path()).unwrap_err();
+        assert!(format!("{:#}", err).contains("missing-2.md"));
+    }
+
     #[test]
     fn cant_load_chapters_when_the_link_is_a_directory() {
         let (_, temp) = dummy_link();
@@ -634,4 +2132,531 @@ And here is some \
         let got = load_book_from_disk(&summary, temp.path());
         assert!(got.is_err());
     }
+
+    #[test]
+    fn display_name_strips_inline_markdown() {
+        let chapter = Chapter::new(
+            "`Code` and *emphasis* in a **name**",
+            String::new(),
+            "chapter_1.md",
+            vec![],
+        );
+
+        assert_eq!(chapter.display_name(), "Code and emphasis in a name");
+    }
+
+    #[test]
+    fn relative_links_between_chapters() {
+        let nested = chapter_at("a/b.md");
+
+        let index_file_names = vec![String::from("README")];
+
+        assert_eq!(
+            nested
+                .relative_link_to(&chapter_at("c.md"), &index_file_names)
+                .unwrap(),
+            "../c.html"
+        );
+        assert_eq!(
+            nested
+                .relative_link_to(&chapter_at("a/d.md"), &index_file_names)
+                .unwrap(),
+            "d.html"
+        );
+        assert_eq!(
+            nested
+                .relative_link_to(&chapter_at("e/f/g.md"), &index_file_names)
+                .unwrap(),
+            "../e/f/g.html"
+        );
+        assert_eq!(
+            chapter_at("c.md")
+                .relative_link_to(&nested, &index_file_names)
+                .unwrap(),
+            "a/b.html"
+        );
+        assert_eq!(
+            nested
+                .relative_link_to(&chapter_at("e/README.md"), &index_file_names)
+                .unwrap(),
+            "../e/index.html"
+        );
+        assert_eq!(
+            chapter_at("e/README.md")
+                .relative_link_to(&nested, &index_file_names)
+                .unwrap(),
+            "../a/b.html"
+        );
+        assert_eq!(
+            nested.relative_link_to(&Chapter::new_draft("Draft", vec![]), &index_file_names),
+            None
+        );
+    }
+
+    #[test]
+    fn set_content_updates_the_word_count() {
+        let mut chapter = Chapter::new("Chapter 1", DUMMY_SRC.to_string(), "chapter_1.md", vec![]);
+        assert_eq!(chapter.word_count(), 13);
+
+        chapter.set_content(String::from(
+            "# New Title\n\nSome `new` **markdown** here.\n",
+        ));
+
+        assert_eq!(chapter.word_count(), 6);
+    }
+
+    #[test]
+    fn plain_text_strips_markdown() {
+        let src =
+            "# A *Title*\n\nSome `code` and [a link](foo.md)\nover two lines.\n\n- one\n- two\n";
+        let chapter = Chapter::new("Chapter 1", src.to_string(), "chapter_1.md", vec![]);
+
+        assert_eq!(
+            chapter.plain_text(),
+            "A Title\nSome code and a link over two lines.\none\ntwo"
+        );
+    }
+
+    #[test]
+    fn headings_deeper_than_the_max_level_are_excluded() {
+        let src = "# Title\n\n## `Setup`\n\n### Details\n\n#### Too Deep\n\n## Setup\n";
+        let chapter = Chapter::new("Chapter 1", src.to_string(), "chapter_1.md", vec![]);
+
+        let summary: Vec<_> = chapter
+            .headings(3)
+            .into_iter()
+            .map(|h| (h.level, h.text, h.id))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (1, String::from("Title"), String::from("title")),
+                (2, String::from("Setup"), String::from("setup")),
+                (3, String::from("Details"), String::from("details")),
+                (2, String::from("Setup"), String::from("setup-1")),
+            ]
+        );
+
+        let levels: Vec<_> = chapter.headings(2).iter().map(|h| h.level).collect();
+        assert_eq!(levels, vec![1, 2, 2]);
+    }
+
+    #[test]
+    fn headings_are_nested_under_the_closest_shallower_heading() {
+        let src = "## One\n\n### One A\n\n## Two\n\n#### Two A\n\n### Two B\n";
+        let chapter = Chapter::new("Chapter 1", src.to_string(), "chapter_1.md", vec![]);
+
+        let tree = chapter.headings_tree(6);
+
+        let outline: Vec<_> = tree
+            .iter()
+            .map(|node| {
+                let children: Vec<_> = node.children.iter().map(|c| c.id.as_str()).collect();
+                (node.id.as_str(), children)
+            })
+            .collect();
+        assert_eq!(
+            outline,
+            vec![("one", vec!["one-a"]), ("two", vec!["two-a", "two-b"])]
+        );
+        assert_eq!(chapter.headings_tree(3)[1].children.len(), 1);
+    }
+
+    #[test]
+    fn code_blocks_are_listed_with_their_language() {
+        let src = "# Title\n\n```rust,ignore\n# use std::io;\nfn main() {}\n```\n\n\
+                   Some text.\n\n    indented\n\n```\nplain\n```\n\n~~~toml\n[book]\n~~~\n";
+        let chapter = Chapter::new("Chapter 1", src.to_string(), "chapter_1.md", vec![]);
+
+        let got: Vec<_> = chapter
+            .code_blocks()
+            .into_iter()
+            .map(|block| (block.language, block.code))
+            .collect();
+
+        assert_eq!(
+            got,
+            vec![
+                (
+                    Some(String::from("rust")),
+                    String::from("# use std::io;\nfn main() {}\n")
+                ),
+                (None, String::from("plain\n")),
+                (Some(String::from("toml")), String::from("[book]\n")),
+            ]
+        );
+    }
+
+    #[test]
+    fn assign_unique_slug_paths() {
+        let setup = |parent: &str| {
+            BookItem::Chapter(Chapter::new(
+                "Setup",
+                String::new(),
+                "setup.md",
+                vec![String::from(parent)],
+            ))
+        };
+        let mut linux = Chapter::new("Linux", String::new(), "linux.md", vec![]);
+        linux.sub_items = vec![setup("Linux")];
+        let mut windows = Chapter::new("*Windows* & `WSL`", String::new(), "win.md", vec![]);
+        windows.sub_items = vec![setup("*Windows* & `WSL`")];
+        let mut book = Book {
+            sections: vec![
+                BookItem::Chapter(linux),
+                BookItem::Chapter(windows),
+                BookItem::Separator,
+                BookItem::Chapter(Chapter::new_draft("Linux", vec![])),
+            ],
+            ..Default::default()
+        };
+
+        book.assign_slugs();
+
+        let got: Vec<_> = book
+            .iter()
+            .filter_map(|item| match item {
+                BookItem::Chapter(ch) => Some(ch.slug_path.clone()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            got,
+            vec![
+                PathBuf::from("linux.md"),
+                PathBuf::from("linux/setup.md"),
+                PathBuf::from("windows--wsl.md"),
+                PathBuf::from("windows--wsl/setup.md"),
+                PathBuf::from("linux-1.md"),
+            ]
+        );
+    }
+
+    #[test]
+    fn write_chapters_as_ndjson() {
+        let mut first = Chapter::new("First", String::new(), "first/index.md", vec![]);
+        first.sub_items = vec![
+            BookItem::Chapter(Chapter::new(
+                "Nested",
+                String::new(),
+                "first/nested.md",
+                vec![String::from("First")],
+            )),
+            BookItem::Separator,
+        ];
+        let book = Book {
+            sections: vec![
+                BookItem::Chapter(first),
+                BookItem::Separator,
+                BookItem::Chapter(Chapter::new_draft("Draft", vec![])),
+            ],
+            ..Default::default()
+        };
+
+        let mut buffer = Vec::new();
+        book.write_ndjson(&mut buffer).unwrap();
+        let output = String::from_utf8(buffer).unwrap();
+
+        let lines: Vec<serde_json::Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let chapter_count = book
+            .iter()
+            .filter(|item| matches!(item, BookItem::Chapter(_)))
+            .count();
+        assert_eq!(lines.len(), chapter_count);
+        assert_eq!(
+            lines,
+            vec![
+                json!({"name": "First", "path": "first/index.md", "depth": 0, "has_children": true}),
+                json!({"name": "Nested", "path": "first/nested.md", "depth": 1, "has_children": false}),
+                json!({"name": "Draft", "path": null, "depth": 0, "has_children": false}),
+            ]
+        );
+    }
+
+    #[test]
+    fn sort_sibling_chapters_by_weight() {
+        let weighted = |name: &str, weight: Option<i32>| {
+            let mut ch = named_chapter(name);
+            ch.weight = weight;
+            BookItem::Chapter(ch)
+        };
+        let mut parent = Chapter::new("Parent", String::new(), "parent.md", vec![]);
+        parent.sub_items = vec![
+            weighted("unweighted", None),
+            weighted("heavy", Some(10)),
+            weighted("light", Some(-1)),
+        ];
+        let mut book = Book {
+            sections: vec![
+                weighted("b", Some(2)),
+                weighted("a", Some(1)),
+                BookItem::Separator,
+                BookItem::Chapter(parent),
+            ],
+            ..Default::default()
+        };
+
+        book.sort_by_weight();
+
+        let names: Vec<_> = book
+            .iter()
+            .filter_map(|item| match item {
+                BookItem::Chapter(ch) => Some(ch.name.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(names, ["a", "b", "Parent", "light", "heavy", "unweighted"]);
+        assert_eq!(book.sections[2], BookItem::Separator);
+    }
+
+    #[test]
+    fn prepended_items_come_before_the_numbered_chapters() {
+        let mut numbered = Chapter::new("Numbered", String::new(), "numbered.md", vec![]);
+        numbered.number = Some(SectionNumber(vec![1]));
+        let mut book = Book::new();
+        book.push_item(numbered)
+            .push_item(Chapter::new("License", String::new(), "license.md", vec![]))
+            .prepend_item(Chapter::new("Cover", String::new(), "cover.md", vec![]));
+
+        let names: Vec<_> = book
+            .iter()
+            .map(|item| match item {
+                BookItem::Chapter(ch) => ch.name.as_str(),
+                other => panic!("expected a chapter, got {:?}", other),
+            })
+            .collect();
+
+        assert_eq!(names, ["Cover", "Numbered", "License"]);
+    }
+
+    #[test]
+    fn for_each_with_siblings_sees_the_neighbouring_items() {
+        let mut middle = Chapter::new("Middle", String::new(), "middle.md", vec![]);
+        middle.sub_items = vec![BookItem::Chapter(Chapter::new(
+            "Only child",
+            String::new(),
+            "child.md",
+            vec![String::from("Middle")],
+        ))];
+        let mut book = Book {
+            sections: vec![
+                BookItem::Chapter(Chapter::new("First", String::new(), "first.md", vec![])),
+                BookItem::Chapter(middle),
+                BookItem::Separator,
+                BookItem::Chapter(Chapter::new("Last", String::new(), "last.md", vec![])),
+            ],
+            ..Default::default()
+        };
+
+        let mut seen = Vec::new();
+        book.for_each_with_siblings(|ch, before, after| {
+            seen.push((ch.name.clone(), item_names(before), item_names(after)));
+            ch.content = format!("{} siblings", before.len() + after.len());
+        });
+
+        assert_eq!(
+            seen,
+            vec![
+                (
+                    String::from("First"),
+                    vec![],
+                    item_names(&book.sections[1..])
+                ),
+                (String::from("Only child"), vec![], vec![]),
+                (
+                    String::from("Middle"),
+                    vec![String::from("First")],
+                    vec![String::from("Separator"), String::from("Last")],
+                ),
+                (
+                    String::from("Last"),
+                    item_names(&book.sections[..3]),
+                    vec![]
+                ),
+            ]
+        );
+        match &book.sections[1] {
+            BookItem::Chapter(ch) => assert_eq!(ch.content, "3 siblings"),
+            other => panic!("expected a chapter, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn structural_equality_ignores_content() {
+        let book_with = |content: &str| {
+            let mut parent = Chapter::new("Parent", String::from(content), "parent.md", vec![]);
+            parent.sub_items = vec![BookItem::Chapter(Chapter::new(
+                "Child",
+                String::from(content),
+                "child.md",
+                vec![String::from("Parent")],
+            ))];
+            Book {
+                sections: vec![BookItem::Chapter(parent), BookItem::Separator],
+                ..Default::default()
+            }
+        };
+
+        let original = book_with("Some text");
+        let edited = book_with("Some other text");
+        assert!(original.structurally_eq(&edited));
+        assert_ne!(original, edited);
+
+        let mut renamed = edited.clone();
+        renamed.for_each_mut(|item| {
+            if let BookItem::Chapter(ch) = item {
+                if ch.name == "Child" {
+                    ch.name = String::from("Renamed");
+                }
+            }
+        });
+        assert!(!original.structurally_eq(&renamed));
+
+        let mut flattened = edited;
+        flattened.sections.pop();
+        assert!(!original.structurally_eq(&flattened));
+    }
+
+    #[test]
+    fn map_chapters_keeps_the_structure() {
+        let mut nested = Chapter::new("Nested", String::new(), "nested.md", vec![]);
+        nested.sub_items = vec![BookItem::Chapter(Chapter::new_draft(
+            "Draft",
+            vec![String::from("Nested")],
+        ))];
+        let book = Book {
+            sections: vec![
+                BookItem::PartTitle(String::from("Part")),
+                BookItem::Chapter(Chapter::new("First", String::new(), "first.md", vec![])),
+                BookItem::Separator,
+                BookItem::Chapter(nested),
+            ],
+            ..Default::default()
+        };
+
+        let mapped = book.clone().map_chapters(|mut ch| {
+            ch.name = ch.name.to_uppercase();
+            ch
+        });
+
+        let names: Vec<_> = mapped
+            .iter()
+            .filter_map(|item| match item {
+                BookItem::Chapter(ch) => Some(ch.name.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(names, ["FIRST", "NESTED", "DRAFT"]);
+        assert_eq!(mapped.sections[0], book.sections[0]);
+        assert_eq!(mapped.sections[2], BookItem::Separator);
+        match &mapped.sections[3] {
+            BookItem::Chapter(ch) => {
+                assert_eq!(ch.sub_items.len(), 1);
+                assert!(
+                    matches!(&ch.sub_items[0], BookItem::Chapter(draft) if draft.is_draft_chapter())
+                );
+            }
+            other => panic!("expected a chapter, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn validate_hierarchy_checks_the_parent_names() {
+        let mut parent = Chapter::new("Parent", String::new(), "parent.md", vec![]);
+        parent.sub_items = vec![BookItem::Chapter(Chapter::new(
+            "Child",
+            String::new(),
+            "child.md",
+            vec![String::from("Parent")],
+        ))];
+        let mut book = Book {
+            sections: vec![BookItem::Chapter(parent.clone())],
+            ..Default::default()
+        };
+        book.validate_hierarchy().unwrap();
+
+        let own_ancestor =
+            Chapter::new("Loop", String::new(), "loop.md", vec![String::from("Loop")]);
+        book.sections.push(BookItem::Chapter(own_ancestor));
+        let err = book.validate_hierarchy().unwrap_err().to_string();
+        assert!(err.contains("lists itself"), "{}", err);
+
+        parent.sub_items = vec![BookItem::Chapter(Chapter::new(
+            "Child",
+            String::new(),
+            "child.md",
+            vec![String::from("Elsewhere")],
+        ))];
+        book.sections = vec![BookItem::Chapter(parent)];
+        let err = book.validate_hierarchy().unwrap_err().to_string();
+        assert!(err.contains("is nested under [\"Parent\"]"), "{}", err);
+    }
+
+    #[test]
+    fn breadcrumbs_follow_the_parent_names() {
+        fn chapter(name: &str, path: &str, parents: &[&str], sub_items: Vec<BookItem>) -> BookItem {
+            let parents = parents.iter().map(|name| name.to_string()).collect();
+            let mut ch = Chapter::new(name, String::new(), path, parents);
+            ch.sub_items = sub_items;
+            BookItem::Chapter(ch)
+        }
+
+        // Both top-level chapters are called "Part", and both contain an
+        // "Intro" with a "Deep" chapter inside it.
+        let book = Book {
+            sections: vec![
+                chapter(
+                    "Part",
+                    "a.md",
+                    &[],
+                    vec![chapter(
+                        "Intro",
+                        "a/intro.md",
+                        &["Part"],
+                        vec![chapter("Deep", "a/deep.md", &["Part", "Intro"], vec![])],
+                    )],
+                ),
+                BookItem::Separator,
+                chapter(
+                    "Part",
+                    "b.md",
+                    &[],
+                    vec![chapter(
+                        "Intro",
+                        "b/intro.md",
+                        &["Part"],
+                        vec![chapter("Deep", "b/deep.md", &["Part", "Intro"], vec![])],
+                    )],
+                ),
+            ],
+            ..Default::default()
+        };
+
+        let deep = book
+            .iter()
+            .filter_map(|item| match item {
+                BookItem::Chapter(ch) if ch.name == "Deep" => Some(ch),
+                _ => None,
+            })
+            .last()
+            .unwrap();
+        let paths: Vec<_> = book
+            .breadcrumbs(deep)
+            .into_iter()
+            .map(|ch| ch.path.as_ref().unwrap().to_str().unwrap())
+            .collect();
+        assert_eq!(paths, vec!["b.md", "b/intro.md"]);
+
+        let top_level = match &book.sections[0] {
+            BookItem::Chapter(ch) => ch,
+            _ => unreachable!(),
+        };
+        assert!(book.breadcrumbs(top_level).is_empty());
+
+        let stranger = Chapter::new("Deep", String::new(), "c/deep.md", vec![]);
+        assert!(book.breadcrumbs(&stranger).is_empty());
+    }
 }
@@ -0,0 +1,119 @@
+//! Per-chapter settings given as a TOML block at the very top of a chapter's
+//! source file, delimited by `+++` lines:
+//!
+//! ```markdown
+//! +++
+//! no-index = true
+//! +++
+//!
+//! # Chapter Title
+//! ```
+
+use super::book::Chapter;
+use crate::errors::*;
+
+const DELIMITER: &str = "+++";
+
+/// The settings a chapter may declare in its front matter.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub(crate) struct FrontMatter {
+    /// Ask search engines not to index the rendered page.
+    pub(crate) no_index: bool,
+    /// Render the chapter with `<template>.hbs` from the theme directory.
+    pub(crate) template: Option<String>,
+    /// A short summary of the chapter, used for link previews.
+    pub(crate) description: Option<String>,
+    /// Extra CSS classes for the element wrapping the chapter's content.
+    pub(crate) class: Vec<String>,
+    /// Set to `false` to render the chapter's Rust code blocks without the
+    /// playground's run buttons or editor.
+    pub(crate) playground: Option<bool>,
+}
+
+impl FrontMatter {
+    /// Copy these settings onto the chapter they were read from.
+    pub(crate) fn apply(self, ch: &mut Chapter) {
+        ch.meta.no_index = self.no_index;
+        ch.meta.template = self.template;
+        ch.meta.description = self.description;
+        ch.meta.classes = self.class;
+        ch.meta.no_playground = self.playground == Some(false);
+    }
+}
+
+/// Split a chapter's source into its front matter (if there is any) and the
+/// remaining markdown.
+pub(crate) fn split_front_matter(content: &str) -> Result<(Option<FrontMatter>, &str)> {
+    let first_line_end = match content.find('\n') {
+        Some(idx) if content[..idx].trim_end() == DELIMITER => idx + 1,
+        _ => return Ok((None, content)),
+    };
+
+    let mut line_start = first_line_end;
+    while line_start < content.len() {
+        let line_end = content[line_start..]
+            .find('\n')
+            .map(|idx| line_start + idx + 1)
+            .unwrap_or_else(|| content.len());
+
+        if content[line_start..line_end].trim_end() == DELIMITER {
+            let front_matter = toml::from_str(&content[first_line_end..line_start])
+                .with_context(|| "Invalid front matter")?;
+            return Ok((Some(front_matter), &content[line_end..]));
+        }
+        line_start = line_end;
+    }
+
+    bail!("Front matter is missing its closing `{}` line", DELIMITER);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_without_front_matter_is_untouched() {
+        let src = "# Chapter\n\n+++\nno-index = true\n+++\n";
+
+        let (front_matter, rest) = split_front_matter(src).unwrap();
+
+        assert!(front_matter.is_none());
+        assert_eq!(rest, src);
+    }
+
+    #[test]
+    fn parse_front_matter() {
+        let src = "+++\r\nno-index = true\r\ntemplate = \"landing\"\r\ndescription = \"Start here\"\r\nclass = [\"wide\"]\r\nplayground = false\r\n+++\r\n# Chapter\n";
+
+        let (front_matter, rest) = split_front_matter(src).unwrap();
+
+        let should_be = FrontMatter {
+            no_index: true,
+            template: Some(String::from("landing")),
+            description: Some(String::from("Start here")),
+            class: vec![String::from("wide")],
+            playground: Some(false),
+        };
+        assert_eq!(front_matter, Some(should_be));
+        assert_eq!(rest, "# Chapter\n");
+    }
+
+    #[test]
+    fn empty_front_matter_uses_the_defaults() {
+        let (front_matter, rest) = split_front_matter("+++\n+++\n").unwrap();
+
+        assert_eq!(front_matter, Some(FrontMatter::default()));
+        assert_eq!(rest, "");
+    }
+
+    #[test]
+    fn unterminated_front_matter_is_an_error() {
+        assert!(split_front_matter("+++\nno-index = true\n# Chapter\n").is_err());
+    }
+
+    #[test]
+    fn invalid_front_matter_is_an_error() {
+        assert!(split_front_matter("+++\nno-index = \"maybe\"\n+++\n").is_err());
+    }
+}
@@ -12,6 +12,7 @@ use crate::theme;
 pub struct BookBuilder {
     root: PathBuf,
     create_gitignore: bool,
+    create_hgignore: bool,
     config: Config,
     copy_theme: bool,
 }
@@ -23,6 +24,7 @@ impl BookBuilder {
         BookBuilder {
             root: root.into(),
             create_gitignore: false,
+            create_hgignore: false,
             config: Config::default(),
             copy_theme: false,
         }
@@ -52,11 +54,17 @@ impl BookBuilder {
         self
     }
 
+    /// Should we create a `.hgignore` file?
+    pub fn create_hgignore(&mut self, create: bool) -> &mut BookBuilder {
+        self.create_hgignore = create;
+        self
+    }
+
     /// Generate the actual book. This will:
     ///
     /// - Create the directory structure.
     /// - Stub out some dummy chapters and the `SUMMARY.md`.
-    /// - Create a `.gitignore` (if applicable)
+    /// - Create a `.gitignore` and/or `.hgignore` (if applicable)
     /// - Create a themes directory and populate it (if applicable)
     /// - Generate a `book.toml` file,
     /// - Then load the book so we can build it or run tests.
@@ -74,6 +82,11 @@ impl BookBuilder {
                 .with_context(|| "Unable to create .gitignore")?;
         }
 
+        if self.create_hgignore {
+            self.build_hgignore()
+                .with_context(|| "Unable to create .hgignore")?;
+        }
+
         if self.copy_theme {
             self.copy_across_theme()
                 .with_context(|| "Unable to copy across the theme")?;
@@ -165,14 +178,28 @@ impl BookBuilder {
 
         let mut f = File::create(self.root.join(".gitignore"))?;
 
-        writeln!(f, "{}", self.config.build.build_dir.display())?;
+        // The trailing slash makes git only ignore a directory of that name.
+        writeln!(f, "{}/", self.config.build.build_dir.display())?;
+
+        Ok(())
+    }
+
+    fn build_hgignore(&self) -> Result<()> {
+        debug!("Creating .hgignore");
+
+        let mut f = File::create(self.root.join(".hgignore"))?;
+
+        // Unlike `glob`, `rootglob` patterns only match from the root, so a
+        // `book` directory elsewhere in the repository isn't ignored.
+        writeln!(f, "syntax: rootglob")?;
+        writeln!(f, "{}/**", self.config.build.build_dir.display())?;
 
         Ok(())
     }
 
     fn create_stub_files(&self) -> Result<()> {
         debug!("Creating example book contents");
-        let src_dir = self.root.join(&self.config.book.src);
+        let src_dir = self.config.source_dir(&self.root);
 
         let summary = src_dir.join("SUMMARY.md");
         if !summary.exists() {
@@ -196,7 +223,7 @@ impl BookBuilder {
         debug!("Creating directory tree");
         fs::create_dir_all(&self.root)?;
 
-        let src = self.root.join(&self.config.book.src);
+        let src = self.config.source_dir(&self.root);
         fs::create_dir_all(&src)?;
 
         let build = self.root.join(&self.config.build.build_dir);
@@ -5,17 +5,28 @@
 //!
 //! [1]: ../index.html
 
+mod auto_summary;
 #[allow(clippy::module_inception)]
 mod book;
+mod front_matter;
 mod init;
+mod source_map;
 mod summary;
 
-pub use self::book::{load_book, Book, BookItem, BookItems, Chapter};
+pub(crate) use self::book::output_path_for_source;
+pub use self::book::{
+    load_book, load_book_collect_errors, load_translations, output_path_for, Book, BookDiff,
+    BookItem, BookItems, BookLoadErrors, Chapter, CodeBlock, Heading, HeadingNode,
+};
 pub use self::init::BookBuilder;
-pub use self::summary::{parse_summary, Link, SectionNumber, Summary, SummaryItem};
+pub use self::source_map::{SourceMap, SourceMapBuilder, SourceSpan};
+pub use self::summary::{parse_summary, Link, SectionNumber, Summary, SummaryItem, SummaryPart};
 
+use shlex::Shlex;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashSet};
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::string::ToString;
 use tempfile::Builder as TempFileBuilder;
@@ -26,7 +37,7 @@ use crate::preprocess::{
     CmdPreprocessor, IndexPreprocessor, LinkPreprocessor, Preprocessor, PreprocessorContext,
 };
 use crate::renderer::{CmdRenderer, HtmlHandlebars, MarkdownRenderer, RenderContext, Renderer};
-use crate::utils;
+use crate::utils::{self, diagnostics};
 
 use crate::config::{Config, RustEdition};
 
@@ -38,17 +49,43 @@ pub struct MDBook {
     pub config: Config,
     /// A representation of the book's contents in memory.
     pub book: Book,
+    /// Each language of a multilingual book, keyed by language. This is only
+    /// filled in when `book.multilingual` is set and `book.languages` isn't
+    /// empty, in which case `book` is the first language's book.
+    pub translations: BTreeMap<String, Book>,
     renderers: Vec<Box<dyn Renderer>>,
 
     /// List of pre-processors to be run on the book.
     preprocessors: Vec<Box<dyn Preprocessor>>,
+
+    /// The number of diagnostics recorded before the book started loading,
+    /// so `build.deny-warnings` also covers warnings from loading it.
+    diagnostics_from: usize,
 }
 
 impl MDBook {
     /// Load a book from its root directory on disk.
     pub fn load<P: Into<PathBuf>>(book_root: P) -> Result<MDBook> {
+        MDBook::load_with_overrides(book_root, None, &[])
+    }
+
+    /// Load a book from its root directory on disk, like [`load()`], with
+    /// the `[profile.<profile>]` table and `key=value` overrides applied to
+    /// its configuration.
+    ///
+    /// Later sources take precedence: `book.toml`, then the profile, then
+    /// environment variables, then `overrides` in order. See
+    /// [`Config::apply_profile()`] and [`Config::apply_override()`].
+    ///
+    /// [`load()`]: #method.load
+    pub fn load_with_overrides<P: Into<PathBuf>>(
+        book_root: P,
+        profile: Option<&str>,
+        overrides: &[&str],
+    ) -> Result<MDBook> {
         let book_root = book_root.into();
         let config_location = book_root.join("book.toml");
+        let diagnostics_from = diagnostics::count();
 
         // the book.json file is no longer used, so we should emit a warning to
         // let people know to migrate to book.toml
@@ -67,7 +104,13 @@ impl MDBook {
             Config::default()
         };
 
+        if let Some(profile) = profile {
+            config.apply_profile(profile)?;
+        }
         config.update_from_env();
+        for pair in overrides {
+            config.apply_override(pair)?;
+        }
 
         if log_enabled!(log::Level::Trace) {
             for line in format!("Config: {:#?}", config).lines() {
@@ -75,15 +118,33 @@ impl MDBook {
             }
         }
 
-        MDBook::load_with_config(book_root, config)
+        let mut book = MDBook::load_with_config(book_root, config)?;
+        book.diagnostics_from = diagnostics_from;
+        Ok(book)
     }
 
     /// Load a book from its root directory using a custom `Config`.
     pub fn load_with_config<P: Into<PathBuf>>(book_root: P, config: Config) -> Result<MDBook> {
         let root = book_root.into();
+        let diagnostics_from = diagnostics::count();
 
-        let src_dir = root.join(&config.book.src);
-        let book = book::load_book(&src_dir, &config.build)?;
+        for problem in config.validate() {
+            warn!("{}", problem);
+        }
+        check_head_include(&root, &config)?;
+
+        let src_dir = config.source_dir(&root);
+        let (book, translations) = match config.book.languages.first() {
+            Some(first) if config.book.multilingual => {
+                let translations =
+                    book::load_translations(&src_dir, &config.book.languages, &config.build)?;
+                (translations[first].clone(), translations)
+            }
+            _ => {
+                let book = book::load_book(&src_dir, &config.build)?;
+                (book, BTreeMap::new())
+            }
+        };
 
         let renderers = determine_renderers(&config);
         let preprocessors = determine_preprocessors(&config)?;
@@ -92,21 +153,43 @@ impl MDBook {
             root,
             config,
             book,
+            translations,
             renderers,
             preprocessors,
+            diagnostics_from,
         })
     }
 
     /// Load a book from its root directory using a custom `Config` and a custom summary.
+    ///
+    /// Multilingual books aren't supported, since each language has its own
+    /// summary.
     pub fn load_with_config_and_summary<P: Into<PathBuf>>(
         book_root: P,
         config: Config,
         summary: Summary,
     ) -> Result<MDBook> {
         let root = book_root.into();
+        let diagnostics_from = diagnostics::count();
 
-        let src_dir = root.join(&config.book.src);
-        let book = book::load_book_from_disk(&summary, &src_dir)?;
+        if config.book.multilingual && !config.book.languages.is_empty() {
+            bail!(
+                "A multilingual book can't be loaded with a single summary, \
+                 each language in `book.languages` has its own SUMMARY.md"
+            );
+        }
+
+        for problem in config.validate() {
+            warn!("{}", problem);
+        }
+        check_head_include(&root, &config)?;
+
+        let src_dir = config.source_dir(&root);
+        let mut book = book::load_book_from_disk(&summary, &src_dir)?;
+        if !config.build.include_drafts {
+            book::remove_draft_chapters(&mut book);
+        }
+        book::handle_empty_chapters(&mut book, &config.build)?;
 
         let renderers = determine_renderers(&config);
         let preprocessors = determine_preprocessors(&config)?;
@@ -115,8 +198,10 @@ impl MDBook {
             root,
             config,
             book,
+            translations: BTreeMap::new(),
             renderers,
             preprocessors,
+            diagnostics_from,
         })
     }
 
@@ -173,41 +258,170 @@ impl MDBook {
     pub fn build(&self) -> Result<()> {
         info!("Book building has started");
 
+        self.check_chapters()?;
+
         for renderer in &self.renderers {
-            self.execute_build_process(&**renderer)?;
+            for (language, book, config) in self.books_by_language() {
+                let mut build_dir = self.build_dir_for(renderer.name());
+                if let Some(language) = language {
+                    info!("Building the \"{}\" translation", language);
+                    build_dir = build_dir.join(language);
+                }
+
+                self.render_book(book.clone(), config, build_dir, None, &**renderer)?;
+            }
+        }
+
+        self.deny_warnings()
+    }
+
+    /// Fail if `build.deny-warnings` is set and warnings were logged since
+    /// the book started loading, listing all of them.
+    ///
+    /// Warnings are only seen if the logger is wrapped in a
+    /// `utils::diagnostics::DiagnosticCollector`.
+    fn deny_warnings(&self) -> Result<()> {
+        if !self.config.build.deny_warnings {
+            return Ok(());
+        }
+
+        let warnings: Vec<_> = diagnostics::since(self.diagnostics_from)
+            .into_iter()
+            .filter(|diagnostic| diagnostic.level == log::Level::Warn)
+            .map(|diagnostic| diagnostic.message)
+            .collect();
+        if !warnings.is_empty() {
+            bail!(
+                "Warnings are denied, but {} warning(s) were emitted:\n\n{}",
+                warnings.len(),
+                warnings.join("\n")
+            );
+        }
+        Ok(())
+    }
+
+    /// The book of each language, with the config to build it with: every
+    /// translation of a multilingual book, or just `book` otherwise.
+    fn books_by_language(&self) -> Vec<(Option<&str>, &Book, Config)> {
+        if self.translations.is_empty() {
+            return vec![(None, &self.book, self.config.clone())];
         }
 
+        self.translations
+            .iter()
+            .map(|(language, book)| {
+                let mut config = self.config.clone();
+                config.book.src = config.book.src.join(language);
+                config.book.language = Some(language.clone());
+                (Some(language.as_str()), book, config)
+            })
+            .collect()
+    }
+
+    /// Run `build.chapter-check-command` against each chapter's source file,
+    /// in every language, failing if it doesn't succeed for all of them.
+    fn check_chapters(&self) -> Result<()> {
+        let command = match self.config.build.chapter_check_command {
+            Some(ref command) => command,
+            None => return Ok(()),
+        };
+
+        let mut failures = Vec::new();
+        let chapters = self
+            .books_by_language()
+            .into_iter()
+            .flat_map(|(_, book, config)| {
+                let src_dir = config.source_dir(&self.root);
+                book.iter().filter_map(move |item| match item {
+                    BookItem::Chapter(ch) => Some((ch, src_dir.clone())),
+                    _ => None,
+                })
+            });
+        for (ch, src_dir) in chapters {
+            let source_path = match ch.source_path {
+                Some(ref path) => src_dir.join(path),
+                None => continue,
+            };
+
+            let file = source_path.to_string_lossy();
+            let mut words = Shlex::new(command).map(|word| word.replace("{file}", &file));
+            let exe = match words.next() {
+                Some(exe) => exe,
+                None => bail!("build.chapter-check-command is empty"),
+            };
+
+            debug!("Checking {}", source_path.display());
+            let output = Command::new(&exe)
+                .args(words)
+                .current_dir(&self.root)
+                .output()
+                .with_context(|| format!("Unable to run the chapter check command `{}`", exe))?;
+
+            if !output.status.success() {
+                failures.push(format!(
+                    "{} ({}): {}\n{}",
+                    ch.name,
+                    source_path.display(),
+                    output.status,
+                    String::from_utf8_lossy(&output.stderr).trim_end()
+                ));
+            }
+        }
+
+        if !failures.is_empty() {
+            bail!(
+                "The chapter check command failed for {} chapter(s):\n\n{}",
+                failures.len(),
+                failures.join("\n\n")
+            );
+        }
         Ok(())
     }
 
     /// Run the entire build process for a particular [`Renderer`].
     pub fn execute_build_process(&self, renderer: &dyn Renderer) -> Result<()> {
-        let mut preprocessed_book = self.book.clone();
+        let build_dir = self.build_dir_for(renderer.name());
+        self.render_book(
+            self.book.clone(),
+            self.config.clone(),
+            build_dir,
+            None,
+            renderer,
+        )
+    }
+
+    /// Preprocess `book` and render it into `build_dir` with `config`,
+    /// telling the renderer if only some of the chapters changed.
+    fn render_book(
+        &self,
+        book: Book,
+        config: Config,
+        build_dir: PathBuf,
+        changed_chapters: Option<&[PathBuf]>,
+        renderer: &dyn Renderer,
+    ) -> Result<()> {
+        let mut preprocessed_book = book;
         let preprocess_ctx = PreprocessorContext::new(
             self.root.clone(),
-            self.config.clone(),
+            config.clone(),
             renderer.name().to_string(),
         );
 
         for preprocessor in &self.preprocessors {
-            if preprocessor_should_run(&**preprocessor, renderer, &self.config) {
+            if preprocessor_should_run(&**preprocessor, renderer, &config) {
                 debug!("Running the {} preprocessor.", preprocessor.name());
                 preprocessed_book = preprocessor.run(&preprocess_ctx, preprocessed_book)?;
             }
         }
+        // Done after the preprocessors, so included files are covered too.
+        remove_disabled_code_blocks(&mut preprocessed_book, &config.build.features);
 
-        let name = renderer.name();
-        let build_dir = self.build_dir_for(name);
-
-        let mut render_context = RenderContext::new(
-            self.root.clone(),
-            preprocessed_book,
-            self.config.clone(),
-            build_dir,
-        );
+        let mut render_context =
+            RenderContext::new(self.root.clone(), preprocessed_book, config, build_dir);
         render_context
             .chapter_titles
             .extend(preprocess_ctx.chapter_titles.borrow_mut().drain());
+        render_context.changed_chapters = changed_chapters.map(<[PathBuf]>::to_vec);
 
         info!("Running the {} backend", renderer.name());
         renderer
@@ -239,65 +453,69 @@ impl MDBook {
 
         let temp_dir = TempFileBuilder::new().prefix("mdbook-").tempdir()?;
 
-        // FIXME: Is "test" the proper renderer name to use here?
-        let preprocess_context =
-            PreprocessorContext::new(self.root.clone(), self.config.clone(), "test".to_string());
-
-        let book = LinkPreprocessor::new().run(&preprocess_context, self.book.clone())?;
-        // Index Preprocessor is disabled so that chapter paths continue to point to the
-        // actual markdown files.
-
         let mut failed = false;
-        for item in book.iter() {
-            if let BookItem::Chapter(ref ch) = *item {
-                let chapter_path = match ch.path {
-                    Some(ref path) if !path.as_os_str().is_empty() => path,
-                    _ => continue,
-                };
-
-                let path = self.source_dir().join(&chapter_path);
-                info!("Testing file: {:?}", path);
-
-                // write preprocessed file to tempdir
-                let path = temp_dir.path().join(&chapter_path);
-                let mut tmpf = utils::fs::create_file(&path)?;
-                tmpf.write_all(ch.content.as_bytes())?;
-
-                let mut cmd = Command::new("rustdoc");
-                cmd.arg(&path).arg("--test").args(&library_args);
-
-                if let Some(edition) = self.config.rust.edition {
-                    match edition {
-                        RustEdition::E2015 => {
-                            cmd.args(&["--edition", "2015"]);
-                        }
-                        RustEdition::E2018 => {
-                            cmd.args(&["--edition", "2018"]);
-                        }
-                        RustEdition::E2021 => {
-                            cmd.args(&["--edition", "2021"])
-                                .args(&["-Z", "unstable-options"]);
+        for (_, book, config) in self.books_by_language() {
+            // FIXME: Is "test" the proper renderer name to use here?
+            let preprocess_context =
+                PreprocessorContext::new(self.root.clone(), config.clone(), "test".to_string());
+
+            let mut book = LinkPreprocessor::new().run(&preprocess_context, book.clone())?;
+            remove_disabled_code_blocks(&mut book, &config.build.features);
+            // Index Preprocessor is disabled so that chapter paths continue to point to the
+            // actual markdown files.
+
+            let src_dir = config.source_dir(&self.root);
+            for item in book.iter() {
+                if let BookItem::Chapter(ref ch) = *item {
+                    let chapter_path = match ch.path {
+                        Some(ref path) if !path.as_os_str().is_empty() => path,
+                        _ => continue,
+                    };
+
+                    let path = src_dir.join(&chapter_path);
+                    info!("Testing file: {:?}", path);
+
+                    // write preprocessed file to tempdir
+                    let path = temp_dir.path().join(&chapter_path);
+                    let mut tmpf = utils::fs::create_file(&path)?;
+                    tmpf.write_all(ch.content.as_bytes())?;
+
+                    let mut cmd = Command::new("rustdoc");
+                    cmd.arg(&path).arg("--test").args(&library_args);
+
+                    if let Some(edition) = self.config.rust.edition {
+                        match edition {
+                            RustEdition::E2015 => {
+                                cmd.args(&["--edition", "2015"]);
+                            }
+                            RustEdition::E2018 => {
+                                cmd.args(&["--edition", "2018"]);
+                            }
+                            RustEdition::E2021 => {
+                                cmd.args(&["--edition", "2021"])
+                                    .args(&["-Z", "unstable-options"]);
+                            }
                         }
                     }
-                }
 
-                let output = cmd.output()?;
+                    let output = cmd.output()?;
 
-                if !output.status.success() {
-                    failed = true;
-                    error!(
-                        "rustdoc returned an error:\n\
-                        \n--- stdout\n{}\n--- stderr\n{}",
-                        String::from_utf8_lossy(&output.stdout),
-                        String::from_utf8_lossy(&output.stderr)
-                    );
+                    if !output.status.success() {
+                        failed = true;
+                        error!(
+                            "rustdoc returned an error:\n\
+                            \n--- stdout\n{}\n--- stderr\n{}",
+                            String::from_utf8_lossy(&output.stdout),
+                            String::from_utf8_lossy(&output.stderr)
+                        );
+                    }
                 }
             }
         }
         if failed {
             bail!("One or more tests failed");
         }
-        Ok(())
+        self.deny_warnings()
     }
 
     /// The logic for determining where a backend should put its build
@@ -336,7 +554,7 @@ impl MDBook {
 
     /// Get the directory containing this book's source files.
     pub fn source_dir(&self) -> PathBuf {
-        self.root.join(&self.config.book.src)
+        self.config.source_dir(&self.root)
     }
 
     /// Get the directory containing the theme resources for the book.
@@ -346,6 +564,163 @@ impl MDBook {
             .unwrap_or_default()
             .theme_dir(&self.root)
     }
+
+    /// The files and directories which, if changed, should trigger a rebuild:
+    /// `book.toml`, `SUMMARY.md` and each chapter's source file (for every
+    /// language of a multilingual book), the theme directory, and any local
+    /// files configured in `output.html`.
+    ///
+    /// Files pulled in by preprocessors (e.g. with `{{#include}}`) aren't
+    /// listed, so watchers will usually want to watch the whole source
+    /// directory as well.
+    pub fn watch_paths(&self) -> Vec<PathBuf> {
+        let mut paths = vec![self.root.join("book.toml")];
+
+        for (_, book, config) in self.books_by_language() {
+            let src_dir = config.source_dir(&self.root);
+            paths.push(src_dir.join("SUMMARY.md"));
+            for item in book.iter() {
+                if let BookItem::Chapter(ref ch) = *item {
+                    if let Some(ref source_path) = ch.source_path {
+                        paths.push(src_dir.join(source_path));
+                    }
+                }
+            }
+        }
+
+        paths.push(self.theme_dir());
+
+        if let Some(html) = self.config.html() {
+            let assets = html
+                .additional_css
+                .iter()
+                .chain(html.additional_js.iter())
+                .filter(|p| !utils::is_external_url(p))
+                .chain(html.inline_css.iter())
+                .chain(html.additional_static.iter())
+                .chain(html.favicon_png.iter())
+                .chain(html.favicon_svg.iter())
+                .chain(html.head_include.iter());
+            paths.extend(assets.map(|p| self.root.join(p)));
+        }
+
+        let mut seen = HashSet::new();
+        paths.retain(|p| seen.insert(p.clone()));
+        paths
+    }
+
+    /// The chapters (identified by their `path`) whose pages need
+    /// re-rendering after the `changed` files were edited, i.e. the chapters
+    /// whose source file is one of them or which include one of them with
+    /// `{{#include}}` and friends.
+    ///
+    /// Returns `None` if the whole book needs rebuilding instead, because one
+    /// of the files isn't part of any chapter, e.g. `book.toml`, `SUMMARY.md`,
+    /// the theme or an image. This is also the case for multilingual books.
+    pub fn chapters_affected_by(&self, changed: &[PathBuf]) -> Result<Option<Vec<PathBuf>>> {
+        if !self.translations.is_empty() {
+            return Ok(None);
+        }
+
+        // Expanding the links records every file each chapter reads in its
+        // source map.
+        let ctx = PreprocessorContext::new(self.root.clone(), self.config.clone(), "html".into());
+        let expanded = LinkPreprocessor::new().run(&ctx, self.book.clone())?;
+
+        let src_dir = self.source_dir();
+        let mut dependencies = Vec::new();
+        for item in expanded.iter() {
+            if let BookItem::Chapter(ref ch) = *item {
+                let path = match ch.path {
+                    Some(ref path) => path,
+                    None => continue,
+                };
+                if let Some(ref source_path) = ch.source_path {
+                    dependencies.push((canonical(&src_dir.join(source_path)), path));
+                }
+                for span in ch.source_map.iter().flat_map(|map| map.spans()) {
+                    dependencies.push((canonical(&span.source), path));
+                }
+            }
+        }
+
+        // Chapters may include these too, but they affect every page anyway.
+        let everything = [
+            canonical(&self.root.join("book.toml")),
+            canonical(&src_dir.join("SUMMARY.md")),
+        ];
+        let theme_dir = canonical(&self.theme_dir());
+
+        let mut affected = Vec::new();
+        for file in changed {
+            let file = canonical(file);
+            if everything.contains(&file) || file.starts_with(&theme_dir) {
+                return Ok(None);
+            }
+
+            let mut found = false;
+            for (dependency, path) in &dependencies {
+                if *dependency == file {
+                    found = true;
+                    if !affected.contains(*path) {
+                        affected.push(path.to_path_buf());
+                    }
+                }
+            }
+            if !found {
+                debug!("{} isn't part of a chapter", file.display());
+                return Ok(None);
+            }
+        }
+
+        Ok(Some(affected))
+    }
+
+    /// Build the book again after only the given chapters changed, as
+    /// returned by [`chapters_affected_by()`]. The HTML renderer only
+    /// re-renders those chapters' pages, leaving the others in place, while
+    /// other renderers build the whole book.
+    ///
+    /// [`chapters_affected_by()`]: #method.chapters_affected_by
+    pub fn build_chapters(&self, chapters: &[PathBuf]) -> Result<()> {
+        if !self.translations.is_empty() {
+            return self.build();
+        }
+
+        info!("Rebuilding {} chapter(s)", chapters.len());
+        self.check_chapters()?;
+
+        for renderer in &self.renderers {
+            let build_dir = self.build_dir_for(renderer.name());
+            self.render_book(
+                self.book.clone(),
+                self.config.clone(),
+                build_dir,
+                Some(chapters),
+                &**renderer,
+            )?;
+        }
+
+        self.deny_warnings()
+    }
+}
+
+/// A path with `..` and symlinks resolved, so it can be compared with paths
+/// from other places, or the path itself if it doesn't exist.
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Make sure `output.html.head-include` names a file, so a typo is reported
+/// when the book is loaded rather than part way through rendering it.
+fn check_head_include(root: &Path, config: &Config) -> Result<()> {
+    if let Some(head_include) = config.html().and_then(|html| html.head_include.as_ref()) {
+        let path = root.join(head_include);
+        if !path.is_file() {
+            bail!("The head include {} doesn't exist", path.display());
+        }
+    }
+    Ok(())
 }
 
 /// Look at the `Config` and try to figure out what renderers to use.
@@ -372,13 +747,6 @@ fn determine_renderers(config: &Config) -> Vec<Box<dyn Renderer>> {
     renderers
 }
 
-fn default_preprocessors() -> Vec<Box<dyn Preprocessor>> {
-    vec![
-        Box::new(LinkPreprocessor::new()),
-        Box::new(IndexPreprocessor::new()),
-    ]
-}
-
 fn is_default_preprocessor(pre: &dyn Preprocessor) -> bool {
     let name = pre.name();
     name == LinkPreprocessor::NAME || name == IndexPreprocessor::NAME
@@ -386,21 +754,19 @@ fn is_default_preprocessor(pre: &dyn Preprocessor) -> bool {
 
 /// Look at the `MDBook` and try to figure out what preprocessors to run.
 fn determine_preprocessors(config: &Config) -> Result<Vec<Box<dyn Preprocessor>>> {
-    let mut preprocessors = Vec::new();
-
-    if config.build.use_default_preprocessors {
-        preprocessors.extend(default_preprocessors());
-    }
-
-    if let Some(preprocessor_table) = config.get("preprocessor").and_then(Value::as_table) {
-        for key in preprocessor_table.keys() {
-            match key.as_ref() {
-                "links" => preprocessors.push(Box::new(LinkPreprocessor::new())),
-                "index" => preprocessors.push(Box::new(IndexPreprocessor::new())),
-                name => preprocessors.push(interpret_custom_preprocessor(
-                    name,
-                    &preprocessor_table[name],
-                )),
+    let mut preprocessors: Vec<Box<dyn Preprocessor>> = Vec::new();
+
+    for name in config.preprocessor_order()? {
+        match name.as_ref() {
+            LinkPreprocessor::NAME => preprocessors.push(Box::new(LinkPreprocessor::new())),
+            IndexPreprocessor::NAME => preprocessors.push(Box::new(IndexPreprocessor::new())),
+            name => {
+                let key = format!("preprocessor.{}", name);
+                let table = config
+                    .get(&key)
+                    .cloned()
+                    .unwrap_or_else(|| Value::Table(Default::default()));
+                preprocessors.push(interpret_custom_preprocessor(name, &table));
             }
         }
     }
@@ -460,9 +826,22 @@ fn preprocessor_should_run(
     preprocessor.supports_renderer(renderer_name)
 }
 
+/// Drop the code blocks for features which aren't listed in
+/// `build.features` from every chapter.
+fn remove_disabled_code_blocks(book: &mut Book, features: &[String]) {
+    book.for_each_mut(|item| {
+        if let BookItem::Chapter(ref mut ch) = *item {
+            if let Cow::Owned(content) = utils::remove_disabled_code_blocks(&ch.content, features) {
+                ch.set_content(content);
+            }
+        }
+    });
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::fs;
     use std::str::FromStr;
     use toml::value::{Table, Value};
 
@@ -621,4 +1000,47 @@ mod tests {
         let got = preprocessor_should_run(&BoolPreprocessor(should_be), &html, &cfg);
         assert_eq!(got, should_be);
     }
+
+    #[test]
+    fn a_missing_head_include_fails_to_load() {
+        let temp = TempFileBuilder::new().prefix("book").tempdir().unwrap();
+        fs::create_dir(temp.path().join("src")).unwrap();
+        fs::write(temp.path().join("src/SUMMARY.md"), "# Summary\n").unwrap();
+        let mut config = Config::default();
+        config.set("output.html.head-include", "head.html").unwrap();
+
+        let err = match MDBook::load_with_config(temp.path(), config.clone()) {
+            Ok(_) => panic!("the book loaded without its head include"),
+            Err(err) => err,
+        };
+        assert!(err.to_string().contains("head.html"), "{}", err);
+
+        fs::write(temp.path().join("head.html"), "<meta name=\"x\">").unwrap();
+        MDBook::load_with_config(temp.path(), config).unwrap();
+    }
+
+    #[test]
+    fn overrides_are_applied_on_top_of_the_profile() {
+        let temp = TempFileBuilder::new().prefix("book").tempdir().unwrap();
+        fs::create_dir(temp.path().join("src")).unwrap();
+        fs::write(temp.path().join("src/SUMMARY.md"), "# Summary\n").unwrap();
+        fs::write(
+            temp.path().join("book.toml"),
+            "[book]\ntitle = \"File\"\ndescription = \"File\"\n\n\
+             [profile.release.book]\ntitle = \"Profile\"\ndescription = \"Profile\"\n",
+        )
+        .unwrap();
+
+        let md = MDBook::load(temp.path()).unwrap();
+        assert_eq!(md.config.book.title.as_deref(), Some("File"));
+
+        let md = MDBook::load_with_overrides(
+            temp.path(),
+            Some("release"),
+            &["book.description=Override"],
+        )
+        .unwrap();
+        assert_eq!(md.config.book.title.as_deref(), Some("Profile"));
+        assert_eq!(md.config.book.description.as_deref(), Some("Override"));
+    }
 }
@@ -0,0 +1,173 @@
+//! Tracking which file each line of a chapter's content came from, so
+//! messages about the content can point at the files an author edits.
+
+use std::path::{Path, PathBuf};
+
+/// Where the lines of a chapter's content came from.
+///
+/// The map is made of spans, each saying that the content from one line
+/// onwards came from a line of some file, until the next span starts. It is
+/// only as precise as the transforms which built it, so a span may cover
+/// lines which were rewritten along the way.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SourceMap {
+    spans: Vec<SourceSpan>,
+}
+
+/// A run of a chapter's lines which came from the same file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SourceSpan {
+    /// The first line of the chapter's content in this span, starting at 1.
+    pub line: usize,
+    /// The file the lines were read from, or the URL for lines downloaded
+    /// by `{{#include-url}}`.
+    pub source: PathBuf,
+    /// The line of `source` which `line` came from, starting at 1.
+    pub source_line: usize,
+}
+
+impl SourceMap {
+    /// The spans making up the map, in order.
+    pub fn spans(&self) -> &[SourceSpan] {
+        &self.spans
+    }
+
+    /// Find the file and line a line of the chapter's content came from.
+    pub fn lookup(&self, line: usize) -> Option<(&Path, usize)> {
+        let index = self.spans.iter().rposition(|span| span.line <= line)?;
+        let span = &self.spans[index];
+        Some((&span.source, span.source_line + (line - span.line)))
+    }
+
+    fn push(&mut self, line: usize, source: &Path, source_line: usize) {
+        if let Some(last) = self.spans.last_mut() {
+            let continues_last = last.source == source
+                && line >= last.line
+                && last.source_line + (line - last.line) == source_line;
+            if continues_last {
+                return;
+            }
+            if last.line == line {
+                // The last span didn't get any lines of its own.
+                self.spans.pop();
+            }
+        }
+
+        self.spans.push(SourceSpan {
+            line,
+            source: source.to_path_buf(),
+            source_line,
+        });
+    }
+}
+
+/// Builds a chapter's new content piece by piece, recording where each piece
+/// came from.
+///
+/// A transform which rewrites [`Chapter::content`] can use this instead of
+/// building the string by hand, then store the result's map in
+/// [`Chapter::source_map`].
+///
+/// [`Chapter::content`]: struct.Chapter.html#structfield.content
+/// [`Chapter::source_map`]: struct.Chapter.html#structfield.source_map
+#[derive(Debug, Default)]
+pub struct SourceMapBuilder {
+    content: String,
+    map: SourceMap,
+    line: usize,
+}
+
+impl SourceMapBuilder {
+    /// Create an empty builder.
+    pub fn new() -> SourceMapBuilder {
+        SourceMapBuilder {
+            line: 1,
+            ..Default::default()
+        }
+    }
+
+    /// Append `text`, which starts at line `source_line` of `source`.
+    pub fn push_str(&mut self, text: &str, source: &Path, source_line: usize) {
+        if text.is_empty() {
+            return;
+        }
+        if self.at_line_start() {
+            self.map.push(self.line, source, source_line);
+        } else if text.contains('\n') {
+            // The rest of the current line stays with the span it started in.
+            self.map.push(self.line + 1, source, source_line + 1);
+        }
+        self.append(text);
+    }
+
+    /// Append `text`, whose lines came from the places in `map`, such as the
+    /// result of a nested transform.
+    pub fn push_mapped(&mut self, text: &str, map: &SourceMap) {
+        if text.is_empty() {
+            return;
+        }
+        let mid_line = !self.at_line_start();
+        for span in map.spans() {
+            let (line, source_line) = if mid_line && span.line == 1 {
+                if !text.contains('\n') {
+                    continue;
+                }
+                (self.line + 1, span.source_line + 1)
+            } else {
+                (self.line + span.line - 1, span.source_line)
+            };
+            self.map.push(line, &span.source, source_line);
+        }
+        self.append(text);
+    }
+
+    /// The content built so far, and where it came from.
+    pub fn finish(self) -> (String, SourceMap) {
+        (self.content, self.map)
+    }
+
+    fn at_line_start(&self) -> bool {
+        self.content.is_empty() || self.content.ends_with('\n')
+    }
+
+    fn append(&mut self, text: &str) {
+        self.line += text.matches('\n').count();
+        self.content.push_str(text);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_are_looked_up_in_the_span_they_belong_to() {
+        let mut builder = SourceMapBuilder::new();
+        builder.push_str("# Chapter\n\n", Path::new("chapter.md"), 1);
+        builder.push_str("fn main() {}\n", Path::new("main.rs"), 3);
+        builder.push_str("\nThe end\n", Path::new("chapter.md"), 3);
+        let (content, map) = builder.finish();
+
+        assert_eq!(content, "# Chapter\n\nfn main() {}\n\nThe end\n");
+        assert_eq!(map.lookup(2), Some((Path::new("chapter.md"), 2)));
+        assert_eq!(map.lookup(3), Some((Path::new("main.rs"), 3)));
+        assert_eq!(map.lookup(5), Some((Path::new("chapter.md"), 4)));
+        assert_eq!(map.spans().len(), 3);
+    }
+
+    #[test]
+    fn nested_maps_are_shifted_to_where_they_are_inserted() {
+        let mut inner = SourceMapBuilder::new();
+        inner.push_str("a\n", Path::new("inner.md"), 1);
+        inner.push_str("b\n", Path::new("other.md"), 10);
+        let (inner_content, inner_map) = inner.finish();
+
+        let mut outer = SourceMapBuilder::new();
+        outer.push_str("one\ntwo\n", Path::new("outer.md"), 1);
+        outer.push_mapped(&inner_content, &inner_map);
+        let (_, map) = outer.finish();
+
+        assert_eq!(map.lookup(3), Some((Path::new("inner.md"), 1)));
+        assert_eq!(map.lookup(4), Some((Path::new("other.md"), 10)));
+    }
+}
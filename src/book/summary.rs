@@ -42,6 +42,13 @@ use std::path::{Path, PathBuf};
 /// You can either use - or * to indicate a numbered chapter, the parser doesn't
 /// care but you'll probably want to stay consistent.
 ///
+/// Starting a list item with `[ ]` keeps it in the hierarchy without giving
+/// it (or anything nested under it) a section number.
+///
+/// ```markdown
+/// - [ ] [Introduction](relative/path/to/intro.md)
+/// ```
+///
 /// **Suffix Chapter:** After the numbered chapters you can add a couple of
 /// non-numbered chapters. They are the same as prefix chapters but come after
 /// the numbered chapters instead of before.
@@ -66,6 +73,46 @@ pub struct Summary {
     pub suffix_chapters: Vec<SummaryItem>,
 }
 
+impl Summary {
+    /// The numbered chapters, grouped under the part title which comes before
+    /// them in `SUMMARY.md`.
+    ///
+    /// Chapters before the first part title are in a part without a title,
+    /// which is left out if there aren't any.
+    pub fn parts(&self) -> Vec<SummaryPart<'_>> {
+        let mut parts = vec![SummaryPart {
+            title: None,
+            chapters: Vec::new(),
+        }];
+
+        for item in &self.numbered_chapters {
+            match item {
+                SummaryItem::PartTitle(title) => parts.push(SummaryPart {
+                    title: Some(title),
+                    chapters: Vec::new(),
+                }),
+                SummaryItem::Link(link) => parts.last_mut().unwrap().chapters.push(link),
+                SummaryItem::Separator => {}
+            }
+        }
+
+        if parts[0].chapters.is_empty() {
+            parts.remove(0);
+        }
+        parts
+    }
+}
+
+/// A part of the numbered chapters, as returned by [`Summary::parts()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SummaryPart<'a> {
+    /// The part's title, or `None` for the chapters before the first title.
+    pub title: Option<&'a str>,
+    /// The part's top-level chapters. Anything nested under them belongs to
+    /// the same part.
+    pub chapters: Vec<&'a Link>,
+}
+
 /// A struct representing an entry in the `SUMMARY.md`, possibly with nested
 /// entries.
 ///
@@ -209,7 +256,10 @@ macro_rules! collect_events {
 
 impl<'a> SummaryParser<'a> {
     fn new(text: &str) -> SummaryParser<'_> {
-        let pulldown_parser = pulldown_cmark::Parser::new(text).into_offset_iter();
+        // Task list markers are how a chapter opts out of being numbered.
+        let pulldown_parser =
+            pulldown_cmark::Parser::new_ext(text, pulldown_cmark::Options::ENABLE_TASKLISTS)
+                .into_offset_iter();
 
         SummaryParser {
             src: text,
@@ -232,6 +282,7 @@ impl<'a> SummaryParser<'a> {
 
     /// Parse the text the `SummaryParser` was created with.
     fn parse(mut self) -> Result<Summary> {
+        self.check_indentation()?;
         let title = self.parse_title();
 
         let prefix_chapters = self
@@ -333,7 +384,9 @@ impl<'a> SummaryParser<'a> {
 
     /// Finishes parsing a link once the `Event::Start(Tag::Link(..))` has been opened.
     fn parse_link(&mut self, href: String) -> Link {
-        let href = href.replace("%20", " ");
+        // Paths written on Windows may use `\` as the separator, which
+        // wouldn't be found anywhere else.
+        let href = href.replace("%20", " ").replace('\\', "/");
         let link_content = collect_events!(self.stream, end Tag::Link(..));
         let name = stringify_events(link_content);
 
@@ -388,7 +441,7 @@ impl<'a> SummaryParser<'a> {
                     // will be numbered from 1. We need to manually go back and update
                     // them
                     update_section_numbers(&mut bunch_of_items, 0, *root_items);
-                    *root_items += bunch_of_items.len() as u32;
+                    *root_items += count_numbered(&bunch_of_items) as u32;
                     items.extend(bunch_of_items);
                 }
                 Some(Event::Start(other_tag)) => {
@@ -448,7 +501,7 @@ impl<'a> SummaryParser<'a> {
         loop {
             match self.next_event() {
                 Some(Event::Start(Tag::Item)) => {
-                    let item = self.parse_nested_item(parent, items.len())?;
+                    let item = self.parse_nested_item(parent, count_numbered(&items))?;
                     items.push(item);
                 }
                 Some(Event::Start(Tag::List(..))) => {
@@ -458,12 +511,16 @@ impl<'a> SummaryParser<'a> {
                     }
                     // recurse to parse the nested list
                     let (_, last_item) = get_last_link(&mut items)?;
-                    let last_item_number = last_item
-                        .number
-                        .as_ref()
-                        .expect("All numbered chapters have numbers");
-
-                    let sub_items = self.parse_nested_numbered(last_item_number)?;
+                    let sub_items = match last_item.number {
+                        Some(ref number) => self.parse_nested_numbered(number)?,
+                        None => {
+                            // Everything nested under an unnumbered chapter is
+                            // unnumbered too.
+                            let mut sub_items = self.parse_nested_numbered(parent)?;
+                            clear_section_numbers(&mut sub_items);
+                            sub_items
+                        }
+                    };
 
                     last_item.nested_items = sub_items;
                 }
@@ -481,9 +538,17 @@ impl<'a> SummaryParser<'a> {
         parent: &SectionNumber,
         num_existing_items: usize,
     ) -> Result<SummaryItem> {
+        let mut numbered = true;
+
         loop {
             match self.next_event() {
                 Some(Event::Start(Tag::Paragraph)) => continue,
+                // `- [ ] [Title](path.md)` keeps its place in the tree but
+                // doesn't get a section number.
+                Some(Event::TaskListMarker(_)) => {
+                    numbered = false;
+                    continue;
+                }
                 Some(Event::Start(Tag::Link(_type, href, _title))) => {
                     let mut link = self.parse_link(href.to_string());
 
@@ -499,7 +564,9 @@ impl<'a> SummaryParser<'a> {
                             .unwrap_or("[draft]")
                     );
 
-                    link.number = Some(number);
+                    if numbered {
+                        link.number = Some(number);
+                    }
 
                     return Ok(SummaryItem::Link(link));
                 }
@@ -513,6 +580,26 @@ impl<'a> SummaryParser<'a> {
         }
     }
 
+    /// Nesting is worked out from the indentation of each list item, and an
+    /// indent which mixes tabs and spaces rarely nests the way it looks like
+    /// it does, so reject it up front.
+    fn check_indentation(&mut self) -> Result<()> {
+        let mut line_start = 0;
+        for line in self.src.split('\n') {
+            let indent = &line[..line.len() - line.trim_start_matches(&[' ', '\t'][..]).len()];
+            let is_list_item = line[indent.len()..].starts_with(&['-', '*', '+'][..]);
+
+            if is_list_item && indent.contains('\t') && indent.contains(' ') {
+                self.offset = line_start;
+                bail!(self.parse_error("don't mix tabs and spaces in SUMMARY.md indentation"));
+            }
+
+            line_start += line.len() + 1;
+        }
+
+        Ok(())
+    }
+
     fn parse_error<D: Display>(&self, msg: D) -> Error {
         let (line, col) = self.current_location();
         anyhow::anyhow!(
@@ -554,6 +641,26 @@ fn update_section_numbers(sections: &mut [SummaryItem], level: usize, by: u32) {
     }
 }
 
+fn clear_section_numbers(sections: &mut [SummaryItem]) {
+    for section in sections {
+        if let SummaryItem::Link(ref mut link) = *section {
+            link.number = None;
+            clear_section_numbers(&mut link.nested_items);
+        }
+    }
+}
+
+/// The number of links in `items` which have a section number.
+fn count_numbered(items: &[SummaryItem]) -> usize {
+    items
+        .iter()
+        .filter(|item| match item {
+            SummaryItem::Link(link) => link.number.is_some(),
+            _ => false,
+        })
+        .count()
+}
+
 /// Gets a pointer to the last `Link` in a list of `SummaryItem`s, and its
 /// index.
 fn get_last_link(links: &mut [SummaryItem]) -> Result<(usize, &mut Link)> {
@@ -713,6 +820,22 @@ mod tests {
         assert!(got.is_err());
     }
 
+    #[test]
+    fn backslashes_in_links_are_separators() {
+        let src = "# Summary\n\n- [Nested](chapter\\sub.md)\n";
+        let forward = "# Summary\n\n- [Nested](chapter/sub.md)\n";
+
+        let got = parse_summary(src).unwrap();
+        assert_eq!(got, parse_summary(forward).unwrap());
+
+        match &got.numbered_chapters[0] {
+            SummaryItem::Link(link) => {
+                assert_eq!(link.location, Some(PathBuf::from("chapter/sub.md")))
+            }
+            other => panic!("Unexpected item {:?}", other),
+        }
+    }
+
     #[test]
     fn parse_a_link() {
         let src = "[First](./first.md)";
@@ -734,6 +857,45 @@ mod tests {
         assert_eq!(got, should_be);
     }
 
+    #[test]
+    fn link_names_can_contain_brackets() {
+        let names = |src: &str| -> Vec<(String, Option<PathBuf>)> {
+            parse_summary(src)
+                .unwrap()
+                .numbered_chapters
+                .into_iter()
+                .filter_map(|item| match item {
+                    SummaryItem::Link(link) => Some((link.name, link.location)),
+                    _ => None,
+                })
+                .collect()
+        };
+
+        let src = "# Summary\n\n\
+                   - [See [note]](x.md)\n\
+                   - [Arrays \\[T; N\\]](arrays.md)\n\
+                   - [Nested [a [b] c]](nested.md)\n\
+                   - [Unbalanced \\]](unbalanced.md)\n";
+        assert_eq!(
+            names(src),
+            vec![
+                (String::from("See [note]"), Some(PathBuf::from("x.md"))),
+                (
+                    String::from("Arrays [T; N]"),
+                    Some(PathBuf::from("arrays.md"))
+                ),
+                (
+                    String::from("Nested [a [b] c]"),
+                    Some(PathBuf::from("nested.md"))
+                ),
+                (
+                    String::from("Unbalanced ]"),
+                    Some(PathBuf::from("unbalanced.md"))
+                ),
+            ]
+        );
+    }
+
     #[test]
     fn parse_a_numbered_chapter() {
         let src = "- [First](./first.md)\n";
@@ -785,6 +947,38 @@ mod tests {
         assert_eq!(got, should_be);
     }
 
+    #[test]
+    fn commented_out_nested_chapters_keep_the_structure() {
+        let src = "- [First](./first.md)\n  <!-- - [Draft](./draft.md) -->\n  - [Nested](./nested.md)\n  <!--\n  Still to be written:\n  - [Later](./later.md)\n  -->\n- [Second](./second.md)";
+
+        let should_be = vec![
+            SummaryItem::Link(Link {
+                name: String::from("First"),
+                location: Some(PathBuf::from("./first.md")),
+                number: Some(SectionNumber(vec![1])),
+                nested_items: vec![SummaryItem::Link(Link {
+                    name: String::from("Nested"),
+                    location: Some(PathBuf::from("./nested.md")),
+                    number: Some(SectionNumber(vec![1, 1])),
+                    nested_items: Vec::new(),
+                })],
+            }),
+            SummaryItem::Link(Link {
+                name: String::from("Second"),
+                location: Some(PathBuf::from("./second.md")),
+                number: Some(SectionNumber(vec![2])),
+                nested_items: Vec::new(),
+            }),
+        ];
+
+        let mut parser = SummaryParser::new(src);
+        let got = parser
+            .parse_numbered(&mut 0, &mut SectionNumber::default())
+            .unwrap();
+
+        assert_eq!(got, should_be);
+    }
+
     #[test]
     fn parse_numbered_chapters_separated_by_comment() {
         let src = "- [First](./first.md)\n<!-- this is a comment -->\n- [Second](./second.md)";
@@ -880,6 +1074,50 @@ mod tests {
         assert_eq!(got, should_be);
     }
 
+    #[test]
+    fn task_list_items_are_unnumbered() {
+        let src = "- [ ] [Intro](./intro.md)\n    - [Aside](./aside.md)\n- [First](./first.md)\n    - [ ] [Note](./note.md)\n    - [Nested](./nested.md)\n";
+        let should_be = vec![
+            SummaryItem::Link(Link {
+                name: String::from("Intro"),
+                location: Some(PathBuf::from("./intro.md")),
+                number: None,
+                nested_items: vec![SummaryItem::Link(Link {
+                    name: String::from("Aside"),
+                    location: Some(PathBuf::from("./aside.md")),
+                    number: None,
+                    nested_items: Vec::new(),
+                })],
+            }),
+            SummaryItem::Link(Link {
+                name: String::from("First"),
+                location: Some(PathBuf::from("./first.md")),
+                number: Some(SectionNumber(vec![1])),
+                nested_items: vec![
+                    SummaryItem::Link(Link {
+                        name: String::from("Note"),
+                        location: Some(PathBuf::from("./note.md")),
+                        number: None,
+                        nested_items: Vec::new(),
+                    }),
+                    SummaryItem::Link(Link {
+                        name: String::from("Nested"),
+                        location: Some(PathBuf::from("./nested.md")),
+                        number: Some(SectionNumber(vec![1, 1])),
+                        nested_items: Vec::new(),
+                    }),
+                ],
+            }),
+        ];
+
+        let mut parser = SummaryParser::new(src);
+        let got = parser
+            .parse_numbered(&mut 0, &mut SectionNumber::default())
+            .unwrap();
+
+        assert_eq!(got, should_be);
+    }
+
     #[test]
     fn an_empty_link_location_is_a_draft_chapter() {
         let src = "- [Empty]()\n";
@@ -1077,4 +1315,51 @@ mod tests {
         let got = parser.parse_affix(false).unwrap();
         assert_eq!(got, should_be);
     }
+
+    #[test]
+    fn numbered_chapters_are_grouped_by_part() {
+        let src = "# Summary\n\n[Intro](intro.md)\n\n# Basics\n\n- [First](first.md)\n\
+                   \t- [Nested](nested.md)\n- [Second](second.md)\n\n\
+                   # Advanced\n\n- [Third](third.md)\n";
+        let summary = parse_summary(src).unwrap();
+
+        let parts: Vec<_> = summary
+            .parts()
+            .into_iter()
+            .map(|part| {
+                let names: Vec<_> = part.chapters.iter().map(|ch| ch.name.as_str()).collect();
+                (part.title, names)
+            })
+            .collect();
+
+        assert_eq!(
+            parts,
+            vec![
+                (Some("Basics"), vec!["First", "Second"]),
+                (Some("Advanced"), vec!["Third"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn chapters_before_the_first_part_title_have_an_untitled_part() {
+        let src = "# Summary\n\n- [First](first.md)\n\n# Part\n\n- [Second](second.md)\n";
+        let summary = parse_summary(src).unwrap();
+
+        let titles: Vec<_> = summary.parts().iter().map(|part| part.title).collect();
+        assert_eq!(titles, vec![None, Some("Part")]);
+    }
+
+    #[test]
+    fn mixed_tab_and_space_indentation_is_an_error() {
+        let src = "# Summary\n\n- [First](./first.md)\n\t  - [Nested](./nested.md)\n";
+        let err = parse_summary(src).unwrap_err().to_string();
+
+        assert!(err.contains("line 4"), "{}", err);
+        assert!(err.contains("don't mix tabs and spaces"), "{}", err);
+
+        let tabs = "# Summary\n\n- [First](./first.md)\n\t- [Nested](./nested.md)\n";
+        let spaces = "# Summary\n\n- [First](./first.md)\n    - [Nested](./nested.md)\n";
+        assert_eq!(parse_summary(tabs).unwrap(), parse_summary(spaces).unwrap());
+    }
 }
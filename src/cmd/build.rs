@@ -1,6 +1,8 @@
 use crate::{get_book_dir, open};
-use clap::{App, ArgMatches, SubCommand};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use log::Level;
 use mdbook::errors::Result;
+use mdbook::utils::diagnostics::{self, Diagnostic};
 use mdbook::MDBook;
 
 // Create clap subcommand arguments
@@ -17,16 +19,109 @@ pub fn make_subcommand<'a, 'b>() -> App<'a, 'b> {
              (Defaults to the Current Directory when omitted)'",
         )
         .arg_from_usage("-o, --open 'Opens the compiled book in a web browser'")
+        .arg_from_usage(
+            "--deny-warnings 'Fails the build if any warnings were emitted{n}\
+             (the same as setting build.deny-warnings in book.toml)'",
+        )
+        .arg_from_usage(
+            "--profile=[profile] 'Applies the [profile.<profile>] table from book.toml{n}\
+             on top of the rest of the configuration'",
+        )
+        .arg(
+            Arg::with_name("config")
+                .short("c")
+                .long("config")
+                .value_name("key=value")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .help(
+                    "Overrides a config value, e.g. `-c output.html.curly-quotes=true`{n}\
+                     The value is parsed as JSON, falling back to a string.{n}\
+                     Takes precedence over book.toml and environment variables.",
+                ),
+        )
+        .arg(
+            Arg::with_name("message-format")
+                .long("message-format")
+                .value_name("format")
+                .takes_value(true)
+                .possible_values(&["human", "json"])
+                .help(
+                    "How to report warnings and errors. `json` also prints each of them{n}\
+                     to stdout as a JSON object, followed by a summary of the build.",
+                ),
+        )
 }
 
 // Build command implementation
 pub fn execute(args: &ArgMatches) -> Result<()> {
+    if args.value_of("message-format") != Some("json") {
+        return build(args);
+    }
+
+    let start = diagnostics::count();
+    let result = build(args);
+
+    let mut logged = diagnostics::since(start);
+    if let Err(ref e) = result {
+        logged.push(Diagnostic {
+            level: Level::Error,
+            message: format!("{:#}", e),
+            file: None,
+            line: None,
+        });
+    }
+    for diagnostic in &logged {
+        print_diagnostic(diagnostic);
+    }
+
+    let count = |level| logged.iter().filter(|d| d.level == level).count();
+    let summary = serde_json::json!({
+        "reason": "build-finished",
+        "success": result.is_ok(),
+        "warnings": count(Level::Warn),
+        "errors": count(Level::Error),
+    });
+    println!("{}", summary);
+
+    result
+}
+
+/// Print a warning or error to stdout as a JSON record.
+fn print_diagnostic(diagnostic: &Diagnostic) {
+    let level = match diagnostic.level {
+        Level::Error => "error",
+        Level::Warn => "warning",
+        _ => "note",
+    };
+    let mut record = serde_json::json!({
+        "reason": "diagnostic",
+        "level": level,
+        "message": diagnostic.message,
+    });
+    if let Some(ref file) = diagnostic.file {
+        record["file"] = file.display().to_string().into();
+    }
+    if let Some(line) = diagnostic.line {
+        record["line"] = line.into();
+    }
+    println!("{}", record);
+}
+
+fn build(args: &ArgMatches) -> Result<()> {
     let book_dir = get_book_dir(args);
-    let mut book = MDBook::load(&book_dir)?;
+    let profile = args.value_of("profile");
+    let overrides: Vec<&str> = args.values_of("config").into_iter().flatten().collect();
+
+    let mut book = MDBook::load_with_overrides(&book_dir, profile, &overrides)?;
 
     if let Some(dest_dir) = args.value_of("dest-dir") {
         book.config.build.build_dir = dest_dir.into();
     }
+    if args.is_present("deny-warnings") {
+        book.config.build.deny_warnings = true;
+    }
 
     book.build()?;
 
@@ -33,6 +33,15 @@ pub fn make_subcommand<'a, 'b>() -> App<'a, 'b> {
                 .help("Creates a VCS ignore file (i.e. .gitignore)")
                 .required(false),
         )
+        .arg(
+            Arg::with_name("vcs")
+                .long("vcs")
+                .takes_value(true)
+                .possible_values(&["git", "hg", "none"])
+                .conflicts_with("ignore")
+                .help("Creates an ignore file for the given VCS (.gitignore or .hgignore)")
+                .required(false),
+        )
 }
 
 // Init command implementation
@@ -59,7 +68,13 @@ pub fn execute(args: &ArgMatches) -> Result<()> {
         }
     }
 
-    if let Some(ignore) = args.value_of("ignore") {
+    if let Some(vcs) = args.value_of("vcs") {
+        match vcs {
+            "git" => builder.create_gitignore(true),
+            "hg" => builder.create_hgignore(true),
+            _ => builder.create_gitignore(false),
+        };
+    } else if let Some(ignore) = args.value_of("ignore") {
         match ignore {
             "git" => builder.create_gitignore(true),
             _ => builder.create_gitignore(false),
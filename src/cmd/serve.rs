@@ -1,6 +1,7 @@
 #[cfg(feature = "watch")]
 use super::watch;
 use crate::{get_book_dir, open};
+use anyhow::Context;
 use clap::{App, Arg, ArgMatches, SubCommand};
 use futures_util::sink::SinkExt;
 use futures_util::StreamExt;
@@ -8,11 +9,16 @@ use mdbook::errors::*;
 use mdbook::utils;
 use mdbook::utils::fs::get_404_output_file;
 use mdbook::MDBook;
+#[cfg(feature = "watch")]
+use std::cell::RefCell;
 use std::net::{SocketAddr, ToSocketAddrs};
 use std::path::PathBuf;
 use tokio::sync::broadcast;
+use warp::filters::fs::File;
+use warp::http::header::{HeaderValue, CONTENT_SECURITY_POLICY};
+use warp::reply::Response;
 use warp::ws::Message;
-use warp::Filter;
+use warp::{Filter, Reply};
 
 /// The HTTP endpoint for the websocket used to trigger reloads when a file changes.
 const LIVE_RELOAD_ENDPOINT: &str = "__livereload";
@@ -88,13 +94,20 @@ pub fn execute(args: &ArgMatches) -> Result<()> {
         .and_then(std::convert::identity) // flatten
         .map(ToString::to_string);
     let file_404 = get_404_output_file(&input_404);
+    let csp = match book.config.html_config().and_then(|html| html.serve.csp) {
+        Some(csp) => Some(
+            HeaderValue::from_str(&csp)
+                .with_context(|| format!("Invalid output.html.serve.csp: {:?}", csp))?,
+        ),
+        None => None,
+    };
 
     // A channel used to broadcast to any websockets to reload when a file changes.
     let (tx, _rx) = tokio::sync::broadcast::channel::<Message>(100);
 
     let reload_tx = tx.clone();
     let thread_handle = std::thread::spawn(move || {
-        serve(build_dir, sockaddr, reload_tx, &file_404);
+        serve(build_dir, sockaddr, reload_tx, &file_404, csp);
     });
 
     let serving_url = format!("http://{}", address);
@@ -104,6 +117,11 @@ pub fn execute(args: &ArgMatches) -> Result<()> {
         open(serving_url);
     }
 
+    // What the last build looked like, to tell whether only some chapters
+    // need re-rendering after a change.
+    #[cfg(feature = "watch")]
+    let last_build = RefCell::new((book.book.clone(), book.config.clone()));
+
     #[cfg(feature = "watch")]
     watch::trigger_on_change(&book, move |paths, book_dir| {
         info!("Files changed: {:?}", paths);
@@ -112,7 +130,21 @@ pub fn execute(args: &ArgMatches) -> Result<()> {
         // FIXME: This area is really ugly because we need to re-set livereload :(
         let result = MDBook::load(&book_dir).and_then(|mut b| {
             update_config(&mut b);
-            b.build()
+
+            let mut last_build = last_build.borrow_mut();
+            let same_layout = b.config == last_build.1 && b.book.structurally_eq(&last_build.0);
+            let affected = if same_layout {
+                b.chapters_affected_by(&paths)?
+            } else {
+                None
+            };
+            match affected {
+                Some(chapters) => b.build_chapters(&chapters)?,
+                None => b.build()?,
+            }
+
+            *last_build = (b.book.clone(), b.config.clone());
+            Ok(())
         });
 
         if let Err(e) = result {
@@ -134,7 +166,27 @@ async fn serve(
     address: SocketAddr,
     reload_tx: broadcast::Sender<Message>,
     file_404: &str,
+    csp: Option<HeaderValue>,
 ) {
+    let routes = routes(build_dir, reload_tx, file_404, csp);
+
+    std::panic::set_hook(Box::new(move |panic_info| {
+        // exit if serve panics
+        error!("Unable to serve: {}", panic_info);
+        std::process::exit(1);
+    }));
+
+    warp::serve(routes).run(address).await;
+}
+
+/// The routes `mdbook serve` answers requests with: the livereload websocket,
+/// the files in `build_dir`, and `file_404` for anything else.
+fn routes(
+    build_dir: PathBuf,
+    reload_tx: broadcast::Sender<Message>,
+    file_404: &str,
+    csp: Option<HeaderValue>,
+) -> impl Filter<Extract = impl Reply, Error = warp::Rejection> + Clone {
     // A warp Filter which captures `reload_tx` and provides an `rx` copy to
     // receive reload messages.
     let sender = warp::any().map(move || reload_tx.subscribe());
@@ -156,17 +208,73 @@ async fn serve(
             })
         });
     // A warp Filter that serves from the filesystem.
-    let book_route = warp::fs::dir(build_dir.clone());
+    let book_csp = csp.clone();
+    let book_route =
+        warp::fs::dir(build_dir.clone()).map(move |file: File| with_csp(file, book_csp.as_ref()));
     // The fallback route for 404 errors
-    let fallback_route = warp::fs::file(build_dir.join(file_404))
-        .map(|reply| warp::reply::with_status(reply, warp::http::StatusCode::NOT_FOUND));
-    let routes = livereload.or(book_route).or(fallback_route);
+    let fallback_route = warp::fs::file(build_dir.join(file_404)).map(move |file: File| {
+        warp::reply::with_status(
+            with_csp(file, csp.as_ref()),
+            warp::http::StatusCode::NOT_FOUND,
+        )
+    });
+    livereload.or(book_route).or(fallback_route)
+}
 
-    std::panic::set_hook(Box::new(move |panic_info| {
-        // exit if serve panics
-        error!("Unable to serve: {}", panic_info);
-        std::process::exit(1);
-    }));
+/// Turn a file into a response, adding the `Content-Security-Policy` header
+/// if it's an HTML page and a policy was configured.
+fn with_csp(file: File, csp: Option<&HeaderValue>) -> Response {
+    let is_html = file
+        .path()
+        .extension()
+        .map(|ext| ext == "html")
+        .unwrap_or(false);
+    let mut response = file.into_response();
+    if let (true, Some(csp)) = (is_html, csp) {
+        response
+            .headers_mut()
+            .insert(CONTENT_SECURITY_POLICY, csp.clone());
+    }
+    response
+}
 
-    warp::serve(routes).run(address).await;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[tokio::test]
+    async fn csp_is_only_sent_with_html_pages() {
+        let temp = tempfile::tempdir().unwrap();
+        fs::write(temp.path().join("index.html"), "<p>Hello</p>").unwrap();
+        fs::write(temp.path().join("404.html"), "<p>Not found</p>").unwrap();
+        fs::write(temp.path().join("book.js"), "").unwrap();
+
+        let (reload_tx, _) = broadcast::channel(1);
+        let csp = HeaderValue::from_static("default-src 'self'");
+        let routes = routes(temp.path().to_path_buf(), reload_tx, "404.html", Some(csp));
+
+        let page = warp::test::request()
+            .path("/index.html")
+            .reply(&routes)
+            .await;
+        assert_eq!(
+            page.headers().get(CONTENT_SECURITY_POLICY).unwrap(),
+            "default-src 'self'"
+        );
+
+        let script = warp::test::request().path("/book.js").reply(&routes).await;
+        assert!(script.headers().get(CONTENT_SECURITY_POLICY).is_none());
+
+        let missing = warp::test::request()
+            .path("/missing.html")
+            .reply(&routes)
+            .await;
+        assert_eq!(missing.status(), warp::http::StatusCode::NOT_FOUND);
+        assert_eq!(missing.body(), "<p>Not found</p>");
+        assert_eq!(
+            missing.headers().get(CONTENT_SECURITY_POLICY).unwrap(),
+            "default-src 'self'"
+        );
+    }
 }
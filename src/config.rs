@@ -49,22 +49,26 @@
 
 #![deny(missing_docs)]
 
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 use std::env;
 use std::fs::File;
 use std::io::Read;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::Mutex;
 use toml::value::Table;
 use toml::{self, Value};
 
 use crate::errors::*;
+use crate::preprocess::{IndexPreprocessor, LinkPreprocessor};
 use crate::utils::{self, toml_ext::TomlExt};
 
 /// The overall configuration object for MDBook, essentially an in-memory
 /// representation of `book.toml`.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub struct Config {
     /// Metadata about the book.
     pub book: BookConfig,
@@ -73,6 +77,32 @@ pub struct Config {
     /// Information about Rust language support.
     pub rust: RustConfig,
     rest: Value,
+    /// `output.html`, parsed once and kept in sync with `rest` by `set()`.
+    html: Option<HtmlConfig>,
+    /// The keys set by `update_from_env()`, and what they were set to.
+    env_overrides: Vec<(String, Value)>,
+}
+
+impl PartialEq for Config {
+    /// Two configs are equal if they have the same settings, no matter how
+    /// they came to be set.
+    fn eq(&self, other: &Config) -> bool {
+        // Destructured so that new fields aren't forgotten here.
+        let Config {
+            book,
+            build,
+            rust,
+            rest,
+            html,
+            env_overrides: _,
+        } = self;
+
+        *book == other.book
+            && *build == other.build
+            && *rust == other.rust
+            && *rest == other.rest
+            && *html == other.html
+    }
 }
 
 impl FromStr for Config {
@@ -85,6 +115,24 @@ impl FromStr for Config {
 }
 
 impl Config {
+    /// Load a `Config` from some string, like [`Config::from_str()`], except
+    /// that the legacy `book.toml` format is an error instead of being
+    /// converted with a warning.
+    ///
+    /// This is for books which have been migrated to the current format and
+    /// shouldn't silently go back to relying on the compatibility code.
+    pub fn from_str_strict(src: &str) -> Result<Config> {
+        let raw: Value = toml::from_str(src).with_context(|| "Invalid configuration file")?;
+        if is_legacy_format(&raw) {
+            bail!(
+                "The configuration uses the legacy book.toml format, move top level entries \
+                 like `title` under `[book]` and `output.html.destination` to `build.build-dir`"
+            );
+        }
+
+        raw.try_into().with_context(|| "Invalid configuration file")
+    }
+
     /// Load the configuration file from disk.
     pub fn from_disk<P: AsRef<Path>>(config_file: P) -> Result<Config> {
         let mut buffer = String::new();
@@ -129,6 +177,9 @@ impl Config {
     /// The latter case may be useful in situations where `mdbook` is invoked
     /// from a script or CI, where it sometimes isn't possible to update the
     /// `book.toml` before building.
+    ///
+    /// Setting a variable to `__MDBOOK_UNSET__` removes the key instead, see
+    /// [`Config::unset()`].
     pub fn update_from_env(&mut self) {
         debug!("Updating the config from environment variables");
 
@@ -137,8 +188,7 @@ impl Config {
 
         for (key, value) in overrides {
             trace!("{} => {}", key, value);
-            let parsed_value = serde_json::from_str(&value)
-                .unwrap_or_else(|_| serde_json::Value::String(value.to_string()));
+            let parsed_value = parse_override_value(&value);
 
             if key == "book" || key == "build" {
                 if let serde_json::Value::Object(ref map) = parsed_value {
@@ -146,12 +196,57 @@ impl Config {
                     for (k, v) in map {
                         let full_key = format!("{}.{}", key, k);
                         self.set(&full_key, v).expect("unreachable");
+                        self.record_env_override(full_key, v);
                     }
                     return;
                 }
             }
 
-            self.set(key, parsed_value).expect("unreachable");
+            if parsed_value == serde_json::Value::String(UNSET_SENTINEL.to_string()) {
+                self.unset(&key);
+                self.record_env_override(key, &parsed_value);
+                continue;
+            }
+
+            self.set(&key, &parsed_value).expect("unreachable");
+            self.record_env_override(key, &parsed_value);
+        }
+    }
+
+    /// The keys which [`Config::update_from_env()`] overrode, in the order it
+    /// applied them, along with their new values. Keys which were removed
+    /// with `__MDBOOK_UNSET__` are listed with that as their value.
+    pub fn env_overrides(&self) -> &[(String, Value)] {
+        &self.env_overrides
+    }
+
+    fn record_env_override(&mut self, key: String, value: &serde_json::Value) {
+        let value = Value::try_from(value).expect("unreachable");
+        self.env_overrides.push((key, value));
+    }
+
+    /// Apply a `key=value` override, as passed to `mdbook build -c`.
+    ///
+    /// Like with [`Config::update_from_env()`], the value is parsed as JSON,
+    /// falling back to a plain string, and `__MDBOOK_UNSET__` removes the key.
+    pub fn apply_override(&mut self, pair: &str) -> Result<()> {
+        let (key, value) = match pair.find('=') {
+            Some(ix) => (pair[..ix].trim(), &pair[ix + 1..]),
+            None => bail!(
+                "Config overrides should look like `key=value`, not {:?}",
+                pair
+            ),
+        };
+        if key.is_empty() {
+            bail!("Config override {:?} is missing a key", pair);
+        }
+
+        let value = parse_override_value(value);
+        if value == serde_json::Value::String(UNSET_SENTINEL.to_string()) {
+            self.unset(key);
+            Ok(())
+        } else {
+            self.set(key, value)
         }
     }
 
@@ -161,12 +256,15 @@ impl Config {
     /// `output.html.playground` will fetch the "playground" out of the html output
     /// table).
     pub fn get(&self, key: &str) -> Option<&Value> {
-        self.rest.read(key)
+        self.rest.read(&resolve_deprecated_key(key))
     }
 
     /// Fetch a value from the `Config` so you can mutate it.
+    ///
+    /// Changes made to `output.html` this way aren't picked up by
+    /// [`Config::html()`], use [`Config::set()`] for those instead.
     pub fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
-        self.rest.read_mut(key)
+        self.rest.read_mut(&resolve_deprecated_key(key))
     }
 
     /// Convenience method for getting the html renderer's configuration.
@@ -177,17 +275,54 @@ impl Config {
     /// HTML renderer is refactored to be less coupled to `mdbook` internals.
     #[doc(hidden)]
     pub fn html_config(&self) -> Option<HtmlConfig> {
-        match self
+        self.html.clone()
+    }
+
+    /// Get the html renderer's configuration, if there is an `[output.html]`
+    /// table.
+    ///
+    /// This is parsed once when the `Config` is loaded and updated whenever an
+    /// `output.html` key is changed with [`Config::set()`], so it is cheap to
+    /// call repeatedly.
+    pub fn html(&self) -> Option<&HtmlConfig> {
+        self.html.as_ref()
+    }
+
+    /// The directory containing the book's source files.
+    ///
+    /// A relative `book.src` is resolved against `book_root`, while an
+    /// absolute one is returned unchanged.
+    pub fn source_dir(&self, book_root: &Path) -> PathBuf {
+        if self.book.src.is_absolute() {
+            self.book.src.clone()
+        } else {
+            book_root.join(&self.book.src)
+        }
+    }
+
+    /// Whether the book is rendered with the renderer called `name`, i.e.
+    /// whether there is an `[output.<name>]` table.
+    ///
+    /// A book without any `[output]` tables is rendered with just the HTML
+    /// renderer.
+    pub fn has_renderer(&self, name: &str) -> bool {
+        match self.get("output").and_then(Value::as_table) {
+            Some(outputs) if !outputs.is_empty() => outputs.contains_key(name),
+            _ => name == "html",
+        }
+    }
+
+    fn refresh_html_config(&mut self) {
+        self.html = match self
             .get_deserialized_opt("output.html")
             .with_context(|| "Parsing configuration [output.html]")
         {
-            Ok(Some(config)) => Some(config),
-            Ok(None) => None,
+            Ok(config) => config,
             Err(e) => {
                 utils::log_backtrace(&e);
                 None
             }
-        }
+        };
     }
 
     /// Deprecated, use get_deserialized_opt instead.
@@ -217,11 +352,36 @@ impl Config {
             .transpose()
     }
 
+    /// Fetch an array from the config and deserialize each of its items.
+    ///
+    /// A missing key gives an empty `Vec`, while a key which is present but
+    /// isn't an array is an error.
+    pub fn get_array<T: DeserializeOwned>(&self, key: &str) -> Result<Vec<T>> {
+        match self.get(key) {
+            None => Ok(Vec::new()),
+            Some(Value::Array(items)) => items
+                .iter()
+                .enumerate()
+                .map(|(i, item)| {
+                    item.clone()
+                        .try_into()
+                        .with_context(|| format!("Couldn't deserialize item {} of {:?}", i, key))
+                })
+                .collect(),
+            Some(other) => bail!(
+                "Expected {:?} to be an array, found a {}",
+                key,
+                other.type_str()
+            ),
+        }
+    }
+
     /// Set a config key, clobbering any existing values along the way.
     ///
     /// The only way this can fail is if we can't serialize `value` into a
     /// `toml::Value`.
     pub fn set<S: Serialize, I: AsRef<str>>(&mut self, index: I, value: S) -> Result<()> {
+        let index = resolve_deprecated_key(index.as_ref());
         let index = index.as_ref();
 
         let value = Value::try_from(value)
@@ -233,11 +393,95 @@ impl Config {
             self.build.update_value(&index[6..], value);
         } else {
             self.rest.insert(index, value);
+
+            if index == "output" || index == "output.html" || index.starts_with("output.html.") {
+                self.refresh_html_config();
+            }
         }
 
         Ok(())
     }
 
+    /// Merge the `[profile.<name>]` table over the rest of the config.
+    ///
+    /// Tables are merged key by key, while any other value in the profile
+    /// replaces the base value outright. The `profile` table is removed
+    /// afterwards. It is an error if there is no such profile.
+    pub fn apply_profile(&mut self, name: &str) -> Result<()> {
+        let overrides = match self.rest.get("profile").and_then(|p| p.get(name)) {
+            Some(Value::Table(overrides)) => overrides.clone(),
+            Some(_) => bail!("[profile.{}] should be a table", name),
+            None => bail!("No profile called {:?} in the configuration", name),
+        };
+
+        let mut overrides = overrides;
+        let book = match overrides.remove("book") {
+            Some(book) => Some(merge_section(&self.book, book, name)?),
+            None => None,
+        };
+        let build = match overrides.remove("build") {
+            Some(build) => Some(merge_section(&self.build, build, name)?),
+            None => None,
+        };
+        let rust = match overrides.remove("rust") {
+            Some(rust) => Some(merge_section(&self.rust, rust, name)?),
+            None => None,
+        };
+
+        // Nothing is changed until the whole profile is known to be valid.
+        if let Some(book) = book {
+            self.book = book;
+        }
+        if let Some(build) = build {
+            self.build = build;
+        }
+        if let Some(rust) = rust {
+            self.rust = rust;
+        }
+        self.rest.delete("profile");
+        merge_values(&mut self.rest, Value::Table(overrides));
+        self.refresh_html_config();
+        Ok(())
+    }
+
+    /// Remove a config key, if it is set.
+    ///
+    /// Keys under `book` and `build` are reset to their default values.
+    pub fn unset(&mut self, key: &str) {
+        let key = resolve_deprecated_key(key);
+        let key = key.as_ref();
+
+        if let Some(key) = key.strip_prefix("book.") {
+            self.book.remove_value(key);
+        } else if let Some(key) = key.strip_prefix("build.") {
+            self.build.remove_value(key);
+        } else {
+            self.rest.delete(key);
+
+            if key == "output" || key == "output.html" || key.starts_with("output.html.") {
+                self.refresh_html_config();
+            }
+        }
+    }
+
+    /// Check for combinations of settings which contradict each other,
+    /// returning a description of each one that was found.
+    ///
+    /// These don't stop the book from building, but usually mean part of the
+    /// config is silently ignored.
+    pub fn validate(&self) -> Vec<String> {
+        let html = match self.html() {
+            Some(html) => html,
+            None => return Vec::new(),
+        };
+
+        HTML_CONFLICTS
+            .iter()
+            .filter(|(conflicts, _)| conflicts(html))
+            .map(|(_, message)| message.to_string())
+            .collect()
+    }
+
     /// Get the table associated with a particular renderer.
     pub fn get_renderer<I: AsRef<str>>(&self, index: I) -> Option<&Table> {
         let key = format!("output.{}", index.as_ref());
@@ -250,6 +494,108 @@ impl Config {
         self.get(&key).and_then(Value::as_table)
     }
 
+    /// Get the name and configuration of every preprocessor with a
+    /// `[preprocessor.<name>]` table, sorted by name.
+    ///
+    /// The tables are returned as-is, so keys such as `command`, `before` and
+    /// `after` are left for the caller to interpret.
+    pub fn preprocessors(&self) -> Vec<(String, &Value)> {
+        self.get("preprocessor")
+            .and_then(Value::as_table)
+            .map(|table| table.iter().map(|(name, v)| (name.clone(), v)).collect())
+            .unwrap_or_default()
+    }
+
+    /// The names of the chapter files which are rendered as their directory's
+    /// `index.html`, to pass to [`output_path_for`]. This is `README` when the
+    /// `index` preprocessor is enabled, which renames those files to
+    /// `index.md`, and nothing otherwise.
+    ///
+    /// [`output_path_for`]: ../book/fn.output_path_for.html
+    pub fn index_file_names(&self) -> Vec<String> {
+        let index_enabled = self.build.use_default_preprocessors
+            || self
+                .get(&format!("preprocessor.{}", IndexPreprocessor::NAME))
+                .is_some();
+        if index_enabled {
+            vec!["README".to_string()]
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Work out the order to run the preprocessors in. These are the builtin
+    /// `links` and `index` preprocessors (unless
+    /// `build.use-default-preprocessors` is turned off) and every preprocessor
+    /// with a `[preprocessor.<name>]` table.
+    ///
+    /// A preprocessor's table can list the preprocessors it has to run
+    /// `before` or `after`. Apart from that, the builtin preprocessors run
+    /// first and the rest follow in name order. It is an error for these
+    /// constraints to form a cycle.
+    pub fn preprocessor_order(&self) -> Result<Vec<String>> {
+        let mut names: Vec<String> = Vec::new();
+        if self.build.use_default_preprocessors {
+            names.push(LinkPreprocessor::NAME.to_string());
+            names.push(IndexPreprocessor::NAME.to_string());
+        }
+        for (name, _) in self.preprocessors() {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+
+        // Pairs of preprocessors where the first has to run before the second.
+        let mut edges = Vec::new();
+        for (name, table) in self.preprocessors() {
+            for &(key, runs_first) in &[("before", true), ("after", false)] {
+                let others = match table.get(key) {
+                    Some(Value::Array(others)) => others,
+                    Some(_) => bail!("`preprocessor.{}.{}` must be an array", name, key),
+                    None => continue,
+                };
+
+                for other in others {
+                    let other = other.as_str().with_context(|| {
+                        format!("`preprocessor.{}.{}` must only contain names", name, key)
+                    })?;
+                    if !names.iter().any(|n| n == other) {
+                        warn!(
+                            "`preprocessor.{}.{}` refers to \"{}\", which isn't enabled",
+                            name, key, other
+                        );
+                        continue;
+                    }
+
+                    let other = other.to_string();
+                    edges.push(if runs_first {
+                        (name.clone(), other)
+                    } else {
+                        (other, name.clone())
+                    });
+                }
+            }
+        }
+
+        let mut order = Vec::with_capacity(names.len());
+        while !names.is_empty() {
+            let next = names.iter().position(|name| {
+                !edges
+                    .iter()
+                    .any(|(first, then)| then == name && names.contains(first))
+            });
+            match next {
+                Some(index) => order.push(names.remove(index)),
+                None => bail!(
+                    "The `before` and `after` settings of these preprocessors form a cycle: {}",
+                    names.join(", ")
+                ),
+            }
+        }
+
+        Ok(order)
+    }
+
     fn from_legacy(mut table: Value) -> Config {
         let mut cfg = Config::default();
 
@@ -280,6 +626,7 @@ impl Config {
         }
 
         cfg.rest = table;
+        cfg.refresh_html_config();
         cfg
     }
 }
@@ -291,6 +638,8 @@ impl Default for Config {
             build: BuildConfig::default(),
             rust: RustConfig::default(),
             rest: Value::Table(Table::default()),
+            html: None,
+            env_overrides: Vec::new(),
         }
     }
 }
@@ -339,12 +688,20 @@ impl<'de> Deserialize<'de> for Config {
             .transpose()?
             .unwrap_or_default();
 
-        Ok(Config {
+        let mut rest = Value::Table(table);
+        rename_deprecated_keys(&mut rest);
+
+        let mut cfg = Config {
             book,
             build,
             rust,
-            rest: Value::Table(table),
-        })
+            rest,
+            html: None,
+            env_overrides: Vec::new(),
+        };
+        cfg.refresh_html_config();
+
+        Ok(cfg)
     }
 }
 
@@ -370,6 +727,183 @@ impl Serialize for Config {
     }
 }
 
+/// An environment variable set to this value removes the corresponding key
+/// from the config in [`Config::update_from_env()`].
+const UNSET_SENTINEL: &str = "__MDBOOK_UNSET__";
+
+/// Config keys which have been renamed, as `(old, new)` pairs.
+///
+/// Reading or writing an old key (or anything nested under it) uses the new
+/// key instead, after warning the user about the rename.
+const DEPRECATED_KEYS: &[(&str, &str)] = &[("output.html.playpen", "output.html.playground")];
+
+/// Keeps options which are off by default out of a generated `book.toml`.
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+/// Keeps options which are on by default out of a generated `book.toml`.
+fn is_true(value: &bool) -> bool {
+    *value
+}
+
+/// Returns `true` if the `output.html` settings have a particular conflict.
+type ConflictCheck = fn(&HtmlConfig) -> bool;
+
+/// Inconsistent `output.html` settings reported by [`Config::validate()`], as
+/// `(check, message)` pairs.
+const HTML_CONFLICTS: &[(ConflictCheck, &str)] = &[
+    (
+        mathjax_support_is_overridden,
+        "`output.html.mathjax-support` is ignored because `output.html.math-engine` \
+         selects a different engine",
+    ),
+    (
+        relative_og_image_without_site_url,
+        "`output.html.og-image` is a relative path, but there is no \
+         `output.html.site-url` to resolve it against",
+    ),
+    (
+        edit_url_template_without_path,
+        "`output.html.edit-url-template` doesn't contain `{path}`, so every \
+         page links to the same place",
+    ),
+    (
+        search_index_without_fields,
+        "`output.html.search-index` is enabled with an empty `fields` list",
+    ),
+    (
+        unknown_theme_name,
+        "`output.html.default-theme` and `output.html.preferred-dark-theme` \
+         must be one of `light`, `rust`, `coal`, `navy` or `ayu` unless \
+         `output.html.theme` is set",
+    ),
+];
+
+/// The color schemes offered by the builtin theme.
+const THEME_NAMES: &[&str] = &["light", "rust", "coal", "navy", "ayu"];
+
+fn mathjax_support_is_overridden(html: &HtmlConfig) -> bool {
+    html.mathjax_support
+        && html
+            .math_engine
+            .map(|engine| engine != MathEngine::MathJax)
+            .unwrap_or(false)
+}
+
+fn relative_og_image_without_site_url(html: &HtmlConfig) -> bool {
+    html.site_url.is_none()
+        && html
+            .og_image
+            .as_deref()
+            .map(|image| !image.contains("://"))
+            .unwrap_or(false)
+}
+
+fn edit_url_template_without_path(html: &HtmlConfig) -> bool {
+    html.edit_url_template
+        .as_deref()
+        .map(|template| !template.contains("{path}"))
+        .unwrap_or(false)
+}
+
+fn search_index_without_fields(html: &HtmlConfig) -> bool {
+    html.search_index.enable && html.search_index.fields.is_empty()
+}
+
+fn unknown_theme_name(html: &HtmlConfig) -> bool {
+    // A custom theme may offer color schemes of its own.
+    html.theme.is_none()
+        && html
+            .default_theme
+            .iter()
+            .chain(&html.preferred_dark_theme)
+            .any(|theme| !THEME_NAMES.contains(&theme.to_lowercase().as_str()))
+}
+
+/// Map a (possibly deprecated) dotted key to its current name.
+fn resolve_deprecated_key(key: &str) -> Cow<'_, str> {
+    for (old, new) in DEPRECATED_KEYS {
+        if key == *old || (key.starts_with(old) && key[old.len()..].starts_with('.')) {
+            warn_deprecated_key(old, new);
+            return format!("{}{}", new, &key[old.len()..]).into();
+        }
+    }
+
+    key.into()
+}
+
+/// Move any values stored under deprecated keys to their new names.
+fn rename_deprecated_keys(rest: &mut Value) {
+    for (old, new) in DEPRECATED_KEYS {
+        if let Some(value) = rest.delete(old) {
+            warn_deprecated_key(old, new);
+            if rest.read(new).is_none() {
+                rest.insert(new, value);
+            }
+        }
+    }
+}
+
+/// Recursively merge `overrides` into `base`.
+fn merge_values(base: &mut Value, overrides: Value) {
+    match (base, overrides) {
+        (Value::Table(base), Value::Table(overrides)) => {
+            for (key, value) in overrides {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_values(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overrides) => *base = overrides,
+    }
+}
+
+/// `section` of the config with the matching table of a profile merged over
+/// it.
+fn merge_section<T: Serialize + DeserializeOwned>(
+    section: &T,
+    overrides: Value,
+    profile: &str,
+) -> Result<T> {
+    let mut merged = Value::try_from(section).expect("unreachable");
+    merge_values(&mut merged, overrides);
+    merged
+        .try_into()
+        .with_context(|| format!("Invalid configuration in [profile.{}]", profile))
+}
+
+fn warn_deprecated_key(old: &'static str, new: &str) {
+    if first_deprecation_warning(old) {
+        warn!(
+            "The `{}` config key is deprecated, please use `{}` instead",
+            old, new
+        );
+    }
+}
+
+/// Returns `true` the first time it is called for a deprecated key, so each
+/// deprecation is only reported once.
+fn first_deprecation_warning(old: &'static str) -> bool {
+    lazy_static! {
+        static ref WARNED: Mutex<HashSet<&'static str>> = Mutex::new(HashSet::new());
+    }
+
+    WARNED
+        .lock()
+        .map(|mut warned| warned.insert(old))
+        .unwrap_or(false)
+}
+
+/// Parse the value of a config override as JSON, falling back to a string if
+/// that fails.
+fn parse_override_value(value: &str) -> serde_json::Value {
+    serde_json::from_str(value).unwrap_or_else(|_| serde_json::Value::String(value.to_string()))
+}
+
 fn parse_env(key: &str) -> Option<String> {
     const PREFIX: &str = "MDBOOK_";
 
@@ -415,8 +949,16 @@ pub struct BookConfig {
     pub src: PathBuf,
     /// Does this book support more than one language?
     pub multilingual: bool,
+    /// The languages of a multilingual book. Each one is loaded from its own
+    /// subdirectory of `src` and rendered into its own subdirectory of the
+    /// build directory.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub languages: Vec<String>,
     /// The main language of the book.
     pub language: Option<String>,
+    /// Which edition of mdBook's behaviour the book was written for. Books
+    /// without one get the oldest behaviour.
+    pub edition: Option<BookEdition>,
 }
 
 impl Default for BookConfig {
@@ -427,11 +969,25 @@ impl Default for BookConfig {
             description: None,
             src: PathBuf::from("src"),
             multilingual: false,
+            languages: Vec::new(),
             language: Some(String::from("en")),
+            edition: None,
         }
     }
 }
 
+/// An edition of the book format, used to opt in to newer defaults without
+/// changing how existing books are built.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum BookEdition {
+    /// The original behaviour.
+    #[serde(rename = "2018")]
+    E2018,
+    /// The 2024 edition.
+    #[serde(rename = "2024")]
+    E2024,
+}
+
 /// Configuration for the build procedure.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(default, rename_all = "kebab-case")]
@@ -444,6 +1000,46 @@ pub struct BuildConfig {
     /// Should the default preprocessors always be used when they are
     /// compatible with the renderer?
     pub use_default_preprocessors: bool,
+    /// A file, relative to the book's root directory, listing chapter paths in
+    /// the order the previous/next links should follow.
+    pub reading_order: Option<PathBuf>,
+    /// A command run against every chapter's source file before rendering,
+    /// with `{file}` replaced by the file's path. The build fails if it
+    /// exits unsuccessfully for any chapter.
+    pub chapter_check_command: Option<String>,
+    /// Make `mdbook build` fail if any warnings were emitted while loading or
+    /// rendering the book.
+    #[serde(skip_serializing_if = "is_false")]
+    pub deny_warnings: bool,
+    /// The features whose code blocks (those with `feature=<name>` in their
+    /// info string) are included in the book.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub features: Vec<String>,
+    /// Generate the summary from the files in the source directory when
+    /// there is no `SUMMARY.md`.
+    #[serde(skip_serializing_if = "is_false")]
+    pub auto_summary: bool,
+    /// The order of the chapters in a generated summary.
+    #[serde(skip_serializing_if = "AutoSummarySort::is_alpha")]
+    pub auto_summary_sort: AutoSummarySort,
+    /// Expand `{{#include-url <url>}}` in chapters by downloading the URL.
+    #[serde(skip_serializing_if = "is_false")]
+    pub allow_remote_includes: bool,
+    /// How many seconds to wait for each remote include. Defaults to 30.
+    pub remote_include_timeout: Option<u64>,
+    /// A directory, relative to the book's root, to keep downloaded remote
+    /// includes in, so they are only fetched once.
+    pub remote_include_cache: Option<PathBuf>,
+    /// Keep draft chapters in the book. When this is off they are removed,
+    /// along with their sub-chapters, as soon as the book is loaded.
+    #[serde(skip_serializing_if = "is_true")]
+    pub include_drafts: bool,
+    /// What to do with chapters whose file is empty.
+    #[serde(skip_serializing_if = "EmptyChapter::is_blank")]
+    pub empty_chapter: EmptyChapter,
+    /// The markdown put in empty chapters when `empty_chapter` is
+    /// `placeholder`.
+    pub empty_chapter_placeholder: Option<String>,
 }
 
 impl Default for BuildConfig {
@@ -452,10 +1048,59 @@ impl Default for BuildConfig {
             build_dir: PathBuf::from("book"),
             create_missing: true,
             use_default_preprocessors: true,
+            reading_order: None,
+            chapter_check_command: None,
+            deny_warnings: false,
+            features: Vec::new(),
+            auto_summary: false,
+            auto_summary_sort: AutoSummarySort::Alpha,
+            allow_remote_includes: false,
+            remote_include_timeout: None,
+            remote_include_cache: None,
+            include_drafts: true,
+            empty_chapter: EmptyChapter::Blank,
+            empty_chapter_placeholder: None,
         }
     }
 }
 
+/// How the files of a generated summary are ordered.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AutoSummarySort {
+    /// By file name.
+    Alpha,
+    /// By file name, comparing runs of digits as numbers, so `2.md` comes
+    /// before `10.md`.
+    Natural,
+    /// By modification time, oldest first.
+    Modified,
+}
+
+impl AutoSummarySort {
+    fn is_alpha(&self) -> bool {
+        *self == AutoSummarySort::Alpha
+    }
+}
+
+/// What happens to chapters whose file is empty.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmptyChapter {
+    /// Render them as they are, i.e. a page with nothing but navigation.
+    Blank,
+    /// Fill them in with a notice saying the page is empty.
+    Placeholder,
+    /// Fail to load the book.
+    Error,
+}
+
+impl EmptyChapter {
+    fn is_blank(&self) -> bool {
+        *self == EmptyChapter::Blank
+    }
+}
+
 /// Configuration for the Rust compiler(e.g., for playground)
 #[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(default, rename_all = "kebab-case")]
@@ -489,19 +1134,82 @@ pub struct HtmlConfig {
     /// The theme to use if the browser requests the dark version of the site.
     /// Defaults to 'navy'.
     pub preferred_dark_theme: Option<String>,
-    /// Use "smart quotes" instead of the usual `"` character.
+    /// Whether the sidebar is shown or hidden until the reader toggles it.
+    pub default_sidebar: SidebarVisibility,
+    /// Use "smart quotes" instead of the usual `"` character. This is the same
+    /// as `smart_punctuation.quotes`, and kept for backwards compatibility.
     pub curly_quotes: bool,
+    /// Which punctuation is replaced with its typographic equivalent.
+    pub smart_punctuation: SmartPunctuation,
+    /// Which markdown extensions to enable.
+    pub markdown: Markdown,
     /// Should mathjax be enabled?
     pub mathjax_support: bool,
+    /// The engine used to render math, overriding `mathjax_support`.
+    pub math_engine: Option<MathEngine>,
     /// Whether to fonts.css and respective font files to the output directory.
     pub copy_fonts: bool,
+    /// Extra fonts to copy to the output, with `@font-face` rules for them
+    /// added to `fonts.css`.
+    pub custom_fonts: Vec<CustomFont>,
     /// An optional google analytics code.
     pub google_analytics: Option<String>,
     /// Additional CSS stylesheets to include in the rendered page's `<head>`.
     pub additional_css: Vec<PathBuf>,
+    /// Stylesheets, relative to the book's root directory, whose contents are
+    /// put in a `<style>` tag in every page's `<head>` rather than being
+    /// linked to.
+    pub inline_css: Vec<PathBuf>,
     /// Additional JS scripts to include at the bottom of the rendered page's
     /// `<body>`.
     pub additional_js: Vec<PathBuf>,
+    /// Additional files or directories, relative to the book's root
+    /// directory, to copy to the same place in the output, e.g. images
+    /// used by a custom theme.
+    pub additional_static: Vec<PathBuf>,
+    /// A directory inside the output which `additional_static` files are
+    /// copied into instead of the output's root.
+    pub additional_static_prefix: Option<PathBuf>,
+    /// A PNG file, relative to the book's root directory, to use as the
+    /// favicon instead of the theme's.
+    pub favicon_png: Option<PathBuf>,
+    /// An SVG file, relative to the book's root directory, to use as the
+    /// favicon instead of the theme's.
+    pub favicon_svg: Option<PathBuf>,
+    /// An HTML fragment file, relative to the book's root directory, which is
+    /// included verbatim in every page's `<head>`.
+    pub head_include: Option<PathBuf>,
+    /// Combine the builtin scripts into a single `bundle-<hash>.js` file
+    /// instead of loading each of them separately.
+    pub bundle_js: bool,
+    /// Add buttons for copying code blocks to the clipboard. When off, the
+    /// clipboard script isn't copied to the output either.
+    pub copy_buttons: bool,
+    /// Wrap each line of a code block in an element with its own `id`, with a
+    /// link to it in the gutter.
+    pub code_line_anchors: bool,
+    /// Write a gzipped copy next to each text file in the output, for servers
+    /// which can serve precompressed files.
+    pub precompress: bool,
+    /// Give every page a `<base>` tag pointing at the root of the book, and
+    /// make the links on it relative to that, instead of to the page.
+    pub use_base_href: bool,
+    /// Write an `index.html` redirecting to the first chapter if the book
+    /// doesn't otherwise have one, e.g. because it starts with a part title.
+    pub generate_index: bool,
+    /// The deepest heading level listed in each page's table of contents.
+    pub page_toc_max_level: u8,
+    /// How many levels of chapters the sidebar lists. Deeper chapters are
+    /// still rendered, but left out of the sidebar.
+    pub toc_max_depth: Option<usize>,
+    /// What the whitespace in a heading's text is replaced with in its
+    /// anchor `id`. Default: `-`.
+    pub anchor_separator: char,
+    /// Whether heading anchor `id`s are lowercased. Default: `true`.
+    pub anchor_lowercase: bool,
+    /// Whether headings get a separate anchor link, shown when hovering over
+    /// them, next to their text.
+    pub heading_anchor: HeadingAnchor,
     /// Fold settings.
     pub fold: Fold,
     /// Playground settings.
@@ -509,10 +1217,17 @@ pub struct HtmlConfig {
     pub playground: Playground,
     /// Print settings.
     pub print: Print,
+    /// Settings for `mdbook serve`.
+    pub serve: Serve,
     /// Don't render section labels.
     pub no_section_label: bool,
+    /// Put the chapter's section number in front of its name in the page's
+    /// `<title>`.
+    pub number_in_title: bool,
     /// Search settings. If `None`, the default will be used.
     pub search: Option<Search>,
+    /// Settings for the raw `search-index.json` file.
+    pub search_index: SearchIndex,
     /// Git repository url. If `None`, the git button will not be shown.
     pub git_repository_url: Option<String>,
     /// FontAwesome icon class to use for the Git repository link.
@@ -522,6 +1237,16 @@ pub struct HtmlConfig {
     pub input_404: Option<String>,
     /// Absolute url to site, used to emit correct paths for the 404 page, which might be accessed in a deeply nested directory
     pub site_url: Option<String>,
+    /// A path, such as `/docs/`, to put in front of links in chapters which
+    /// start with `/`, for books which aren't served from the root of their
+    /// site.
+    pub site_base_path: Option<String>,
+    /// Image shown in link previews (`og:image`) for every page. Relative
+    /// paths are resolved against `site-url`.
+    pub og_image: Option<String>,
+    /// Markdown, such as a copyright notice, rendered at the bottom of every
+    /// page.
+    pub footer: Option<String>,
     /// The DNS subdomain or apex domain at which your book will be hosted. This
     /// string will be written to a file named CNAME in the root of your site,
     /// as required by GitHub Pages (see [*Managing a custom domain for your
@@ -552,22 +1277,50 @@ impl Default for HtmlConfig {
             theme: None,
             default_theme: None,
             preferred_dark_theme: None,
+            default_sidebar: SidebarVisibility::Visible,
             curly_quotes: false,
+            smart_punctuation: SmartPunctuation::default(),
+            markdown: Markdown::default(),
             mathjax_support: false,
+            math_engine: None,
             copy_fonts: true,
+            custom_fonts: Vec::new(),
             google_analytics: None,
             additional_css: Vec::new(),
+            inline_css: Vec::new(),
             additional_js: Vec::new(),
+            additional_static: Vec::new(),
+            additional_static_prefix: None,
+            favicon_png: None,
+            favicon_svg: None,
+            head_include: None,
+            bundle_js: false,
+            copy_buttons: true,
+            code_line_anchors: false,
+            precompress: false,
+            use_base_href: false,
+            generate_index: false,
+            page_toc_max_level: 3,
+            toc_max_depth: None,
+            anchor_separator: '-',
+            anchor_lowercase: true,
+            heading_anchor: HeadingAnchor::None,
             fold: Fold::default(),
             playground: Playground::default(),
             print: Print::default(),
+            serve: Serve::default(),
             no_section_label: false,
+            number_in_title: false,
             search: None,
+            search_index: SearchIndex::default(),
             git_repository_url: None,
             git_repository_icon: None,
             edit_url_template: None,
             input_404: None,
             site_url: None,
+            site_base_path: None,
+            og_image: None,
+            footer: None,
             cname: None,
             livereload_url: None,
             redirect: HashMap::new(),
@@ -576,27 +1329,198 @@ impl Default for HtmlConfig {
 }
 
 impl HtmlConfig {
+    /// How heading text is turned into anchor `id`s.
+    pub fn anchor_style(&self) -> utils::AnchorStyle {
+        utils::AnchorStyle {
+            separator: self.anchor_separator,
+            lowercase: self.anchor_lowercase,
+        }
+    }
+
     /// Returns the directory of theme from the provided root directory. If the
     /// directory is not present it will append the default directory of "theme"
     pub fn theme_dir(&self, root: &Path) -> PathBuf {
-        match self.theme {
-            Some(ref d) => root.join(d),
-            None => root.join("theme"),
+        self.resolved_theme_dir(root)
+            .unwrap_or_else(|| root.join("theme"))
+    }
+
+    /// Resolves the configured `theme` directory, if any.
+    ///
+    /// A relative `theme` is always interpreted relative to the book's root
+    /// directory (never the current working directory), so building a book
+    /// gives the same result no matter where `mdbook` is invoked from.
+    /// Absolute paths are returned unchanged.
+    pub fn resolved_theme_dir(&self, book_root: &Path) -> Option<PathBuf> {
+        self.theme.as_ref().map(|theme| {
+            if theme.is_absolute() {
+                theme.clone()
+            } else {
+                book_root.join(theme)
+            }
+        })
+    }
+
+    /// The engine used to render math.
+    ///
+    /// When no `math-engine` is given, `mathjax-support = true` selects
+    /// MathJax for backwards compatibility.
+    pub fn resolved_math_engine(&self) -> MathEngine {
+        match self.math_engine {
+            Some(engine) => engine,
+            None if self.mathjax_support => MathEngine::MathJax,
+            None => MathEngine::None,
+        }
+    }
+
+    /// The punctuation to make typographic, with `curly-quotes` turning on
+    /// the quotes.
+    pub fn resolved_smart_punctuation(&self) -> SmartPunctuation {
+        SmartPunctuation {
+            quotes: self.smart_punctuation.quotes || self.curly_quotes,
+            ..self.smart_punctuation
+        }
+    }
+}
+
+/// The engine used to render math equations.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MathEngine {
+    /// Render math with [MathJax](https://www.mathjax.org/).
+    MathJax,
+    /// Render math with [KaTeX](https://katex.org/).
+    KaTeX,
+    /// Don't render math.
+    None,
+}
+
+/// Whether the sidebar is shown when a reader first opens the book.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SidebarVisibility {
+    /// Show the sidebar on wide enough screens.
+    Visible,
+    /// Hide the sidebar.
+    Hidden,
+}
+
+/// The anchor link added after each heading's text.
+///
+/// In `book.toml` this is either `"none"` or the symbol to show.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HeadingAnchor {
+    /// No anchor link. The heading itself still links to its `id`.
+    None,
+    /// An anchor link showing the given text, e.g. `"§"`.
+    Symbol(String),
+}
+
+impl<'de> Deserialize<'de> for HeadingAnchor {
+    fn deserialize<D: Deserializer<'de>>(de: D) -> std::result::Result<Self, D::Error> {
+        let value = String::deserialize(de)?;
+        if value == "none" {
+            Ok(HeadingAnchor::None)
+        } else {
+            Ok(HeadingAnchor::Symbol(value))
+        }
+    }
+}
+
+impl Serialize for HeadingAnchor {
+    fn serialize<S: Serializer>(&self, s: S) -> std::result::Result<S::Ok, S::Error> {
+        match self {
+            HeadingAnchor::None => s.serialize_str("none"),
+            HeadingAnchor::Symbol(symbol) => s.serialize_str(symbol),
         }
     }
 }
 
 /// Configuration for how to render the print icon, print.html, and print.css.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "kebab-case")]
+#[serde(default, rename_all = "kebab-case")]
 pub struct Print {
     /// Whether print support is enabled.
     pub enable: bool,
+    /// Where the single-page print version of the book is written, relative
+    /// to the output directory. Default: `print.html`.
+    pub output_name: String,
+    /// The title of the print page, instead of the book's title.
+    pub title: Option<String>,
 }
 
 impl Default for Print {
     fn default() -> Self {
-        Self { enable: true }
+        Self {
+            enable: true,
+            output_name: String::from("print.html"),
+            title: None,
+        }
+    }
+}
+
+/// A font file to ship with the book, from `[[output.html.custom-fonts]]`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct CustomFont {
+    /// The `font-family` the font is used with in CSS.
+    pub family: String,
+    /// The font's `font-weight`. Default: `400`.
+    pub weight: Option<u16>,
+    /// The font's `font-style`, e.g. `italic`. Default: `normal`.
+    pub style: Option<String>,
+    /// The font file, relative to the book's root directory.
+    pub path: PathBuf,
+}
+
+/// Configuration for how `mdbook serve` serves the rendered book.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Serve {
+    /// A `Content-Security-Policy` header to send with every HTML page, for
+    /// trying out a site's policy locally.
+    pub csp: Option<String>,
+}
+
+/// Configuration for which punctuation is made typographic when rendering
+/// markdown, from `[output.html.smart-punctuation]`.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct SmartPunctuation {
+    /// Turn straight quotes into curly ones. Default: `false`.
+    pub quotes: bool,
+    /// Turn `--` into an en dash and `---` into an em dash. Default: `false`.
+    pub dashes: bool,
+    /// Turn `...` into an ellipsis. Default: `false`.
+    pub ellipses: bool,
+}
+
+/// Configuration for which markdown extensions are enabled when rendering
+/// chapters.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Markdown {
+    /// Enable footnotes. Default: `true`.
+    pub footnotes: bool,
+    /// Enable pipe tables. Default: `true`.
+    pub tables: bool,
+    /// Enable `~~strikethrough~~`. Default: `true`.
+    pub strikethrough: bool,
+    /// Enable `- [ ]` task lists. Default: `true`.
+    pub tasklists: bool,
+    /// Enable definition lists, with each definition on a line starting with
+    /// `: ` after its term. Default: `false`.
+    pub definition_lists: bool,
+}
+
+impl Default for Markdown {
+    fn default() -> Markdown {
+        Markdown {
+            footnotes: true,
+            tables: true,
+            strikethrough: true,
+            tasklists: true,
+            definition_lists: false,
+        }
     }
 }
 
@@ -625,6 +1549,12 @@ pub struct Playground {
     pub copy_js: bool,
     /// Display line numbers on playground snippets. Default: `false`.
     pub line_numbers: bool,
+    /// How long to wait for the playground to run a snippet before giving
+    /// up. Default: `6`.
+    pub run_timeout_secs: u32,
+    /// How many times to retry running a snippet after a failed request.
+    /// Default: `0`.
+    pub run_retries: u32,
 }
 
 impl Default for Playground {
@@ -634,6 +1564,8 @@ impl Default for Playground {
             copyable: true,
             copy_js: true,
             line_numbers: false,
+            run_timeout_secs: 6,
+            run_retries: 0,
         }
     }
 }
@@ -689,6 +1621,45 @@ impl Default for Search {
     }
 }
 
+/// Configuration for the `search-index.json` file, a raw index of the book's
+/// chapters for external search tools.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct SearchIndex {
+    /// Write `search-index.json`. Default: `false`.
+    pub enable: bool,
+    /// The fields to include for each chapter. Default: all of them.
+    pub fields: Vec<SearchIndexField>,
+}
+
+impl Default for SearchIndex {
+    fn default() -> SearchIndex {
+        SearchIndex {
+            enable: false,
+            fields: vec![
+                SearchIndexField::Title,
+                SearchIndexField::Path,
+                SearchIndexField::Section,
+                SearchIndexField::Body,
+            ],
+        }
+    }
+}
+
+/// A field of a chapter's entry in `search-index.json`.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchIndexField {
+    /// The chapter's name.
+    Title,
+    /// The path of the rendered page, relative to the book's root.
+    Path,
+    /// The chapter's section number, such as `"1.2."`, or `null`.
+    Section,
+    /// The chapter's text, without any markdown.
+    Body,
+}
+
 /// Allows you to "update" any arbitrary field in a struct by round-tripping via
 /// a `toml::Value`.
 ///
@@ -708,6 +1679,18 @@ trait Updateable<'de>: Serialize + Deserialize<'de> {
             *self = updated;
         }
     }
+
+    fn remove_value(&mut self, key: &str) {
+        let mut raw = Value::try_from(&self).expect("unreachable");
+
+        if raw.delete(key).is_none() {
+            return;
+        }
+
+        if let Ok(updated) = raw.try_into() {
+            *self = updated;
+        }
+    }
 }
 
 impl<'de, T> Updateable<'de> for T where T: Serialize + Deserialize<'de> {}
@@ -762,13 +1745,27 @@ mod tests {
             authors: vec![String::from("Michael-F-Bryan <michaelfbryan@gmail.com>")],
             description: Some(String::from("A completely useless book")),
             multilingual: true,
+            languages: Vec::new(),
             src: PathBuf::from("source"),
             language: Some(String::from("ja")),
+            edition: None,
         };
         let build_should_be = BuildConfig {
             build_dir: PathBuf::from("outputs"),
             create_missing: false,
             use_default_preprocessors: true,
+            reading_order: None,
+            chapter_check_command: None,
+            deny_warnings: false,
+            features: Vec::new(),
+            auto_summary: false,
+            auto_summary_sort: AutoSummarySort::Alpha,
+            allow_remote_includes: false,
+            remote_include_timeout: None,
+            remote_include_cache: None,
+            include_drafts: true,
+            empty_chapter: EmptyChapter::Blank,
+            empty_chapter_placeholder: None,
         };
         let rust_should_be = RustConfig { edition: None };
         let playground_should_be = Playground {
@@ -776,6 +1773,8 @@ mod tests {
             copyable: true,
             copy_js: true,
             line_numbers: false,
+            run_timeout_secs: 6,
+            run_retries: 0,
         };
         let html_should_be = HtmlConfig {
             curly_quotes: true,
@@ -806,6 +1805,108 @@ mod tests {
         assert_eq!(got.html_config().unwrap(), html_should_be);
     }
 
+    #[test]
+    fn apply_a_profile() {
+        let src = r#"
+        [book]
+        title = "Draft Book"
+        authors = ["Someone"]
+
+        [output.html]
+        git-repository-url = "https://foo.com/"
+        additional-css = ["draft.css"]
+
+        [profile.release.book]
+        title = "Release Book"
+
+        [profile.release.output.html]
+        additional-css = ["release.css"]
+        "#;
+
+        let mut cfg = Config::from_str(src).unwrap();
+        cfg.apply_profile("release").unwrap();
+
+        assert_eq!(cfg.book.title, Some(String::from("Release Book")));
+        assert_eq!(cfg.book.authors, vec![String::from("Someone")]);
+        let html = cfg.html().unwrap();
+        assert_eq!(html.additional_css, vec![PathBuf::from("release.css")]);
+        assert_eq!(
+            html.git_repository_url,
+            Some(String::from("https://foo.com/"))
+        );
+        assert!(cfg.get("profile").is_none());
+    }
+
+    #[test]
+    fn applying_a_profile_keeps_env_overrides() {
+        let src = r#"
+        [profile.release.book]
+        title = "Release Book"
+        "#;
+        let mut cfg = Config::from_str(src).unwrap();
+
+        env::set_var("MDBOOK_OUTPUT__PROFILED__NAME", "from-env");
+        cfg.update_from_env();
+        env::remove_var("MDBOOK_OUTPUT__PROFILED__NAME");
+        cfg.apply_profile("release").unwrap();
+
+        assert_eq!(cfg.book.title, Some(String::from("Release Book")));
+        assert_eq!(
+            cfg.get("output.profiled.name"),
+            Some(&Value::String(String::from("from-env")))
+        );
+        assert!(cfg.env_overrides().contains(&(
+            String::from("output.profiled.name"),
+            Value::String(String::from("from-env"))
+        )));
+    }
+
+    #[test]
+    fn applying_a_missing_profile_is_an_error() {
+        let src = r#"
+        [profile.release.book]
+        title = "Release Book"
+        "#;
+
+        let mut cfg = Config::from_str(src).unwrap();
+
+        assert!(cfg.apply_profile("dev").is_err());
+        assert!(cfg.get("profile.release").is_some());
+    }
+
+    #[test]
+    fn load_fold_settings() {
+        let src = r#"
+        [output.html.fold]
+        enable = true
+        level = 2
+        "#;
+
+        let got = Config::from_str(src).unwrap();
+
+        let fold_should_be = Fold {
+            enable: true,
+            level: 2,
+        };
+        assert_eq!(got.html().unwrap().fold, fold_should_be);
+        assert_eq!(HtmlConfig::default().fold, Fold::default());
+    }
+
+    #[test]
+    fn get_arrays_from_the_config() {
+        let mut cfg = Config::from_str(COMPLEX_CONFIG).unwrap();
+        cfg.set("foo.names", vec!["first", "second"]).unwrap();
+
+        let got: Vec<PathBuf> = cfg.get_array("output.html.additional-css").unwrap();
+        assert_eq!(got, vec![PathBuf::from("./foo/bar/baz.css")]);
+
+        let missing: Vec<PathBuf> = cfg.get_array("output.html.additional-js").unwrap();
+        assert!(missing.is_empty());
+
+        assert!(cfg.get_array::<String>("output.html.theme").is_err());
+        assert!(cfg.get_array::<u32>("foo.names").is_err());
+    }
+
     #[test]
     fn edition_2015() {
         let src = r#"
@@ -878,6 +1979,34 @@ mod tests {
         assert_eq!(got.rust, rust_should_be);
     }
 
+    #[test]
+    fn book_edition_round_trips() {
+        let src = r#"
+        [book]
+        title = "mdBook Documentation"
+        edition = "2024"
+        "#;
+
+        let got = Config::from_str(src).unwrap();
+        assert_eq!(got.book.edition, Some(BookEdition::E2024));
+
+        let serialized = toml::to_string(&got).unwrap();
+        let round_tripped = Config::from_str(&serialized).unwrap();
+        assert_eq!(round_tripped.book, got.book);
+        assert_eq!(Config::default().book.edition, None);
+    }
+
+    #[test]
+    fn unknown_book_editions_are_an_error() {
+        let src = r#"
+        [book]
+        edition = "1999"
+        "#;
+
+        let err = Config::from_str(src).unwrap_err();
+        assert!(format!("{:#}", err).contains("1999"), "{:#}", err);
+    }
+
     #[test]
     fn load_arbitrary_output_type() {
         #[derive(Debug, Deserialize, PartialEq)]
@@ -914,6 +2043,21 @@ mod tests {
         assert_eq!(got_baz, baz_should_be);
     }
 
+    #[test]
+    fn html_is_only_the_default_renderer() {
+        let cfg = Config::default();
+        assert!(cfg.has_renderer("html"));
+        assert!(!cfg.has_renderer("epub"));
+
+        let cfg = Config::from_str("[output.epub]").unwrap();
+        assert!(!cfg.has_renderer("html"));
+        assert!(cfg.has_renderer("epub"));
+
+        let cfg = Config::from_str("[output.html]\n[output.epub]").unwrap();
+        assert!(cfg.has_renderer("html"));
+        assert!(cfg.has_renderer("epub"));
+    }
+
     #[test]
     fn mutate_some_stuff() {
         // really this is just a sanity check to make sure the borrow checker
@@ -962,6 +2106,18 @@ mod tests {
             build_dir: PathBuf::from("my-book"),
             create_missing: true,
             use_default_preprocessors: true,
+            reading_order: None,
+            chapter_check_command: None,
+            deny_warnings: false,
+            features: Vec::new(),
+            auto_summary: false,
+            auto_summary_sort: AutoSummarySort::Alpha,
+            allow_remote_includes: false,
+            remote_include_timeout: None,
+            remote_include_cache: None,
+            include_drafts: true,
+            empty_chapter: EmptyChapter::Blank,
+            empty_chapter_placeholder: None,
         };
 
         let html_should_be = HtmlConfig {
@@ -977,6 +2133,15 @@ mod tests {
         assert_eq!(got.book, book_should_be);
         assert_eq!(got.build, build_should_be);
         assert_eq!(got.html_config().unwrap(), html_should_be);
+
+        let err = Config::from_str_strict(src).unwrap_err();
+        assert!(err.to_string().contains("legacy"), "{}", err);
+    }
+
+    #[test]
+    fn strict_parsing_accepts_the_current_format() {
+        let got = Config::from_str_strict(COMPLEX_CONFIG).unwrap();
+        assert_eq!(got, Config::from_str(COMPLEX_CONFIG).unwrap());
     }
 
     #[test]
@@ -992,6 +2157,179 @@ mod tests {
         assert_eq!(got, value);
     }
 
+    #[test]
+    fn setting_an_html_key_updates_the_html_config() {
+        let src = r#"
+        [output.html]
+        default-theme = "rust"
+        "#;
+        let mut cfg = Config::from_str(src).unwrap();
+        assert_eq!(cfg.html().unwrap().default_theme.as_deref(), Some("rust"));
+
+        cfg.set("output.html.default-theme", "ayu").unwrap();
+        assert_eq!(cfg.html().unwrap().default_theme.as_deref(), Some("ayu"));
+
+        cfg.set("output.html.fold.enable", true).unwrap();
+        assert!(cfg.html().unwrap().fold.enable);
+        assert_eq!(cfg.html_config().as_ref(), cfg.html());
+
+        let mut cfg = Config::default();
+        assert!(cfg.html().is_none());
+        cfg.set("output.html", Table::new()).unwrap();
+        assert_eq!(cfg.html(), Some(&HtmlConfig::default()));
+    }
+
+    #[test]
+    fn readme_is_an_index_file_while_the_index_preprocessor_is_enabled() {
+        let cfg = Config::default();
+        assert_eq!(cfg.index_file_names(), ["README"]);
+
+        let src = "[build]\nuse-default-preprocessors = false\n";
+        let cfg = Config::from_str(src).unwrap();
+        assert!(cfg.index_file_names().is_empty());
+
+        let src = "[build]\nuse-default-preprocessors = false\n[preprocessor.index]\n";
+        let cfg = Config::from_str(src).unwrap();
+        assert_eq!(cfg.index_file_names(), ["README"]);
+    }
+
+    #[test]
+    fn list_the_configured_preprocessors() {
+        let src = r#"
+        [preprocessor.toc]
+        command = "mdbook-toc"
+        before = ["links"]
+
+        [preprocessor.admonish]
+        after = ["toc"]
+        "#;
+        let cfg = Config::from_str(src).unwrap();
+
+        let got = cfg.preprocessors();
+
+        let names: Vec<_> = got.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["admonish", "toc"]);
+        assert_eq!(got[0].1.get("after"), Some(&Value::from(vec!["toc"])));
+        assert_eq!(got[1].1.get("command"), Some(&Value::from("mdbook-toc")));
+        assert_eq!(got[1].1.get("before"), Some(&Value::from(vec!["links"])));
+
+        assert!(Config::default().preprocessors().is_empty());
+    }
+
+    #[test]
+    fn preprocessors_are_ordered_by_their_constraints() {
+        let src = r#"
+        [preprocessor.a]
+
+        [preprocessor.b]
+        after = ["a"]
+
+        [preprocessor.c]
+        before = ["b"]
+        after = ["missing"]
+        "#;
+        let cfg = Config::from_str(src).unwrap();
+
+        let order = cfg.preprocessor_order().unwrap();
+
+        let position = |name: &str| order.iter().position(|n| n == name).unwrap();
+        assert_eq!(order.len(), 5);
+        assert_eq!(&order[..2], ["links", "index"]);
+        assert!(position("a") < position("b"));
+        assert!(position("c") < position("b"));
+    }
+
+    #[test]
+    fn cyclic_preprocessor_constraints_are_an_error() {
+        let src = r#"
+        [build]
+        use-default-preprocessors = false
+
+        [preprocessor.a]
+        before = ["b"]
+
+        [preprocessor.b]
+        before = ["c"]
+
+        [preprocessor.c]
+        before = ["a"]
+        "#;
+        let cfg = Config::from_str(src).unwrap();
+
+        let err = cfg.preprocessor_order().unwrap_err().to_string();
+        assert!(err.contains("cycle: a, b, c"), "{}", err);
+    }
+
+    #[test]
+    fn validate_reports_conflicting_settings() {
+        let src = r#"
+        [output.html]
+        mathjax-support = true
+        math-engine = "none"
+        "#;
+        let mut cfg = Config::from_str(src).unwrap();
+
+        let problems = cfg.validate();
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].contains("mathjax-support"));
+
+        cfg.set("output.html.math-engine", "mathjax").unwrap();
+        assert!(cfg.validate().is_empty());
+
+        cfg.set("output.html.default-theme", "Ayu").unwrap();
+        assert!(cfg.validate().is_empty());
+        cfg.set("output.html.preferred-dark-theme", "midnight")
+            .unwrap();
+        let problems = cfg.validate();
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].contains("preferred-dark-theme"));
+
+        cfg.set("output.html.theme", "my-theme").unwrap();
+        assert!(cfg.validate().is_empty());
+    }
+
+    #[test]
+    fn math_engine_falls_back_to_mathjax_support() {
+        let html_config = HtmlConfig::default();
+        assert_eq!(html_config.resolved_math_engine(), MathEngine::None);
+
+        let html_config = HtmlConfig {
+            mathjax_support: true,
+            ..Default::default()
+        };
+        assert_eq!(html_config.resolved_math_engine(), MathEngine::MathJax);
+
+        let src = r#"
+        [output.html]
+        mathjax-support = true
+        math-engine = "katex"
+        "#;
+        let html_config = Config::from_str(src).unwrap().html_config().unwrap();
+        assert_eq!(html_config.resolved_math_engine(), MathEngine::KaTeX);
+    }
+
+    #[test]
+    fn deprecated_keys_resolve_to_their_new_names() {
+        let mut cfg = Config::default();
+        cfg.set("output.html.playground.editable", true).unwrap();
+
+        let got = cfg.get("output.html.playpen.editable").unwrap();
+        assert_eq!(got, &Value::Boolean(true));
+
+        cfg.set("output.html.playpen.copyable", false).unwrap();
+        let got = cfg.get("output.html.playground.copyable").unwrap();
+        assert_eq!(got, &Value::Boolean(false));
+        assert!(cfg.get("output.html.playpen-ish").is_none());
+
+        let src = r#"
+        [output.html.playpen]
+        line-numbers = true
+        "#;
+        let cfg = Config::from_str(src).unwrap();
+        let got = cfg.get("output.html.playground.line-numbers").unwrap();
+        assert_eq!(got, &Value::Boolean(true));
+    }
+
     #[test]
     fn parse_env_vars() {
         let inputs = vec![
@@ -1058,6 +2396,25 @@ mod tests {
         );
     }
 
+    #[test]
+    fn env_overrides_are_recorded() {
+        let mut cfg = Config::default();
+        assert!(cfg.env_overrides().is_empty());
+
+        env::set_var("MDBOOK_OUTPUT__RECORDED__NAME", "recorded");
+        env::set_var("MDBOOK_OUTPUT__RECORDED__COUNT", "3");
+        cfg.update_from_env();
+        env::remove_var("MDBOOK_OUTPUT__RECORDED__NAME");
+        env::remove_var("MDBOOK_OUTPUT__RECORDED__COUNT");
+
+        let overrides = cfg.env_overrides();
+        assert!(overrides.contains(&(
+            String::from("output.recorded.name"),
+            Value::String(String::from("recorded"))
+        )));
+        assert!(overrides.contains(&(String::from("output.recorded.count"), Value::Integer(3))));
+    }
+
     #[test]
     fn update_book_title_via_env() {
         let mut cfg = Config::default();
@@ -1071,6 +2428,69 @@ mod tests {
         assert_eq!(cfg.book.title, Some(should_be));
     }
 
+    #[test]
+    fn unset_keys_via_env() {
+        let src = r#"
+        [book]
+        description = "A book"
+
+        [output.html]
+        git-repository-url = "https://foo.com/"
+        "#;
+        let mut cfg = Config::from_str(src).unwrap();
+
+        env::set_var("MDBOOK_BOOK__DESCRIPTION", "__MDBOOK_UNSET__");
+        env::set_var(
+            "MDBOOK_OUTPUT__HTML__GIT_REPOSITORY_URL",
+            "__MDBOOK_UNSET__",
+        );
+        cfg.update_from_env();
+
+        env::remove_var("MDBOOK_BOOK__DESCRIPTION");
+        env::remove_var("MDBOOK_OUTPUT__HTML__GIT_REPOSITORY_URL");
+
+        assert_eq!(cfg.book.description, None);
+        assert!(cfg.get("output.html.git-repository-url").is_none());
+        assert_eq!(cfg.html().unwrap().git_repository_url, None);
+    }
+
+    #[test]
+    fn command_line_overrides() {
+        let mut cfg = Config::from_str(COMPLEX_CONFIG).unwrap();
+
+        cfg.apply_override("output.html.curly-quotes=false")
+            .unwrap();
+        cfg.apply_override("book.title=Another Book").unwrap();
+        cfg.apply_override("output.html.additional-css=[\"a.css\"]")
+            .unwrap();
+        cfg.apply_override("output.html.theme=__MDBOOK_UNSET__")
+            .unwrap();
+
+        let html = cfg.html().unwrap();
+        assert!(!html.curly_quotes);
+        assert_eq!(html.additional_css, vec![PathBuf::from("a.css")]);
+        assert_eq!(html.theme, None);
+        assert_eq!(cfg.book.title, Some(String::from("Another Book")));
+
+        assert!(cfg.apply_override("book.title").is_err());
+        assert!(cfg.apply_override("=value").is_err());
+    }
+
+    #[test]
+    fn unset_keys() {
+        let mut cfg = Config::from_str(COMPLEX_CONFIG).unwrap();
+
+        cfg.unset("book.title");
+        cfg.unset("build.build-dir");
+        cfg.unset("output.html.theme");
+        cfg.unset("no.such.key");
+
+        assert_eq!(cfg.book.title, None);
+        assert_eq!(cfg.build.build_dir, PathBuf::from("book"));
+        assert!(cfg.get("output.html.theme").is_none());
+        assert_eq!(cfg.html().unwrap().theme, None);
+    }
+
     #[test]
     fn file_404_default() {
         let src = r#"
@@ -1098,6 +2518,65 @@ mod tests {
         assert_eq!(&get_404_output_file(&html_config.input_404), "missing.html");
     }
 
+    #[test]
+    fn relative_theme_dir_is_resolved_against_the_book_root() {
+        let root = Path::new("/path/to/book");
+
+        let html_config = HtmlConfig {
+            theme: Some(PathBuf::from("my-theme")),
+            ..Default::default()
+        };
+        assert_eq!(
+            html_config.resolved_theme_dir(root),
+            Some(root.join("my-theme"))
+        );
+
+        let absolute = env::temp_dir().join("absolute-theme");
+        let html_config = HtmlConfig {
+            theme: Some(absolute.clone()),
+            ..Default::default()
+        };
+        assert_eq!(html_config.resolved_theme_dir(root), Some(absolute));
+
+        let html_config = HtmlConfig::default();
+        assert_eq!(html_config.resolved_theme_dir(root), None);
+        assert_eq!(html_config.theme_dir(root), root.join("theme"));
+    }
+
+    #[test]
+    fn curly_quotes_is_an_alias_for_smart_quotes() {
+        let src = r#"
+        [output.html]
+        curly-quotes = true
+
+        [output.html.smart-punctuation]
+        dashes = true
+        "#;
+        let got = Config::from_str(src).unwrap();
+
+        let html = got.html().unwrap();
+        assert_eq!(
+            html.resolved_smart_punctuation(),
+            SmartPunctuation {
+                quotes: true,
+                dashes: true,
+                ellipses: false,
+            }
+        );
+    }
+
+    #[test]
+    fn relative_source_dir_is_resolved_against_the_book_root() {
+        let root = Path::new("/path/to/book");
+
+        let mut cfg = Config::default();
+        assert_eq!(cfg.source_dir(root), root.join("src"));
+
+        let absolute = env::temp_dir().join("absolute-src");
+        cfg.book.src = absolute.clone();
+        assert_eq!(cfg.source_dir(root), absolute);
+    }
+
     #[test]
     #[should_panic(expected = "Invalid configuration file")]
     fn invalid_language_type_error() {
@@ -9,6 +9,7 @@ use clap::{App, AppSettings, Arg, ArgMatches, Shell, SubCommand};
 use env_logger::Builder;
 use log::LevelFilter;
 use mdbook::utils;
+use mdbook::utils::diagnostics::DiagnosticCollector;
 use std::env;
 use std::ffi::OsStr;
 use std::io::Write;
@@ -19,12 +20,16 @@ mod cmd;
 const VERSION: &str = concat!("v", crate_version!());
 
 fn main() {
-    init_logger();
-
     let app = create_clap_app();
+    let matches = app.get_matches();
+
+    init_logger(
+        global_flag(&matches, "quiet"),
+        global_value(&matches, "log-format") == Some("json"),
+    );
 
     // Check which subcomamnd the user ran...
-    let res = match app.get_matches().subcommand() {
+    let res = match matches.subcommand() {
         ("init", Some(sub_matches)) => cmd::init::execute(sub_matches),
         ("build", Some(sub_matches)) => cmd::build::execute(sub_matches),
         ("clean", Some(sub_matches)) => cmd::clean::execute(sub_matches),
@@ -62,6 +67,22 @@ fn create_clap_app<'a, 'b>() -> App<'a, 'b> {
         .setting(AppSettings::GlobalVersion)
         .setting(AppSettings::ArgRequiredElseHelp)
         .setting(AppSettings::ColoredHelp)
+        .arg(
+            Arg::with_name("quiet")
+                .short("q")
+                .long("quiet")
+                .global(true)
+                .help("Only log warnings and errors"),
+        )
+        .arg(
+            Arg::with_name("log-format")
+                .long("log-format")
+                .value_name("format")
+                .takes_value(true)
+                .possible_values(&["text", "json"])
+                .global(true)
+                .help("How to print log messages. `json` prints one JSON object per line"),
+        )
         .after_help(
             "For more information about a specific command, try `mdbook <command> --help`\n\
              The source code for mdBook is available at: https://github.com/rust-lang/mdBook",
@@ -91,19 +112,50 @@ fn create_clap_app<'a, 'b>() -> App<'a, 'b> {
     app
 }
 
-fn init_logger() {
+/// Whether a global flag was passed, either before or after the subcommand.
+fn global_flag(matches: &ArgMatches<'_>, name: &str) -> bool {
+    matches.is_present(name)
+        || matches
+            .subcommand()
+            .1
+            .map(|sub| sub.is_present(name))
+            .unwrap_or(false)
+}
+
+/// The value of a global option, either before or after the subcommand.
+fn global_value<'a>(matches: &'a ArgMatches<'_>, name: &str) -> Option<&'a str> {
+    matches
+        .subcommand()
+        .1
+        .and_then(|sub| sub.value_of(name))
+        .or_else(|| matches.value_of(name))
+}
+
+fn init_logger(quiet: bool, json: bool) {
     let mut builder = Builder::new();
 
-    builder.format(|formatter, record| {
-        writeln!(
-            formatter,
-            "{} [{}] ({}): {}",
-            Local::now().format("%Y-%m-%d %H:%M:%S"),
-            record.level(),
-            record.target(),
-            record.args()
-        )
-    });
+    if json {
+        builder.format(|formatter, record| {
+            let line = serde_json::json!({
+                "timestamp": Local::now().to_rfc3339(),
+                "level": record.level().to_string(),
+                "module": record.target(),
+                "message": record.args().to_string(),
+            });
+            writeln!(formatter, "{}", line)
+        });
+    } else {
+        builder.format(|formatter, record| {
+            writeln!(
+                formatter,
+                "{} [{}] ({}): {}",
+                Local::now().format("%Y-%m-%d %H:%M:%S"),
+                record.level(),
+                record.target(),
+                record.args()
+            )
+        });
+    }
 
     if let Ok(var) = env::var("RUST_LOG") {
         builder.parse_filters(&var);
@@ -113,8 +165,15 @@ fn init_logger() {
         // Filter extraneous html5ever not-implemented messages
         builder.filter(Some("html5ever"), LevelFilter::Error);
     }
+    if quiet {
+        builder.filter(None, LevelFilter::Warn);
+    }
 
-    builder.init();
+    let logger = builder.build();
+    let max_level = logger.filter().max(LevelFilter::Warn);
+    log::set_boxed_logger(Box::new(DiagnosticCollector::new(logger)))
+        .expect("the logger is only initialized once");
+    log::set_max_level(max_level);
 }
 
 fn get_book_dir(args: &ArgMatches) -> PathBuf {
@@ -26,7 +26,7 @@ impl Preprocessor for IndexPreprocessor {
     }
 
     fn run(&self, ctx: &PreprocessorContext, mut book: Book) -> Result<Book> {
-        let source_dir = ctx.root.join(&ctx.config.book.src);
+        let source_dir = ctx.config.source_dir(&ctx.root);
         book.for_each_mut(|section: &mut BookItem| {
             if let BookItem::Chapter(ref mut ch) = *section {
                 if let Some(ref mut path) = ch.path {
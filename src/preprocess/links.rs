@@ -1,6 +1,6 @@
 use crate::errors::*;
 use crate::utils::{
-    take_anchored_lines, take_lines, take_rustdoc_include_anchored_lines,
+    diagnostics, take_anchored_lines, take_lines, take_rustdoc_include_anchored_lines,
     take_rustdoc_include_lines,
 };
 use regex::{CaptureMatches, Captures, Regex};
@@ -8,8 +8,9 @@ use std::fs;
 use std::ops::{Bound, Range, RangeBounds, RangeFrom, RangeFull, RangeTo};
 use std::path::{Path, PathBuf};
 
+use super::remote_include::RemoteIncludes;
 use super::{Preprocessor, PreprocessorContext};
-use crate::book::{Book, BookItem};
+use crate::book::{Book, BookItem, SourceMap, SourceMapBuilder};
 
 const ESCAPE_CHAR: char = '\\';
 const MAX_LINK_NESTED_DEPTH: usize = 10;
@@ -23,6 +24,8 @@ const MAX_LINK_NESTED_DEPTH: usize = 10;
 ///   This hides the lines from initial display but shows them when the reader expands the code
 ///   block and provides them to Rustdoc for testing.
 /// - `{{# playground}}` - Insert runnable Rust files
+/// - `{{# include-url}}` - Insert the contents of a URL, if `build.allow-remote-includes` is set.
+///   The downloaded text is inserted as it is, without expanding any helpers in it.
 /// - `{{# title}}` - Override \<title\> of a webpage.
 #[derive(Default)]
 pub struct LinkPreprocessor;
@@ -42,24 +45,35 @@ impl Preprocessor for LinkPreprocessor {
     }
 
     fn run(&self, ctx: &PreprocessorContext, mut book: Book) -> Result<Book> {
-        let src_dir = ctx.root.join(&ctx.config.book.src);
+        let src_dir = ctx.config.source_dir(&ctx.root);
+        let remote = RemoteIncludes::new(&ctx.config.build, &ctx.root);
 
         book.for_each_mut(|section: &mut BookItem| {
             if let BookItem::Chapter(ref mut ch) = *section {
-                if let Some(ref chapter_path) = ch.path {
+                if let Some(chapter_path) = ch.path.clone() {
                     let base = chapter_path
                         .parent()
                         .map(|dir| src_dir.join(dir))
                         .expect("All book items have a parent");
 
+                    let file = src_dir.join(ch.source_path.as_ref().unwrap_or(&chapter_path));
                     let mut chapter_title = ch.name.clone();
-                    let content =
-                        replace_all(&ch.content, base, chapter_path, 0, &mut chapter_title);
-                    ch.content = content;
+                    let (content, source_map) = replace_all(
+                        &ch.content,
+                        base,
+                        &chapter_path,
+                        0,
+                        &mut chapter_title,
+                        &file,
+                        ch.meta.front_matter_lines + 1,
+                        &remote,
+                    );
+                    ch.set_content(content);
+                    ch.source_map = Some(source_map);
                     if chapter_title != ch.name {
                         ctx.chapter_titles
                             .borrow_mut()
-                            .insert(chapter_path.clone(), chapter_title);
+                            .insert(chapter_path, chapter_title);
                     }
                 }
             }
@@ -69,13 +83,19 @@ impl Preprocessor for LinkPreprocessor {
     }
 }
 
+/// Expand the links in `s`, which was read from `file` starting at
+/// `first_line`, returning the new text and where its lines came from.
+#[allow(clippy::too_many_arguments)]
 fn replace_all<P1, P2>(
     s: &str,
     path: P1,
     source: P2,
     depth: usize,
     chapter_title: &mut String,
-) -> String
+    file: &Path,
+    first_line: usize,
+    remote: &RemoteIncludes,
+) -> (String, SourceMap)
 where
     P1: AsRef<Path>,
     P2: AsRef<Path>,
@@ -86,38 +106,72 @@ where
     let path = path.as_ref();
     let source = source.as_ref();
     let mut previous_end_index = 0;
-    let mut replaced = String::new();
+    let mut replaced = SourceMapBuilder::new();
+    let line_at = |index: usize| first_line + s[..index].matches('\n').count();
 
     for link in find_links(s) {
-        replaced.push_str(&s[previous_end_index..link.start_index]);
+        replaced.push_str(
+            &s[previous_end_index..link.start_index],
+            file,
+            line_at(previous_end_index),
+        );
+
+        if let LinkType::IncludeUrl(_) = link.link_type {
+            if !remote.is_allowed() {
+                diagnostics::at(file, Some(line_at(link.start_index)), || {
+                    warn!(
+                        "{} uses {{{{#include-url}}}}, which is ignored unless \
+                         `build.allow-remote-includes` is set",
+                        file.display()
+                    )
+                });
+                previous_end_index = link.start_index;
+                continue;
+            }
+        }
 
-        match link.render_with_path(&path, chapter_title) {
+        match link.render_with_path(&path, chapter_title, remote) {
             Ok(new_content) => {
-                if depth < MAX_LINK_NESTED_DEPTH {
-                    if let Some(rel_path) = link.link_type.relative_path(path) {
-                        replaced.push_str(&replace_all(
+                if let LinkType::IncludeUrl(url) = link.link_type {
+                    // Never expanded again, so a remote document can't pull
+                    // local files into the book.
+                    replaced.push_str(&new_content, Path::new(url), 1);
+                } else if depth < MAX_LINK_NESTED_DEPTH {
+                    let origin = link.origin(path);
+                    if let (Some(rel_path), Some((included, line))) =
+                        (link.link_type.relative_path(path), origin)
+                    {
+                        let (expanded, map) = replace_all(
                             &new_content,
                             rel_path,
                             source,
                             depth + 1,
                             chapter_title,
-                        ));
+                            &included,
+                            line,
+                            remote,
+                        );
+                        replaced.push_mapped(&expanded, &map);
                     } else {
-                        replaced.push_str(&new_content);
+                        replaced.push_str(&new_content, file, line_at(link.start_index));
                     }
                 } else {
-                    error!(
-                        "Stack depth exceeded in {}. Check for cyclic includes",
-                        source.display()
-                    );
+                    diagnostics::at(file, Some(line_at(link.start_index)), || {
+                        error!(
+                            "Stack depth exceeded in {}. Check for cyclic includes",
+                            source.display()
+                        )
+                    });
                 }
                 previous_end_index = link.end_index;
             }
             Err(e) => {
-                error!("Error updating \"{}\", {}", link.link_text, e);
-                for cause in e.chain().skip(1) {
-                    warn!("Caused By: {}", cause);
-                }
+                diagnostics::at(file, Some(line_at(link.start_index)), || {
+                    error!("Error updating \"{}\", {}", link.link_text, e);
+                    for cause in e.chain().skip(1) {
+                        warn!("Caused By: {}", cause);
+                    }
+                });
 
                 // This should make sure we include the raw `{{# ... }}` snippet
                 // in the page content if there are any errors.
@@ -126,8 +180,8 @@ where
         }
     }
 
-    replaced.push_str(&s[previous_end_index..]);
-    replaced
+    replaced.push_str(&s[previous_end_index..], file, line_at(previous_end_index));
+    replaced.finish()
 }
 
 #[derive(PartialEq, Debug, Clone)]
@@ -137,6 +191,7 @@ enum LinkType<'a> {
     Playground(PathBuf, Vec<&'a str>),
     RustdocInclude(PathBuf, RangeOrAnchor),
     Title(&'a str),
+    IncludeUrl(&'a str),
 }
 
 #[derive(PartialEq, Debug, Clone)]
@@ -207,6 +262,7 @@ impl<'a> LinkType<'a> {
             LinkType::Playground(p, _) => Some(return_relative_path(base, &p)),
             LinkType::RustdocInclude(p, _) => Some(return_relative_path(base, &p)),
             LinkType::Title(_) => None,
+            LinkType::IncludeUrl(_) => None,
         }
     }
 }
@@ -297,6 +353,7 @@ impl<'a> Link<'a> {
                         Some(LinkType::Playground(pth.into(), props))
                     }
                     ("rustdoc_include", Some(pth)) => Some(parse_rustdoc_include_path(pth)),
+                    ("include-url", Some(url)) => Some(LinkType::IncludeUrl(url)),
                     _ => None,
                 }
             }
@@ -316,10 +373,29 @@ impl<'a> Link<'a> {
         })
     }
 
+    /// The file this link's contents are read from, and the line of it they
+    /// start at. Anchored includes are mapped to the start of the file.
+    fn origin(&self, base: &Path) -> Option<(PathBuf, usize)> {
+        match self.link_type {
+            LinkType::Include(ref pat, RangeOrAnchor::Range(ref range)) => {
+                let first_line = match range.start_bound() {
+                    Bound::Included(&start) => start + 1,
+                    _ => 1,
+                };
+                Some((base.join(pat), first_line))
+            }
+            LinkType::Include(ref pat, RangeOrAnchor::Anchor(_))
+            | LinkType::RustdocInclude(ref pat, _)
+            | LinkType::Playground(ref pat, _) => Some((base.join(pat), 1)),
+            LinkType::Escaped | LinkType::Title(_) | LinkType::IncludeUrl(_) => None,
+        }
+    }
+
     fn render_with_path<P: AsRef<Path>>(
         &self,
         base: P,
         chapter_title: &mut String,
+        remote: &RemoteIncludes,
     ) -> Result<String> {
         let base = base.as_ref();
         match self.link_type {
@@ -386,6 +462,7 @@ impl<'a> Link<'a> {
                 *chapter_title = title.to_owned();
                 Ok(String::new())
             }
+            LinkType::IncludeUrl(url) => remote.fetch(url),
         }
     }
 }
@@ -413,7 +490,7 @@ fn find_links(contents: &str) -> LinkIter<'_> {
             \\\{\{\#.*\}\}      # match escaped link
             |                   # or
             \{\{\s*             # link opening parens and whitespace
-            \#([a-zA-Z0-9_]+)   # link type
+            \#([a-zA-Z0-9_-]+)  # link type
             \s+                 # separating whitespace
             ([^}]+)             # link target path and space separated properties
             \}\}                # link closing parens"
@@ -440,7 +517,20 @@ mod tests {
         {{#include file.rs}} << an escaped link!
         ```";
         let mut chapter_title = "test_replace_all_escaped".to_owned();
-        assert_eq!(replace_all(start, "", "", 0, &mut chapter_title), end);
+        assert_eq!(
+            replace_all(
+                start,
+                "",
+                "",
+                0,
+                &mut chapter_title,
+                Path::new(""),
+                1,
+                &RemoteIncludes::default()
+            )
+            .0,
+            end
+        );
     }
 
     #[test]
@@ -452,10 +542,149 @@ mod tests {
         # My Chapter
         ";
         let mut chapter_title = "test_set_chapter_title".to_owned();
-        assert_eq!(replace_all(start, "", "", 0, &mut chapter_title), end);
+        assert_eq!(
+            replace_all(
+                start,
+                "",
+                "",
+                0,
+                &mut chapter_title,
+                Path::new(""),
+                1,
+                &RemoteIncludes::default()
+            )
+            .0,
+            end
+        );
         assert_eq!(chapter_title, "My Title");
     }
 
+    #[test]
+    fn included_lines_are_mapped_back_to_their_file() {
+        let temp = tempfile::tempdir().unwrap();
+        let listing = temp.path().join("listing.rs");
+        fs::write(&listing, "one\ntwo\nthree\nfour\nfive\n").unwrap();
+        let chapter = temp.path().join("chapter.md");
+        let start = "# Chapter\n\n{{#include listing.rs:3:4}}\n\nAfter\n";
+        let mut chapter_title = String::from("Chapter");
+
+        let (content, map) = replace_all(
+            start,
+            temp.path(),
+            "chapter.md",
+            0,
+            &mut chapter_title,
+            &chapter,
+            1,
+            &RemoteIncludes::default(),
+        );
+
+        assert_eq!(content, "# Chapter\n\nthree\nfour\n\nAfter\n");
+        assert_eq!(map.lookup(1), Some((chapter.as_path(), 1)));
+        assert_eq!(map.lookup(3), Some((listing.as_path(), 3)));
+        assert_eq!(map.lookup(4), Some((listing.as_path(), 4)));
+        assert_eq!(map.lookup(6), Some((chapter.as_path(), 5)));
+    }
+
+    #[test]
+    fn lines_are_mapped_past_the_front_matter() {
+        use crate::book::load_book;
+        use crate::config::{BuildConfig, Config};
+
+        let temp = tempfile::tempdir().unwrap();
+        let src = temp.path().join("src");
+        fs::create_dir(&src).unwrap();
+        fs::write(
+            src.join("SUMMARY.md"),
+            "# Summary\n\n- [Chapter](chapter.md)\n",
+        )
+        .unwrap();
+        fs::write(
+            src.join("chapter.md"),
+            "+++\nno-index = true\n+++\n# Chapter\n\n{{#include listing.rs}}\nAfter\n",
+        )
+        .unwrap();
+        fs::write(src.join("listing.rs"), "fn main() {}\n").unwrap();
+
+        let book = load_book(&src, &BuildConfig::default()).unwrap();
+        let ctx = PreprocessorContext::new(
+            temp.path().to_path_buf(),
+            Config::default(),
+            "html".to_string(),
+        );
+        let book = LinkPreprocessor::new().run(&ctx, book).unwrap();
+
+        let ch = match &book.sections[0] {
+            BookItem::Chapter(ch) => ch,
+            _ => unreachable!(),
+        };
+        let map = ch.source_map.as_ref().unwrap();
+        let chapter = src.join("chapter.md");
+        let listing = src.join("listing.rs");
+        assert_eq!(map.lookup(1), Some((chapter.as_path(), 4)));
+        assert_eq!(map.lookup(3), Some((listing.as_path(), 1)));
+        assert_eq!(ch.content.lines().nth(3), Some("After"));
+        assert_eq!(map.lookup(4), Some((chapter.as_path(), 7)));
+    }
+
+    #[test]
+    fn remote_includes_are_off_by_default() {
+        let start = "{{#include-url http://127.0.0.1:1/nothing.md}}";
+        let mut chapter_title = String::from("Chapter");
+
+        let (content, _) = replace_all(
+            start,
+            "",
+            "",
+            0,
+            &mut chapter_title,
+            Path::new("chapter.md"),
+            1,
+            &RemoteIncludes::default(),
+        );
+
+        assert_eq!(content, start);
+    }
+
+    #[test]
+    fn remote_includes_are_not_expanded_again() {
+        use super::super::remote_include::tests::{curl_is_installed, serve};
+        use crate::config::BuildConfig;
+
+        if !curl_is_installed() {
+            eprintln!("skipping, curl isn't installed");
+            return;
+        }
+        let temp = tempfile::tempdir().unwrap();
+        fs::write(temp.path().join("secret.txt"), "local secret").unwrap();
+        let url = serve("remote text\n{{#include secret.txt}}\n", 1);
+        let chapter = temp.path().join("chapter.md");
+        let start = format!("# Chapter\n{{{{#include-url {}}}}}\nAfter\n", url);
+        let cfg = BuildConfig {
+            allow_remote_includes: true,
+            ..Default::default()
+        };
+        let mut chapter_title = String::from("Chapter");
+
+        let (content, map) = replace_all(
+            &start,
+            temp.path(),
+            "chapter.md",
+            0,
+            &mut chapter_title,
+            &chapter,
+            1,
+            &RemoteIncludes::new(&cfg, temp.path()),
+        );
+
+        assert_eq!(
+            content,
+            "# Chapter\nremote text\n{{#include secret.txt}}\n\nAfter\n"
+        );
+        assert_eq!(map.lookup(2), Some((Path::new(&url), 1)));
+        assert_eq!(map.lookup(3), Some((Path::new(&url), 2)));
+    }
+
     #[test]
     fn test_find_links_no_link() {
         let s = "Some random text without link...";
@@ -7,6 +7,7 @@ pub use self::links::LinkPreprocessor;
 mod cmd;
 mod index;
 mod links;
+mod remote_include;
 
 use crate::book::Book;
 use crate::config::Config;
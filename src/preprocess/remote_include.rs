@@ -0,0 +1,230 @@
+//! Downloading the text for `{{#include-url <url>}}`, which the links
+//! preprocessor expands.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+
+use sha2::{Digest, Sha256};
+
+use crate::config::BuildConfig;
+use crate::errors::*;
+
+/// How long to wait for a remote include when `build.remote-include-timeout`
+/// isn't set.
+const DEFAULT_TIMEOUT_SECS: u64 = 30;
+
+/// The `{{#include-url}}` settings from `[build]`.
+///
+/// The default doesn't allow remote includes at all.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct RemoteIncludes {
+    allowed: bool,
+    timeout_secs: Option<u64>,
+    cache_dir: Option<PathBuf>,
+}
+
+impl RemoteIncludes {
+    /// The settings from `cfg`, with the cache directory relative to `root`.
+    pub(crate) fn new(cfg: &BuildConfig, root: &Path) -> RemoteIncludes {
+        RemoteIncludes {
+            allowed: cfg.allow_remote_includes,
+            timeout_secs: cfg.remote_include_timeout,
+            cache_dir: cfg.remote_include_cache.as_ref().map(|dir| root.join(dir)),
+        }
+    }
+
+    /// Whether `build.allow-remote-includes` is set.
+    pub(crate) fn is_allowed(&self) -> bool {
+        self.allowed
+    }
+
+    /// The contents of `url`.
+    ///
+    /// Each URL is only downloaded once per process, so rebuilds by
+    /// `mdbook serve` and `mdbook watch` don't need the network. Downloads are
+    /// done with `curl`, and also saved in `build.remote-include-cache`, if it
+    /// is set, so later builds don't need the network either.
+    pub(crate) fn fetch(&self, url: &str) -> Result<String> {
+        lazy_static! {
+            static ref FETCHED: Mutex<HashMap<String, String>> = Mutex::new(HashMap::new());
+        }
+
+        // Anything else could read local files, or be taken by curl as an option.
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            bail!(
+                "Unable to fetch {}: only http and https URLs can be included",
+                url
+            );
+        }
+
+        if let Some(text) = FETCHED.lock().unwrap().get(url) {
+            return Ok(text.clone());
+        }
+
+        let cache_file = self
+            .cache_dir
+            .as_ref()
+            .map(|dir| dir.join(format!("{:x}", Sha256::digest(url.as_bytes()))));
+
+        let text = match cache_file {
+            Some(ref cache_file) if cache_file.exists() => {
+                debug!("Using the cached copy of {}", url);
+                fs::read_to_string(cache_file)
+                    .with_context(|| format!("Unable to read {}", cache_file.display()))?
+            }
+            _ => {
+                let timeout = self.timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS);
+                let text = fetch(url, timeout)?;
+                if let Some(ref cache_file) = cache_file {
+                    crate::utils::fs::write_file(
+                        cache_file.parent().unwrap(),
+                        cache_file.file_name().unwrap(),
+                        text.as_bytes(),
+                    )?;
+                }
+                text
+            }
+        };
+
+        FETCHED
+            .lock()
+            .unwrap()
+            .insert(url.to_string(), text.clone());
+        Ok(text)
+    }
+}
+
+fn fetch(url: &str, timeout_secs: u64) -> Result<String> {
+    info!("Fetching {}", url);
+    let output = Command::new("curl")
+        .arg("--silent")
+        .arg("--show-error")
+        .arg("--fail")
+        .arg("--location")
+        .arg("--proto")
+        .arg("=http,https")
+        .arg("--proto-redir")
+        .arg("=http,https")
+        .arg("--max-time")
+        .arg(timeout_secs.to_string())
+        .arg("--")
+        .arg(url)
+        .output()
+        .with_context(|| format!("Unable to run curl to fetch {}", url))?;
+
+    if !output.status.success() {
+        bail!(
+            "Unable to fetch {}: {}",
+            url,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    String::from_utf8(output.stdout).with_context(|| format!("{} is not valid UTF-8", url))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// Whether `curl` can be run, which the tests that download anything
+    /// need.
+    pub(crate) fn curl_is_installed() -> bool {
+        Command::new("curl").arg("--version").output().is_ok()
+    }
+
+    /// Serve `body` to the given number of requests on a local port,
+    /// returning the server's URL.
+    pub(crate) fn serve(body: &'static str, requests: usize) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/snippet.md", listener.local_addr().unwrap());
+
+        thread::spawn(move || {
+            for stream in listener.incoming().take(requests) {
+                let mut stream = stream.unwrap();
+                let mut request = [0; 1024];
+                let _ = stream.read(&mut request).unwrap();
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                )
+                .unwrap();
+            }
+        });
+
+        url
+    }
+
+    #[test]
+    fn downloads_are_cached() {
+        if !curl_is_installed() {
+            eprintln!("skipping, curl isn't installed");
+            return;
+        }
+        let url = serve("remote text", 1);
+        let temp = tempfile::tempdir().unwrap();
+        let cfg = BuildConfig {
+            allow_remote_includes: true,
+            remote_include_cache: Some("cache".into()),
+            ..Default::default()
+        };
+        let remote = RemoteIncludes::new(&cfg, temp.path());
+
+        assert_eq!(remote.fetch(&url).unwrap(), "remote text");
+        let cache_file = temp
+            .path()
+            .join("cache")
+            .join(format!("{:x}", Sha256::digest(url.as_bytes())));
+        assert_eq!(fs::read_to_string(cache_file).unwrap(), "remote text");
+
+        // The server is gone, so this has to come from a cache.
+        assert_eq!(remote.fetch(&url).unwrap(), "remote text");
+    }
+
+    #[test]
+    fn only_http_urls_are_fetched() {
+        let cfg = BuildConfig {
+            allow_remote_includes: true,
+            ..Default::default()
+        };
+        let remote = RemoteIncludes::new(&cfg, Path::new(""));
+
+        for url in &["file:///etc/passwd", "-K/etc/passwd"] {
+            let err = remote.fetch(url).unwrap_err();
+
+            let message = format!("{:#}", err);
+            assert!(message.contains("only http and https"), "{}", message);
+        }
+    }
+
+    #[test]
+    fn failures_name_the_url() {
+        if !curl_is_installed() {
+            eprintln!("skipping, curl isn't installed");
+            return;
+        }
+        let cfg = BuildConfig {
+            allow_remote_includes: true,
+            remote_include_timeout: Some(5),
+            ..Default::default()
+        };
+        let remote = RemoteIncludes::new(&cfg, Path::new(""));
+
+        let err = remote.fetch("http://127.0.0.1:1/nothing.md").unwrap_err();
+
+        let message = format!("{:#}", err);
+        assert!(
+            message.contains("http://127.0.0.1:1/nothing.md"),
+            "{}",
+            message
+        );
+    }
+}
@@ -1,5 +1,8 @@
-use crate::book::{Book, BookItem};
-use crate::config::{BookConfig, Config, HtmlConfig, Playground, RustEdition};
+use crate::book::{output_path_for, Book, BookItem, Chapter};
+use crate::config::{
+    BookConfig, Config, HeadingAnchor, HtmlConfig, Markdown, MathEngine, Playground, RustEdition,
+    SearchIndexField,
+};
 use crate::errors::*;
 use crate::renderer::html_handlebars::helpers;
 use crate::renderer::{RenderContext, Renderer};
@@ -9,12 +12,20 @@ use crate::utils;
 use std::borrow::Cow;
 use std::collections::BTreeMap;
 use std::collections::HashMap;
+use std::fmt::Write;
 use std::fs::{self, File};
+use std::io;
 use std::path::{Path, PathBuf};
 
+use crate::utils::definition_list::expand_definition_lists;
+use crate::utils::diagnostics;
 use crate::utils::fs::get_404_output_file;
+use flate2::write::GzEncoder;
+use flate2::Compression;
 use handlebars::Handlebars;
+use pulldown_cmark::{Event, Options, Tag};
 use regex::{Captures, Regex};
+use sha2::{Digest, Sha256};
 
 #[derive(Default)]
 pub struct HtmlHandlebars;
@@ -33,32 +44,71 @@ impl HtmlHandlebars {
         // FIXME: This should be made DRY-er and rely less on mutable state
 
         let (ch, path) = match item {
-            BookItem::Chapter(ch) if !ch.is_draft_chapter() => (ch, ch.path.as_ref().unwrap()),
+            BookItem::Chapter(ch) => match ch.path {
+                Some(ref path) => (ch, path),
+                // Drafts and links to other sites don't get a page.
+                None => return Ok(()),
+            },
             _ => return Ok(()),
         };
 
-        if let Some(ref edit_url_template) = ctx.html_config.edit_url_template {
+        // Chapters which were generated rather than read from a file (e.g. by
+        // a preprocessor) have nothing to edit.
+        if let (Some(edit_url_template), Some(source_path)) =
+            (&ctx.html_config.edit_url_template, &ch.source_path)
+        {
             let full_path = ctx.book_config.src.to_str().unwrap_or_default().to_owned()
                 + "/"
-                + ch.source_path
-                    .clone()
-                    .unwrap_or_default()
-                    .to_str()
-                    .unwrap_or_default();
+                + &utils::fs::normalize_path(source_path.to_str().unwrap_or_default());
 
             let edit_url = edit_url_template.replace("{path}", &full_path);
             ctx.data
                 .insert("git_repository_edit_url".to_owned(), json!(edit_url));
         }
 
-        let content = ch.content.clone();
-        let content = utils::render_markdown(&content, ctx.html_config.curly_quotes);
-
-        let fixed_content = utils::render_markdown_with_path(
-            &ch.content,
-            ctx.html_config.curly_quotes,
+        let source = if ctx.html_config.markdown.definition_lists {
+            Cow::Owned(expand_definition_lists(&ch.content))
+        } else {
+            Cow::Borrowed(&ch.content)
+        };
+        let content = utils::render_markdown_with_options(
+            &source,
+            ctx.html_config.resolved_smart_punctuation(),
+            None,
+            ctx.markdown_options,
+        );
+        let mut fixed_content = utils::render_markdown_with_options(
+            &source,
+            ctx.html_config.resolved_smart_punctuation(),
             Some(&path),
+            ctx.markdown_options,
+        );
+        let content = if ch.meta.no_playground {
+            fixed_content = disable_playground(&fixed_content);
+            disable_playground(&content)
+        } else {
+            content
+        };
+        // With a `<base>` tag, links are resolved from the root of the book
+        // like they are on the print page.
+        let content = if ctx.html_config.use_base_href {
+            fixed_content.clone()
+        } else {
+            content
+        };
+        let content = format!(
+            "<div class=\"{}\">\n{}</div>\n",
+            chapter_classes(ch, path),
+            content
         );
+
+        let content = match ctx.html_config.site_base_path {
+            Some(ref base_path) => {
+                fixed_content = prefix_absolute_links(&fixed_content, base_path);
+                prefix_absolute_links(&content, base_path)
+            }
+            None => content,
+        };
         if !ctx.is_index {
             // Add page break between chapters
             // See https://developer.mozilla.org/en-US/docs/Web/CSS/break-before and https://developer.mozilla.org/en-US/docs/Web/CSS/page-break-before
@@ -68,14 +118,22 @@ impl HtmlHandlebars {
         }
         print_content.push_str(&fixed_content);
 
+        if let Some(changed) = ctx.changed_chapters {
+            if !changed.contains(path) {
+                trace!("Keeping the page for {}", path.display());
+                return Ok(());
+            }
+        }
+
         // Update the context with data for this file
         let ctx_path = path
             .to_str()
             .with_context(|| "Could not convert path to str")?;
-        let filepath = Path::new(&ctx_path).with_extension("html");
+        let filepath =
+            output_path_for(ch, ctx.index_file_names).expect("draft chapters are skipped");
 
-        // "print.html" is used for the print page.
-        if path == Path::new("print.md") {
+        // The print page's name is taken.
+        if *path == Path::new(&ctx.html_config.print.output_name).with_extension("md") {
             bail!("{} is reserved for internal use", path.display());
         };
 
@@ -85,12 +143,18 @@ impl HtmlHandlebars {
             .and_then(serde_json::Value::as_str)
             .unwrap_or("");
 
+        let name = match ch.number {
+            Some(ref number) if ctx.html_config.number_in_title => {
+                format!("{} {}", number, ch.display_name())
+            }
+            _ => ch.display_name(),
+        };
         let title = if let Some(title) = ctx.chapter_titles.get(path) {
             title.clone()
         } else if book_title.is_empty() {
-            ch.name.clone()
+            name
         } else {
-            ch.name.clone() + " - " + book_title
+            name + " - " + book_title
         };
 
         ctx.data.insert("path".to_owned(), json!(path));
@@ -101,16 +165,43 @@ impl HtmlHandlebars {
             "path_to_root".to_owned(),
             json!(utils::fs::path_to_root(&path)),
         );
+        if ctx.html_config.use_base_href {
+            insert_base_href(&mut ctx.data, path);
+        }
         if let Some(ref section) = ch.number {
             ctx.data
                 .insert("section".to_owned(), json!(section.to_string()));
         }
+        if ch.meta.no_index {
+            ctx.data.insert("no_index".to_owned(), json!(true));
+        }
+        ctx.data.insert(
+            "page_toc".to_owned(),
+            json!(ch.headings_with_style(
+                ctx.html_config.page_toc_max_level,
+                ctx.html_config.anchor_style()
+            )),
+        );
+        insert_open_graph_data(
+            &mut ctx.data,
+            ch,
+            &filepath,
+            &ctx.html_config,
+            &ctx.book_config,
+        );
 
         // Render the handlebars template with the data
         debug!("Render template");
-        let rendered = ctx.handlebars.render("index", &ctx.data)?;
+        let template = chapter_template(ch);
+        let rendered = ctx.handlebars.render(&template, &ctx.data)?;
 
-        let rendered = self.post_process(rendered, &ctx.html_config.playground, ctx.edition);
+        let mut rendered = self.post_process(rendered, &ctx.html_config, ctx.edition);
+        if ctx.html_config.code_line_anchors {
+            rendered = add_code_line_anchors(&rendered, &chapter_slug(path));
+        }
+        if ctx.html_config.use_base_href {
+            rendered = fragment_links_to_page(&rendered, &filepath);
+        }
 
         // Write to file
         debug!("Creating {}", filepath.display());
@@ -119,10 +210,19 @@ impl HtmlHandlebars {
         if ctx.is_index {
             ctx.data.insert("path".to_owned(), json!("index.md"));
             ctx.data.insert("path_to_root".to_owned(), json!(""));
+            if ctx.html_config.use_base_href {
+                insert_base_href(&mut ctx.data, Path::new("index.md"));
+            }
             ctx.data.insert("is_index".to_owned(), json!("true"));
-            let rendered_index = ctx.handlebars.render("index", &ctx.data)?;
-            let rendered_index =
-                self.post_process(rendered_index, &ctx.html_config.playground, ctx.edition);
+            let rendered_index = ctx.handlebars.render(&template, &ctx.data)?;
+            let mut rendered_index =
+                self.post_process(rendered_index, &ctx.html_config, ctx.edition);
+            if ctx.html_config.code_line_anchors {
+                rendered_index = add_code_line_anchors(&rendered_index, &chapter_slug(path));
+            }
+            if ctx.html_config.use_base_href {
+                rendered_index = fragment_links_to_page(&rendered_index, Path::new("index.html"));
+            }
             debug!("Creating index.html from {}", ctx_path);
             utils::fs::write_file(&ctx.destination, "index.html", rendered_index.as_bytes())?;
         }
@@ -156,7 +256,12 @@ impl HtmlHandlebars {
                     .to_string()
             }
         };
-        let html_content_404 = utils::render_markdown(&content_404, html_config.curly_quotes);
+        let html_content_404 = utils::render_markdown_with_options(
+            &content_404,
+            html_config.resolved_smart_punctuation(),
+            None,
+            utils::markdown_options(&html_config.markdown),
+        );
 
         let mut data_404 = data.clone();
         let base_url = if let Some(site_url) = &html_config.site_url {
@@ -175,8 +280,7 @@ impl HtmlHandlebars {
         data_404.insert("content".to_owned(), json!(html_content_404));
         let rendered = handlebars.render("index", &data_404)?;
 
-        let rendered =
-            self.post_process(rendered, &html_config.playground, ctx.config.rust.edition);
+        let rendered = self.post_process(rendered, html_config, ctx.config.rust.edition);
         let output_file = get_404_output_file(&html_config.input_404);
         utils::fs::write_file(&destination, output_file, rendered.as_bytes())?;
         debug!("Creating 404.html ✓");
@@ -187,18 +291,23 @@ impl HtmlHandlebars {
     fn post_process(
         &self,
         rendered: String,
-        playground_config: &Playground,
+        html_config: &HtmlConfig,
         edition: Option<RustEdition>,
     ) -> String {
-        let rendered = build_header_links(&rendered);
+        let rendered = build_header_links(
+            &rendered,
+            &html_config.heading_anchor,
+            html_config.anchor_style(),
+        );
         let rendered = fix_code_blocks(&rendered);
-        let rendered = add_playground_pre(&rendered, playground_config, edition);
+        let rendered = add_playground_pre(&rendered, &html_config.playground, edition);
 
         rendered
     }
 
     fn copy_static_files(
         &self,
+        root: &Path,
         destination: &Path,
         theme: &Theme,
         html_config: &HtmlConfig,
@@ -215,7 +324,15 @@ impl HtmlHandlebars {
             write_file(destination, "CNAME", format!("{}\n", cname).as_bytes())?;
         }
 
-        write_file(destination, "book.js", &theme.js)?;
+        if let Some((file_name, contents)) = js_bundle(theme, html_config) {
+            write_file(destination, &file_name, &contents)?;
+        } else {
+            write_file(destination, "book.js", &theme.js)?;
+            write_file(destination, "highlight.js", &theme.highlight_js)?;
+            if html_config.copy_buttons {
+                write_file(destination, "clipboard.min.js", &theme.clipboard_js)?;
+            }
+        }
         write_file(destination, "css/general.css", &theme.general_css)?;
         write_file(destination, "css/chrome.css", &theme.chrome_css)?;
         if html_config.print.enable {
@@ -231,8 +348,6 @@ impl HtmlHandlebars {
         write_file(destination, "highlight.css", &theme.highlight_css)?;
         write_file(destination, "tomorrow-night.css", &theme.tomorrow_night_css)?;
         write_file(destination, "ayu-highlight.css", &theme.ayu_highlight_css)?;
-        write_file(destination, "highlight.js", &theme.highlight_js)?;
-        write_file(destination, "clipboard.min.js", &theme.clipboard_js)?;
         write_file(
             destination,
             "FontAwesome/css/font-awesome.css",
@@ -268,8 +383,14 @@ impl HtmlHandlebars {
             "FontAwesome/fonts/FontAwesome.ttf",
             theme::FONT_AWESOME_TTF,
         )?;
+        let custom_fonts = load_custom_fonts(root, html_config)?;
+        for (file_name, contents) in &custom_fonts.files {
+            write_file(destination, file_name, contents)?;
+        }
         if html_config.copy_fonts {
-            write_file(destination, "fonts/fonts.css", theme::fonts::CSS)?;
+            let mut css = theme::fonts::CSS.to_vec();
+            css.extend_from_slice(custom_fonts.css.as_bytes());
+            write_file(destination, "fonts/fonts.css", &css)?;
             for (file_name, contents) in theme::fonts::LICENSES.iter() {
                 write_file(destination, file_name, contents)?;
             }
@@ -281,12 +402,14 @@ impl HtmlHandlebars {
                 theme::fonts::SOURCE_CODE_PRO.0,
                 theme::fonts::SOURCE_CODE_PRO.1,
             )?;
+        } else if !custom_fonts.files.is_empty() {
+            write_file(destination, "fonts/fonts.css", custom_fonts.css.as_bytes())?;
         }
 
         let playground_config = &html_config.playground;
 
         // Ace is a very large dependency, so only load it when requested
-        if playground_config.editable && playground_config.copy_js {
+        if playground_config.editable && playground_config.copy_js && !html_config.bundle_js {
             // Load the editor
             write_file(destination, "editor.js", playground_editor::JS)?;
             write_file(destination, "ace.js", playground_editor::ACE_JS)?;
@@ -311,46 +434,92 @@ impl HtmlHandlebars {
         &self,
         data: &mut serde_json::Map<String, serde_json::Value>,
         print_content: &str,
+        output_name: &str,
     ) {
+        let path = Path::new(output_name).with_extension("md");
+
         // Make sure that the Print chapter does not display the title from
         // the last rendered chapter by removing it from its context
         data.remove("title");
         data.insert("is_print".to_owned(), json!(true));
-        data.insert("path".to_owned(), json!("print.md"));
+        data.insert("path".to_owned(), json!(path));
         data.insert("content".to_owned(), json!(print_content));
         data.insert(
             "path_to_root".to_owned(),
-            json!(utils::fs::path_to_root(Path::new("print.md"))),
+            json!(utils::fs::path_to_root(&path)),
         );
     }
 
-    fn register_hbs_helpers(&self, handlebars: &mut Handlebars<'_>, html_config: &HtmlConfig) {
+    fn register_hbs_helpers(
+        &self,
+        handlebars: &mut Handlebars<'_>,
+        html_config: &HtmlConfig,
+        index_file_names: &[String],
+    ) {
         handlebars.register_helper(
             "toc",
             Box::new(helpers::toc::RenderToc {
                 no_section_label: html_config.no_section_label,
+                max_depth: html_config.toc_max_depth,
+                index_file_names: index_file_names.to_vec(),
             }),
         );
-        handlebars.register_helper("previous", Box::new(helpers::navigation::previous));
-        handlebars.register_helper("next", Box::new(helpers::navigation::next));
+        handlebars.register_helper(
+            "previous",
+            Box::new(helpers::navigation::RenderNavigation::previous(
+                index_file_names.to_vec(),
+            )),
+        );
+        handlebars.register_helper(
+            "next",
+            Box::new(helpers::navigation::RenderNavigation::next(
+                index_file_names.to_vec(),
+            )),
+        );
         handlebars.register_helper("theme_option", Box::new(helpers::theme::theme_option));
     }
 
-    /// Copy across any additional CSS and JavaScript files which the book
-    /// has been configured to use.
-    fn copy_additional_css_and_js(
+    /// Copy across any additional CSS, JavaScript and static files which the
+    /// book has been configured to use.
+    fn copy_additional_files(
         &self,
         html: &HtmlConfig,
         root: &Path,
         destination: &Path,
     ) -> Result<()> {
-        let custom_files = html.additional_css.iter().chain(html.additional_js.iter());
+        let static_destination = match html.additional_static_prefix {
+            Some(ref prefix) => destination.join(prefix),
+            None => destination.to_path_buf(),
+        };
+        let custom_files = html
+            .additional_css
+            .iter()
+            .chain(html.additional_js.iter())
+            .filter(|file| !utils::is_external_url(file))
+            .map(|file| (file, destination))
+            .chain(
+                html.additional_static
+                    .iter()
+                    .map(|file| (file, static_destination.as_path())),
+            );
 
-        debug!("Copying additional CSS and JS");
+        debug!("Copying additional CSS, JS and static files");
 
-        for custom_file in custom_files {
+        for (custom_file, destination) in custom_files {
             let input_location = root.join(custom_file);
             let output_location = destination.join(custom_file);
+            if input_location.is_dir() {
+                fs::create_dir_all(&output_location)
+                    .with_context(|| format!("Unable to create {}", output_location.display()))?;
+                utils::fs::copy_files_except_ext(
+                    &input_location,
+                    &output_location,
+                    true,
+                    None,
+                    &[],
+                )?;
+                continue;
+            }
             if let Some(parent) = output_location.parent() {
                 fs::create_dir_all(parent)
                     .with_context(|| format!("Unable to create {}", parent.display()))?;
@@ -398,6 +567,37 @@ impl HtmlHandlebars {
         Ok(())
     }
 
+    /// Redirect `index.html` to the first chapter, unless something (normally
+    /// the first chapter itself) already wrote one.
+    fn emit_index_redirect(
+        &self,
+        destination: &Path,
+        handlebars: &Handlebars<'_>,
+        book: &Book,
+        index_file_names: &[String],
+    ) -> Result<()> {
+        let index = destination.join("index.html");
+        if index.exists() {
+            return Ok(());
+        }
+
+        let first_chapter = book.iter().find_map(|item| match item {
+            BookItem::Chapter(ch) => output_path_for(ch, index_file_names),
+            _ => None,
+        });
+        match first_chapter {
+            Some(path) => {
+                let url = utils::fs::normalize_path(&path.to_string_lossy());
+                debug!("Redirecting index.html to {}", url);
+                self.emit_redirect(handlebars, &index, &url)
+            }
+            None => {
+                warn!("Not generating index.html because the book has no chapters");
+                Ok(())
+            }
+        }
+    }
+
     fn emit_redirect(
         &self,
         handlebars: &Handlebars<'_>,
@@ -436,6 +636,46 @@ impl HtmlHandlebars {
     }
 }
 
+/// Files smaller than this aren't worth compressing.
+const PRECOMPRESS_MIN_SIZE: u64 = 1024;
+
+/// File types which are worth compressing. Fonts and images (other than SVG)
+/// are already compressed.
+const PRECOMPRESS_EXTENSIONS: &[&str] = &["html", "css", "js", "json", "svg", "txt", "xml"];
+
+/// Write a `.gz` file next to each text file in `dir` which is big enough to
+/// benefit from it.
+fn precompress_dir(dir: &Path) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            precompress_dir(&path)?;
+            continue;
+        }
+
+        let is_text = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| PRECOMPRESS_EXTENSIONS.contains(&ext))
+            .unwrap_or(false);
+        if !is_text || path.metadata()?.len() < PRECOMPRESS_MIN_SIZE {
+            continue;
+        }
+
+        let mut file =
+            File::open(&path).with_context(|| format!("Unable to read {}", path.display()))?;
+        let mut gz_path = path.into_os_string();
+        gz_path.push(".gz");
+        let gz_file =
+            File::create(&gz_path).with_context(|| format!("Unable to create {:?}", gz_path))?;
+        let mut encoder = GzEncoder::new(gz_file, Compression::best());
+        io::copy(&mut file, &mut encoder)
+            .and_then(|_| encoder.finish())
+            .with_context(|| format!("Unable to write {:?}", gz_path))?;
+    }
+    Ok(())
+}
+
 // TODO(mattico): Remove some time after the 0.1.8 release
 fn maybe_wrong_theme_dir(dir: &Path) -> Result<bool> {
     fn entry_is_maybe_book_file(entry: fs::DirEntry) -> Result<bool> {
@@ -463,12 +703,14 @@ impl Renderer for HtmlHandlebars {
     fn render(&self, ctx: &RenderContext) -> Result<()> {
         let book_config = &ctx.config.book;
         let html_config = ctx.config.html_config().unwrap_or_default();
-        let src_dir = ctx.root.join(&ctx.config.book.src);
+        let src_dir = ctx.source_dir();
         let destination = &ctx.destination;
         let book = &ctx.book;
         let build_dir = ctx.root.join(&ctx.config.build.build_dir);
+        let index_file_names = ctx.config.index_file_names();
 
-        if destination.exists() {
+        // Pages for chapters which didn't change are kept from the last build.
+        if destination.exists() && ctx.changed_chapters.is_none() {
             utils::fs::remove_dir_content(destination)
                 .with_context(|| "Unable to remove stale HTML output")?;
         }
@@ -476,10 +718,7 @@ impl Renderer for HtmlHandlebars {
         trace!("render");
         let mut handlebars = Handlebars::new();
 
-        let theme_dir = match html_config.theme {
-            Some(ref theme) => ctx.root.join(theme),
-            None => ctx.root.join("theme"),
-        };
+        let theme_dir = html_config.theme_dir(&ctx.root);
 
         if html_config.theme.is_none()
             && maybe_wrong_theme_dir(&src_dir.join("theme")).unwrap_or(false)
@@ -491,7 +730,8 @@ impl Renderer for HtmlHandlebars {
             warn!("Please move your theme files to `./theme` for them to continue being used");
         }
 
-        let theme = theme::Theme::new(theme_dir);
+        let mut theme = theme::Theme::new(&theme_dir);
+        load_custom_favicons(&mut theme, &ctx.root, &html_config)?;
 
         debug!("Register the index handlebars template");
         handlebars.register_template_string("index", String::from_utf8(theme.index.clone())?)?;
@@ -506,10 +746,30 @@ impl Renderer for HtmlHandlebars {
         debug!("Register the header handlebars template");
         handlebars.register_partial("header", String::from_utf8(theme.header.clone())?)?;
 
-        debug!("Register handlebars helpers");
-        self.register_hbs_helpers(&mut handlebars, &html_config);
+        debug!("Register the chapter handlebars templates");
+        register_chapter_templates(&mut handlebars, book, &theme_dir)?;
 
-        let mut data = make_data(&ctx.root, &book, &ctx.config, &html_config, &theme)?;
+        debug!("Register handlebars helpers");
+        self.register_hbs_helpers(&mut handlebars, &html_config, &index_file_names);
+
+        let head_include = match html_config.head_include {
+            Some(ref head_include) => {
+                let path = ctx.root.join(head_include);
+                let html = fs::read_to_string(&path).with_context(|| {
+                    format!("Unable to read the head include {}", path.display())
+                })?;
+                Some(html)
+            }
+            None => None,
+        };
+        let mut data = make_data(
+            &ctx.root,
+            &book,
+            &ctx.config,
+            &html_config,
+            &theme,
+            head_include.as_deref(),
+        )?;
 
         // Print version
         let mut print_content = String::new();
@@ -517,6 +777,7 @@ impl Renderer for HtmlHandlebars {
         fs::create_dir_all(&destination)
             .with_context(|| "Unexpected error when constructing destination path")?;
 
+        let markdown_options = utils::markdown_options(&html_config.markdown);
         let mut is_index = true;
         for item in book.iter() {
             let ctx = RenderItemContext {
@@ -528,6 +789,9 @@ impl Renderer for HtmlHandlebars {
                 html_config: html_config.clone(),
                 edition: ctx.config.rust.edition,
                 chapter_titles: &ctx.chapter_titles,
+                changed_chapters: ctx.changed_chapters.as_deref(),
+                markdown_options,
+                index_file_names: &index_file_names,
             };
             self.render_item(item, ctx, &mut print_content)?;
             is_index = false;
@@ -539,8 +803,9 @@ impl Renderer for HtmlHandlebars {
         }
 
         // Print version
-        self.configure_print_version(&mut data, &print_content);
-        if let Some(ref title) = ctx.config.book.title {
+        let print = &html_config.print;
+        self.configure_print_version(&mut data, &print_content, &print.output_name);
+        if let Some(title) = print.title.as_ref().or(ctx.config.book.title.as_ref()) {
             data.insert("title".to_owned(), json!(title));
         }
 
@@ -549,25 +814,41 @@ impl Renderer for HtmlHandlebars {
             debug!("Render template");
             let rendered = handlebars.render("index", &data)?;
 
-            let rendered =
-                self.post_process(rendered, &html_config.playground, ctx.config.rust.edition);
+            let rendered = self.post_process(rendered, &html_config, ctx.config.rust.edition);
 
-            utils::fs::write_file(&destination, "print.html", rendered.as_bytes())?;
-            debug!("Creating print.html ✓");
+            utils::fs::write_file(&destination, &print.output_name, rendered.as_bytes())?;
+            debug!("Creating {} ✓", print.output_name);
         }
 
         debug!("Copy static files");
-        self.copy_static_files(&destination, &theme, &html_config)
+        self.copy_static_files(&ctx.root, &destination, &theme, &html_config)
             .with_context(|| "Unable to copy across static files")?;
-        self.copy_additional_css_and_js(&html_config, &ctx.root, &destination)
-            .with_context(|| "Unable to copy across additional CSS and JS")?;
+        self.copy_additional_files(&html_config, &ctx.root, &destination)
+            .with_context(|| "Unable to copy across additional files")?;
+
+        if html_config.search_index.enable {
+            debug!("Writing search-index.json");
+            let index = raw_search_index(book, &html_config.search_index.fields, &index_file_names);
+            utils::fs::write_file(
+                destination,
+                "search-index.json",
+                index.to_string().as_bytes(),
+            )?;
+        }
 
         // Render search index
         #[cfg(feature = "search")]
         {
+            let anchor_style = html_config.anchor_style();
             let search = html_config.search.unwrap_or_default();
             if search.enable {
-                super::search::create_files(&search, &destination, &book)?;
+                super::search::create_files(
+                    &search,
+                    anchor_style,
+                    &destination,
+                    &book,
+                    &index_file_names,
+                )?;
             }
         }
 
@@ -577,16 +858,31 @@ impl Renderer for HtmlHandlebars {
         // Copy all remaining files, avoid a recursive copy from/to the book build dir
         utils::fs::copy_files_except_ext(&src_dir, &destination, true, Some(&build_dir), &["md"])?;
 
+        if html_config.generate_index {
+            self.emit_index_redirect(destination, &handlebars, book, &index_file_names)
+                .context("Unable to generate index.html")?;
+        }
+
+        if html_config.precompress {
+            debug!("Precompressing the output");
+            precompress_dir(destination).with_context(|| "Unable to precompress the output")?;
+        }
+
         Ok(())
     }
 }
 
+/// Inline stylesheets bigger than this get a warning, because they are
+/// repeated in every page.
+const MAX_INLINE_CSS_SIZE: usize = 8 * 1024;
+
 fn make_data(
     root: &Path,
     book: &Book,
     config: &Config,
     html_config: &HtmlConfig,
     theme: &Theme,
+    head_include: Option<&str>,
 ) -> Result<serde_json::Map<String, serde_json::Value>> {
     trace!("make_data");
 
@@ -609,9 +905,40 @@ fn make_data(
     if theme.favicon_svg.is_some() {
         data.insert("favicon_svg".to_owned(), json!("favicon.svg"));
     }
+    if let Some(head_include) = head_include {
+        data.insert("head_include".to_owned(), json!(head_include));
+    }
+    if !html_config.inline_css.is_empty() {
+        let mut styles = Vec::new();
+        for style in &html_config.inline_css {
+            let path = root.join(style);
+            let css = fs::read_to_string(&path).with_context(|| {
+                format!("Unable to read the inline stylesheet {}", path.display())
+            })?;
+            if css.len() > MAX_INLINE_CSS_SIZE {
+                warn!(
+                    "{} is {} KiB, which makes every page bigger, consider using \
+                     `output.html.additional-css` for it instead",
+                    style.display(),
+                    css.len() / 1024
+                );
+            }
+            styles.push(css);
+        }
+        data.insert("inline_css".to_owned(), json!(styles));
+    }
     if let Some(ref livereload) = html_config.livereload_url {
         data.insert("livereload".to_owned(), json!(livereload));
     }
+    if let Some(ref footer) = html_config.footer {
+        let footer = utils::render_markdown_with_options(
+            footer,
+            html_config.resolved_smart_punctuation(),
+            None,
+            utils::markdown_options(&Markdown::default()),
+        );
+        data.insert("footer".to_owned(), json!(footer));
+    }
 
     let default_theme = match html_config.default_theme {
         Some(ref theme) => theme.to_lowercase(),
@@ -619,6 +946,11 @@ fn make_data(
     };
     data.insert("default_theme".to_owned(), json!(default_theme));
 
+    data.insert(
+        "default_sidebar".to_owned(),
+        json!(html_config.default_sidebar),
+    );
+
     let preferred_dark_theme = match html_config.preferred_dark_theme {
         Some(ref theme) => theme.to_lowercase(),
         None => "navy".to_string(),
@@ -633,49 +965,90 @@ fn make_data(
         data.insert("google_analytics".to_owned(), json!(ga));
     }
 
-    if html_config.mathjax_support {
-        data.insert("mathjax_support".to_owned(), json!(true));
+    match html_config.resolved_math_engine() {
+        MathEngine::MathJax => {
+            data.insert("mathjax_support".to_owned(), json!(true));
+        }
+        MathEngine::KaTeX => {
+            data.insert("katex_support".to_owned(), json!(true));
+        }
+        MathEngine::None => {}
     }
 
-    if html_config.copy_fonts {
+    // Custom fonts are declared in fonts.css, so it's linked even if the
+    // builtin fonts aren't copied.
+    if html_config.copy_fonts || !html_config.custom_fonts.is_empty() {
         data.insert("copy_fonts".to_owned(), json!(true));
     }
 
     // Add check to see if there is an additional style
     if !html_config.additional_css.is_empty() {
         let mut css = Vec::new();
+        let mut external_css = Vec::new();
         for style in &html_config.additional_css {
+            if utils::is_external_url(style) {
+                external_css.push(style.to_str().expect("Could not convert to str"));
+                continue;
+            }
             match style.strip_prefix(root) {
                 Ok(p) => css.push(p.to_str().expect("Could not convert to str")),
                 Err(_) => css.push(style.to_str().expect("Could not convert to str")),
             }
         }
         data.insert("additional_css".to_owned(), json!(css));
+        data.insert("external_css".to_owned(), json!(external_css));
     }
 
     // Add check to see if there is an additional script
     if !html_config.additional_js.is_empty() {
         let mut js = Vec::new();
+        let mut external_js = Vec::new();
         for script in &html_config.additional_js {
+            if utils::is_external_url(script) {
+                external_js.push(script.to_str().expect("Could not convert to str"));
+                continue;
+            }
             match script.strip_prefix(root) {
                 Ok(p) => js.push(p.to_str().expect("Could not convert to str")),
                 Err(_) => js.push(script.to_str().expect("Could not convert to str")),
             }
         }
         data.insert("additional_js".to_owned(), json!(js));
+        data.insert("external_js".to_owned(), json!(external_js));
+    }
+
+    if let Some((file_name, _)) = js_bundle(theme, html_config) {
+        data.insert("bundle_js".to_owned(), json!(file_name));
     }
 
     if html_config.playground.editable && html_config.playground.copy_js {
-        data.insert("playground_js".to_owned(), json!(true));
+        if !html_config.bundle_js {
+            data.insert("playground_js".to_owned(), json!(true));
+        }
         if html_config.playground.line_numbers {
             data.insert("playground_line_numbers".to_owned(), json!(true));
         }
     }
-    if html_config.playground.copyable {
-        data.insert("playground_copyable".to_owned(), json!(true));
+    if html_config.copy_buttons {
+        data.insert("copy_buttons".to_owned(), json!(true));
+        if html_config.playground.copyable {
+            data.insert("playground_copyable".to_owned(), json!(true));
+        }
     }
+    data.insert(
+        "playground_run_timeout".to_owned(),
+        json!(html_config.playground.run_timeout_secs),
+    );
+    data.insert(
+        "playground_run_retries".to_owned(),
+        json!(html_config.playground.run_retries),
+    );
 
     data.insert("print_enable".to_owned(), json!(html_config.print.enable));
+    data.insert(
+        "print_output_name".to_owned(),
+        json!(html_config.print.output_name),
+    );
     data.insert("fold_enable".to_owned(), json!(html_config.fold.enable));
     data.insert("fold_level".to_owned(), json!(html_config.fold.level));
 
@@ -732,6 +1105,9 @@ fn make_data(
                         .with_context(|| "Could not convert path to str")?;
                     chapter.insert("path".to_owned(), json!(p));
                 }
+                if let Some(ref url) = ch.external_url {
+                    chapter.insert("external_url".to_owned(), json!(url));
+                }
             }
             BookItem::Separator => {
                 chapter.insert("spacer".to_owned(), json!("_spacer_"));
@@ -741,6 +1117,11 @@ fn make_data(
         chapters.push(chapter);
     }
 
+    if let Some(ref reading_order) = config.build.reading_order {
+        let reading_order = load_reading_order(&root.join(reading_order), &chapters)?;
+        data.insert("reading_order".to_owned(), json!(reading_order));
+    }
+
     data.insert("chapters".to_owned(), json!(chapters));
 
     debug!("[*]: JSON constructed");
@@ -749,7 +1130,7 @@ fn make_data(
 
 /// Goes through the rendered HTML, making sure all header tags have
 /// an anchor respectively so people can link to sections directly.
-fn build_header_links(html: &str) -> String {
+fn build_header_links(html: &str, anchor: &HeadingAnchor, style: utils::AnchorStyle) -> String {
     let regex = Regex::new(r"<h(\d)>(.*?)</h\d>").unwrap();
     let mut id_counter = HashMap::new();
 
@@ -759,7 +1140,7 @@ fn build_header_links(html: &str) -> String {
                 .parse()
                 .expect("Regex should ensure we only ever get numbers here");
 
-            insert_link_into_header(level, &caps[2], &mut id_counter)
+            insert_link_into_header(level, &caps[2], anchor, style, &mut id_counter)
         })
         .into_owned()
 }
@@ -769,9 +1150,11 @@ fn build_header_links(html: &str) -> String {
 fn insert_link_into_header(
     level: usize,
     content: &str,
+    anchor: &HeadingAnchor,
+    style: utils::AnchorStyle,
     id_counter: &mut HashMap<String, usize>,
 ) -> String {
-    let raw_id = utils::id_from_content(content);
+    let raw_id = utils::id_from_content_with_style(content, style);
 
     let id_count = id_counter.entry(raw_id.clone()).or_insert(0);
 
@@ -782,11 +1165,21 @@ fn insert_link_into_header(
 
     *id_count += 1;
 
+    let anchor = match anchor {
+        HeadingAnchor::None => String::new(),
+        HeadingAnchor::Symbol(symbol) => format!(
+            r##"<a class="header-anchor" href="#{}" aria-hidden="true">{}</a>"##,
+            id,
+            handlebars::html_escape(symbol)
+        ),
+    };
+
     format!(
-        r##"<h{level} id="{id}"><a class="header" href="#{id}">{text}</a></h{level}>"##,
+        r##"<h{level} id="{id}"><a class="header" href="#{id}">{text}</a>{anchor}</h{level}>"##,
         level = level,
         id = id,
-        text = content
+        text = content,
+        anchor = anchor
     )
 }
 
@@ -885,34 +1278,486 @@ fn add_playground_pre(
         .into_owned()
 }
 
-lazy_static! {
-    static ref BORING_LINES_REGEX: Regex = Regex::new(r"^(\s*)#(.?)(.*)$").unwrap();
+/// Give a page a `<base>` tag pointing at the root of the book, making every
+/// link on it relative to the root instead of `path`'s directory.
+fn insert_base_href(data: &mut serde_json::Map<String, serde_json::Value>, path: &Path) {
+    let path_to_root = utils::fs::path_to_root(path);
+    let base_url = if path_to_root.is_empty() {
+        String::from("./")
+    } else {
+        path_to_root
+    };
+    data.insert("base_url".to_owned(), json!(base_url));
+    data.insert("path_to_root".to_owned(), json!(""));
+}
+
+/// Make links to a fragment of the current page (e.g. heading anchors and
+/// footnotes) include the page, so they aren't resolved against the `<base>`
+/// tag's URL.
+fn fragment_links_to_page(html: &str, page: &Path) -> String {
+    let page = utils::fs::normalize_path(&page.to_string_lossy());
+    html.replace("href=\"#", &format!("href=\"{}#", page))
+}
+
+/// Mark every Rust code block in `html` as `noplayground`, and take away any
+/// classes asking for it to be runnable or editable, for chapters whose front
+/// matter turns the playground off.
+fn disable_playground(html: &str) -> String {
+    lazy_static! {
+        static ref CODE_CLASS: Regex = Regex::new(r#"<code class="([^"]*)""#).unwrap();
+    }
+    CODE_CLASS
+        .replace_all(html, |caps: &Captures<'_>| {
+            // pulldown-cmark separates a fenced block's attributes with
+            // commas, which `fix_code_blocks` only turns into spaces later.
+            let classes: Vec<&str> = caps[1]
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|class| !class.is_empty())
+                .collect();
+            if !classes.contains(&"language-rust") {
+                return caps[0].to_string();
+            }
+            let mut classes: Vec<&str> = classes
+                .into_iter()
+                .filter(|class| *class != "editable" && *class != "mdbook-runnable")
+                .collect();
+            classes.push("noplayground");
+            format!("<code class=\"{}\"", classes.join(" "))
+        })
+        .into_owned()
+}
+
+/// Put the chapters in the order given by the `build.reading-order` file, one
+/// chapter path per line. Chapters which aren't listed follow in `SUMMARY.md`
+/// order.
+fn load_reading_order(
+    file: &Path,
+    chapters: &[BTreeMap<String, serde_json::Value>],
+) -> Result<Vec<BTreeMap<String, serde_json::Value>>> {
+    let contents = fs::read_to_string(file)
+        .with_context(|| format!("Unable to read the reading order, {}", file.display()))?;
+
+    let mut remaining: Vec<_> = chapters
+        .iter()
+        .filter(|chapter| chapter.contains_key("path"))
+        .collect();
+    let mut ordered = Vec::with_capacity(remaining.len());
+
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let wanted = Path::new(line);
+        let position = remaining
+            .iter()
+            .position(|chapter| chapter["path"].as_str().map(Path::new) == Some(wanted));
+        match position {
+            Some(position) => ordered.push(remaining.remove(position).clone()),
+            None => diagnostics::at(file, Some(index + 1), || {
+                warn!(
+                    "{} lists \"{}\", which isn't a chapter (or is listed twice)",
+                    file.display(),
+                    line
+                )
+            }),
+        }
+    }
+
+    ordered.extend(remaining.into_iter().cloned());
+    Ok(ordered)
+}
+
+/// The name of the handlebars template a chapter is rendered with.
+fn chapter_template(ch: &Chapter) -> String {
+    match ch.meta.template {
+        Some(ref name) => format!("chapter-template/{}", name),
+        None => String::from("index"),
+    }
+}
+
+/// Register the `<name>.hbs` file from the theme directory for each chapter
+/// that asks to be rendered with a custom `template`.
+fn register_chapter_templates(
+    handlebars: &mut Handlebars<'_>,
+    book: &Book,
+    theme_dir: &Path,
+) -> Result<()> {
+    for item in book.iter() {
+        let (ch, name) = match item {
+            BookItem::Chapter(ch) => match ch.meta.template {
+                Some(ref name) => (ch, name),
+                None => continue,
+            },
+            _ => continue,
+        };
+
+        let template = chapter_template(ch);
+        if handlebars.get_template(&template).is_some() {
+            continue;
+        }
+
+        let path = theme_dir.join(format!("{}.hbs", name));
+        let contents = fs::read_to_string(&path).with_context(|| {
+            format!(
+                "Unable to load the \"{}\" template used by chapter \"{}\" ({})",
+                name,
+                ch.name,
+                path.display()
+            )
+        })?;
+        handlebars
+            .register_template_string(&template, contents)
+            .with_context(|| format!("Invalid template {}", path.display()))?;
+    }
+
+    Ok(())
+}
+
+/// Concatenate the builtin scripts, in the order the page would otherwise load
+/// them, returning the bundle's (content hashed) file name and contents.
+///
+/// Returns `None` unless `output.html.bundle-js` is enabled.
+fn js_bundle(theme: &Theme, html_config: &HtmlConfig) -> Option<(String, Vec<u8>)> {
+    if !html_config.bundle_js {
+        return None;
+    }
+
+    let mut scripts: Vec<&[u8]> = Vec::new();
+    if html_config.playground.editable && html_config.playground.copy_js {
+        scripts.extend(&[
+            playground_editor::ACE_JS,
+            playground_editor::JS,
+            playground_editor::MODE_RUST_JS,
+            playground_editor::THEME_DAWN_JS,
+            playground_editor::THEME_TOMORROW_NIGHT_JS,
+        ]);
+    }
+    if html_config.copy_buttons {
+        scripts.push(theme.clipboard_js.as_slice());
+    }
+    scripts.extend(&[theme.highlight_js.as_slice(), theme.js.as_slice()]);
+
+    let mut contents = Vec::new();
+    for script in scripts {
+        contents.extend_from_slice(script);
+        // Guard against automatic semicolon insertion joining two scripts
+        contents.extend_from_slice(b"\n;\n");
+    }
+
+    let file_name = format!("bundle-{}.js", content_hash(&contents));
+
+    Some((file_name, contents))
+}
+
+/// A short digest of `contents` for cache-busting file names. It only changes
+/// when the contents do, whichever mdBook or Rust version does the build.
+fn content_hash(contents: &[u8]) -> String {
+    let digest = format!("{:x}", Sha256::digest(contents));
+    digest[..16].to_string()
+}
+
+/// Put `base_path` in front of every `href` and `src` which is an absolute
+/// path, e.g. `/foo` becomes `/docs/foo` for a `base_path` of `/docs/`.
+/// Links to other sites, including protocol-relative ones like
+/// `//example.com`, are left alone.
+fn prefix_absolute_links(html: &str, base_path: &str) -> String {
+    lazy_static! {
+        static ref ABSOLUTE_LINK: Regex = Regex::new(r#"(\s(?:href|src)=")/([^/])"#).unwrap();
+    }
+    let base_path = base_path.trim_end_matches('/');
+
+    ABSOLUTE_LINK
+        .replace_all(html, |caps: &Captures<'_>| {
+            format!("{}{}/{}", &caps[1], base_path, &caps[2])
+        })
+        .into_owned()
+}
+
+/// The classes of the element wrapping a chapter's content, so themes can
+/// style particular chapters, e.g. `chapter chapter-first-nested depth-1`,
+/// followed by any classes from the chapter's front matter.
+fn chapter_classes(ch: &Chapter, path: &Path) -> String {
+    let mut classes = vec![
+        String::from("chapter"),
+        format!("chapter-{}", chapter_slug(path)),
+        format!("depth-{}", ch.parent_names.len()),
+    ];
+    classes.extend(
+        ch.meta
+            .classes
+            .iter()
+            .map(|class| handlebars::html_escape(class)),
+    );
+    classes.join(" ")
+}
+
+/// A chapter's path as an identifier, e.g. `first-nested` for
+/// `first/nested.md`. This is also the prefix of the `id`s of the chapter's
+/// code lines.
+fn chapter_slug(path: &Path) -> String {
+    let path = path.with_extension("");
+    let slug = path
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("-");
+    utils::normalize_id(&slug)
+}
+
+/// Wrap every line of each code block in a `<span>` with an `id` of the form
+/// `{prefix}-{block}-L{line}`, starting with a gutter link to that line.
+///
+/// The gutter shows the line number using CSS, so it isn't part of the code
+/// that gets copied or sent to the playground. Lines hidden with `#` keep the
+/// `boring` class so they can still be toggled.
+fn add_code_line_anchors(html: &str, prefix: &str) -> String {
+    lazy_static! {
+        static ref CODE_BLOCK: Regex =
+            Regex::new(r##"(?s)(<pre[^>]*><code[^>]*>)(.*?)(</code></pre>)"##).unwrap();
+    }
+    const BORING_START: &str = "<span class=\"boring\">";
+    const BORING_END: &str = "</span>";
+
+    let mut block = 0;
+    CODE_BLOCK
+        .replace_all(html, |caps: &Captures<'_>| {
+            block += 1;
+
+            // `hide_lines()` puts the newline inside the `boring` span, move
+            // it out so every line ends with its own newline.
+            let code = caps[2].replace("\n</span>", "</span>\n");
+
+            let mut result = String::with_capacity(code.len() * 2);
+            result.push_str(&caps[1]);
+            for (i, line) in code.lines().enumerate() {
+                let (class, line) = if line.starts_with(BORING_START) && line.ends_with(BORING_END)
+                {
+                    (
+                        "code-line boring",
+                        &line[BORING_START.len()..line.len() - BORING_END.len()],
+                    )
+                } else {
+                    ("code-line", line)
+                };
+                let id = format!("{}-{}-L{}", prefix, block, i + 1);
+                write!(
+                    result,
+                    "<span class=\"{}\" id=\"{}\"><a class=\"code-line-anchor\" href=\"#{}\" \
+                     data-line=\"{}\" aria-hidden=\"true\"></a>{}\n</span>",
+                    class,
+                    id,
+                    id,
+                    i + 1,
+                    line
+                )
+                .unwrap();
+            }
+            result.push_str(&caps[3]);
+            result
+        })
+        .into_owned()
+}
+
+/// A JSON array with an entry for every chapter, holding the requested fields.
+fn raw_search_index(
+    book: &Book,
+    fields: &[SearchIndexField],
+    index_file_names: &[String],
+) -> serde_json::Value {
+    let entries = book
+        .iter()
+        .filter_map(|item| match item {
+            BookItem::Chapter(ch) => Some((ch, output_path_for(ch, index_file_names)?)),
+            _ => None,
+        })
+        .map(|(ch, path)| {
+            let mut entry = serde_json::Map::new();
+            for field in fields {
+                let (key, value) = match field {
+                    SearchIndexField::Title => ("title", json!(ch.name)),
+                    SearchIndexField::Path => {
+                        let page = utils::fs::normalize_path(&path.to_string_lossy());
+                        ("path", json!(page))
+                    }
+                    SearchIndexField::Section => (
+                        "section",
+                        json!(ch.number.as_ref().map(ToString::to_string)),
+                    ),
+                    SearchIndexField::Body => ("body", json!(ch.plain_text())),
+                };
+                entry.insert(key.to_owned(), value);
+            }
+            serde_json::Value::Object(entry)
+        })
+        .collect();
+
+    serde_json::Value::Array(entries)
+}
+
+/// Replace the theme's favicons with the ones from `output.html.favicon-png`
+/// and `output.html.favicon-svg`.
+///
+/// Like a theme which only overrides one favicon, setting just one of them
+/// drops the builtin favicon of the other format.
+fn load_custom_favicons(theme: &mut Theme, root: &Path, html_config: &HtmlConfig) -> Result<()> {
+    let load = |path: &Path| {
+        let path = root.join(path);
+        fs::read(&path).with_context(|| format!("Unable to read the favicon {}", path.display()))
+    };
+
+    let png = html_config.favicon_png.as_deref().map(load).transpose()?;
+    let svg = html_config.favicon_svg.as_deref().map(load).transpose()?;
+
+    if png.is_some() && theme.favicon_svg.as_deref() == Some(theme::FAVICON_SVG) {
+        theme.favicon_svg = None;
+    }
+    if svg.is_some() && theme.favicon_png.as_deref() == Some(theme::FAVICON_PNG) {
+        theme.favicon_png = None;
+    }
+    if png.is_some() {
+        theme.favicon_png = png;
+    }
+    if svg.is_some() {
+        theme.favicon_svg = svg;
+    }
+    Ok(())
+}
+
+/// The files for `output.html.custom-fonts`, as (content hashed) file names
+/// and contents, and the `@font-face` rules which refer to them from
+/// `fonts/fonts.css`.
+struct CustomFonts {
+    css: String,
+    files: Vec<(String, Vec<u8>)>,
+}
+
+/// Read the fonts from `output.html.custom-fonts`.
+fn load_custom_fonts(root: &Path, html_config: &HtmlConfig) -> Result<CustomFonts> {
+    let mut css = String::new();
+    let mut files = Vec::new();
+
+    for font in &html_config.custom_fonts {
+        let path = root.join(&font.path);
+        let contents = fs::read(&path)
+            .with_context(|| format!("Unable to read the font {}", path.display()))?;
+
+        let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("font");
+        let extension = path.extension().and_then(|s| s.to_str()).unwrap_or("");
+        let file_name = format!("{}-{}.{}", stem, content_hash(&contents), extension);
+
+        let format = match extension.to_lowercase().as_str() {
+            "woff2" => " format('woff2')",
+            "woff" => " format('woff')",
+            "ttf" => " format('truetype')",
+            "otf" => " format('opentype')",
+            _ => "",
+        };
+        write!(
+            css,
+            "\n@font-face {{\n  font-family: '{}';\n  font-style: {};\n  font-weight: {};\n  \
+             src: url('{}'){};\n}}\n",
+            font.family.replace('\'', "\\'"),
+            font.style.as_deref().unwrap_or("normal"),
+            font.weight.unwrap_or(400),
+            file_name,
+            format
+        )
+        .unwrap();
+
+        files.push((format!("fonts/{}", file_name), contents));
+    }
+
+    Ok(CustomFonts { css, files })
+}
+
+/// Add the `og:*` and `twitter:*` link preview metadata for a chapter.
+fn insert_open_graph_data(
+    data: &mut serde_json::Map<String, serde_json::Value>,
+    ch: &Chapter,
+    filepath: &Path,
+    html_config: &HtmlConfig,
+    book_config: &BookConfig,
+) {
+    data.insert("og_title".to_owned(), json!(ch.display_name()));
+
+    let description = ch
+        .meta
+        .description
+        .clone()
+        .or_else(|| first_paragraph(&ch.content))
+        .or_else(|| book_config.description.clone());
+    if let Some(description) = description {
+        data.insert("og_description".to_owned(), json!(description));
+    }
+
+    let site_url = html_config.site_url.as_deref();
+    if let Some(site_url) = site_url {
+        let page = utils::fs::normalize_path(&filepath.to_string_lossy());
+        data.insert("og_url".to_owned(), json!(join_url(site_url, &page)));
+    }
+
+    let twitter_card = match &html_config.og_image {
+        Some(image) => {
+            let image = match site_url {
+                Some(site_url) if !image.contains("://") => join_url(site_url, image),
+                _ => image.clone(),
+            };
+            data.insert("og_image".to_owned(), json!(image));
+            "summary_large_image"
+        }
+        None => "summary",
+    };
+    data.insert("twitter_card".to_owned(), json!(twitter_card));
+}
+
+/// The plain text of the first paragraph in a chapter, used as its description
+/// when it doesn't give one itself.
+fn first_paragraph(content: &str) -> Option<String> {
+    let mut events = utils::new_cmark_parser(content)
+        .skip_while(|event| !matches!(event, Event::Start(Tag::Paragraph)));
+    events.next()?;
+
+    let mut text = String::new();
+    for event in events {
+        match event {
+            Event::End(Tag::Paragraph) => break,
+            Event::Text(t) | Event::Code(t) => text.push_str(&t),
+            Event::SoftBreak | Event::HardBreak => text.push(' '),
+            _ => {}
+        }
+    }
+
+    let text = utils::collapse_whitespace(text.trim()).into_owned();
+    if text.is_empty() {
+        None
+    } else {
+        Some(text)
+    }
+}
+
+fn join_url(base: &str, path: &str) -> String {
+    format!(
+        "{}/{}",
+        base.trim_end_matches('/'),
+        path.trim_start_matches("./").trim_start_matches('/')
+    )
 }
 
 fn hide_lines(content: &str) -> String {
     let mut result = String::with_capacity(content.len());
     for line in content.lines() {
-        if let Some(caps) = BORING_LINES_REGEX.captures(line) {
-            if &caps[2] == "#" {
-                result += &caps[1];
-                result += &caps[2];
-                result += &caps[3];
-                result += "\n";
-                continue;
-            } else if &caps[2] != "!" && &caps[2] != "[" {
-                result += "<span class=\"boring\">";
-                result += &caps[1];
-                if &caps[2] != " " {
-                    result += &caps[2];
-                }
-                result += &caps[3];
-                result += "\n";
-                result += "</span>";
-                continue;
-            }
+        let (hidden, text) = utils::parse_hidden_line(line);
+        if hidden {
+            result += "<span class=\"boring\">";
+            result += &text;
+            result += "\n";
+            result += "</span>";
+        } else {
+            result += &text;
+            result += "\n";
         }
-        result += line;
-        result += "\n";
     }
     result
 }
@@ -947,11 +1792,16 @@ struct RenderItemContext<'a> {
     html_config: HtmlConfig,
     edition: Option<RustEdition>,
     chapter_titles: &'a HashMap<PathBuf, String>,
+    changed_chapters: Option<&'a [PathBuf]>,
+    markdown_options: Options,
+    /// See `Config::index_file_names`.
+    index_file_names: &'a [String],
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::str::FromStr;
 
     #[test]
     fn original_build_header_links() {
@@ -983,11 +1833,133 @@ mod tests {
         ];
 
         for (src, should_be) in inputs {
-            let got = build_header_links(&src);
+            let got = build_header_links(&src, &HeadingAnchor::None, Default::default());
             assert_eq!(got, should_be);
         }
     }
 
+    #[test]
+    fn absolute_links_get_the_base_path() {
+        let html = r##"<p><a href="/guide/intro.html">x</a> <img src="/img/logo.png"> <a href="/">home</a>
+<a href="https://example.com/a">y</a> <a href="//cdn.example.com/b">z</a> <a href="#top">t</a> <a href="rel.html">r</a></p>"##;
+
+        let got = prefix_absolute_links(html, "/docs/");
+
+        assert_eq!(
+            got,
+            r##"<p><a href="/docs/guide/intro.html">x</a> <img src="/docs/img/logo.png"> <a href="/docs/">home</a>
+<a href="https://example.com/a">y</a> <a href="//cdn.example.com/b">z</a> <a href="#top">t</a> <a href="rel.html">r</a></p>"##
+        );
+        assert_eq!(prefix_absolute_links(html, "/docs"), got);
+    }
+
+    #[test]
+    fn header_links_with_an_anchor_symbol() {
+        let got = build_header_links(
+            "<h2>Foo</h2>",
+            &HeadingAnchor::Symbol(String::from("§")),
+            Default::default(),
+        );
+        assert_eq!(
+            got,
+            r##"<h2 id="foo"><a class="header" href="#foo">Foo</a><a class="header-anchor" href="#foo" aria-hidden="true">§</a></h2>"##
+        );
+    }
+
+    #[test]
+    fn playground_run_settings_are_passed_to_the_template() {
+        let src = r#"
+        [output.html.playground]
+        run-timeout-secs = 20
+        run-retries = 2
+        "#;
+        let config = Config::from_str(src).unwrap();
+        let html_config = config.html_config().unwrap();
+        assert_eq!(html_config.playground.run_timeout_secs, 20);
+        assert_eq!(html_config.playground.run_retries, 2);
+
+        let data = make_data(
+            Path::new(""),
+            &Book::new(),
+            &config,
+            &html_config,
+            &Theme::default(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(data["playground_run_timeout"], json!(20));
+        assert_eq!(data["playground_run_retries"], json!(2));
+    }
+
+    #[test]
+    fn footer_markdown_is_rendered() {
+        let src = r#"
+        [output.html]
+        footer = "© 2021 Someone, licensed under [CC-BY](https://example.com/cc-by)"
+        "#;
+        let config = Config::from_str(src).unwrap();
+        let html_config = config.html_config().unwrap();
+        let data = make_data(
+            Path::new(""),
+            &Book::new(),
+            &config,
+            &html_config,
+            &Theme::default(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            data["footer"],
+            json!("<p>© 2021 Someone, licensed under <a href=\"https://example.com/cc-by\">CC-BY</a></p>\n")
+        );
+
+        let config = Config::default();
+        let html_config = config.html_config().unwrap_or_default();
+        let data = make_data(
+            Path::new(""),
+            &Book::new(),
+            &config,
+            &html_config,
+            &Theme::default(),
+            None,
+        )
+        .unwrap();
+        assert!(!data.contains_key("footer"));
+    }
+
+    #[test]
+    fn head_include_is_passed_to_the_template() {
+        let fragment = r#"<link rel="preload" href="font.woff2" as="font">"#;
+        let config = Config::default();
+        let html_config = config.html_config().unwrap_or_default();
+        let data = make_data(
+            Path::new(""),
+            &Book::new(),
+            &config,
+            &html_config,
+            &Theme::default(),
+            Some(fragment),
+        )
+        .unwrap();
+        assert_eq!(data["head_include"], json!(fragment));
+    }
+
+    #[test]
+    fn disabling_the_playground_only_touches_rust_blocks() {
+        let html = "<pre><code class=\"language-rust,editable,mdbook-runnable\">fn main() {}\n</code></pre>\n\
+                    <pre><code class=\"language-toml\">[book]\n</code></pre>\n";
+
+        let got = disable_playground(html);
+
+        assert_eq!(
+            got,
+            "<pre><code class=\"language-rust noplayground\">fn main() {}\n</code></pre>\n\
+             <pre><code class=\"language-toml\">[book]\n</code></pre>\n"
+        );
+        let processed = add_playground_pre(&got, &Playground::default(), None);
+        assert!(!processed.contains("class=\"playground\""));
+    }
+
     #[test]
     fn add_playground() {
         let inputs = [
@@ -1090,4 +2062,32 @@ mod tests {
             assert_eq!(&*got, *should_be);
         }
     }
+
+    #[test]
+    fn code_line_anchors() {
+        let html = "<p>x</p><pre class=\"playground\"><code class=\"language-rust\">\
+                    <span class=\"boring\">fn main() {\n</span>x();\ny();\n\
+                    <span class=\"boring\">}\n</span></code></pre>\
+                    <pre><code>a\n</code></pre>";
+        let got = add_code_line_anchors(html, "first-nested");
+
+        let should_be = "<p>x</p><pre class=\"playground\"><code class=\"language-rust\">\
+            <span class=\"code-line boring\" id=\"first-nested-1-L1\"><a class=\"code-line-anchor\" href=\"#first-nested-1-L1\" data-line=\"1\" aria-hidden=\"true\"></a>fn main() {\n</span>\
+            <span class=\"code-line\" id=\"first-nested-1-L2\"><a class=\"code-line-anchor\" href=\"#first-nested-1-L2\" data-line=\"2\" aria-hidden=\"true\"></a>x();\n</span>\
+            <span class=\"code-line\" id=\"first-nested-1-L3\"><a class=\"code-line-anchor\" href=\"#first-nested-1-L3\" data-line=\"3\" aria-hidden=\"true\"></a>y();\n</span>\
+            <span class=\"code-line boring\" id=\"first-nested-1-L4\"><a class=\"code-line-anchor\" href=\"#first-nested-1-L4\" data-line=\"4\" aria-hidden=\"true\"></a>}\n</span>\
+            </code></pre><pre><code>\
+            <span class=\"code-line\" id=\"first-nested-2-L1\"><a class=\"code-line-anchor\" href=\"#first-nested-2-L1\" data-line=\"1\" aria-hidden=\"true\"></a>a\n</span>\
+            </code></pre>";
+        assert_eq!(got, should_be);
+    }
+
+    #[test]
+    fn chapter_slugs() {
+        assert_eq!(chapter_slug(Path::new("intro.md")), "intro");
+        assert_eq!(
+            chapter_slug(Path::new("first/Nested Chapter.md")),
+            "first-nested-chapter"
+        );
+    }
 }
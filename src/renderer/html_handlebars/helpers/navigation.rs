@@ -1,13 +1,17 @@
 use std::collections::BTreeMap;
 use std::path::Path;
 
-use handlebars::{Context, Handlebars, Helper, Output, RenderContext, RenderError, Renderable};
+use handlebars::{
+    Context, Handlebars, Helper, HelperDef, Output, RenderContext, RenderError, Renderable,
+};
 
+use crate::book::output_path_for_source;
 use crate::utils;
 
 type StringMap = BTreeMap<String, String>;
 
 /// Target for `find_chapter`.
+#[derive(Clone, Copy)]
 enum Target {
     Previous,
     Next,
@@ -51,12 +55,23 @@ fn find_chapter(
 ) -> Result<Option<StringMap>, RenderError> {
     debug!("Get data from context");
 
-    let chapters = rc.evaluate(ctx, "@root/chapters").and_then(|c| {
+    let decode = |c: handlebars::ScopedJson<'_, '_>| {
         serde_json::value::from_value::<Vec<StringMap>>(c.as_json().clone())
             .map_err(|_| RenderError::new("Could not decode the JSON data"))
-    })?;
+    };
 
-    let base_path = rc
+    let summary_chapters = rc.evaluate(ctx, "@root/chapters").and_then(decode)?;
+
+    // The reading order, if the book has one, is used instead of the order of
+    // the chapters in the table of contents.
+    let reading_order = rc.evaluate(ctx, "@root/reading_order")?;
+    let chapters = if reading_order.is_missing() {
+        summary_chapters.clone()
+    } else {
+        decode(reading_order)?
+    };
+
+    let mut base_path = rc
         .evaluate(ctx, "@root/path")?
         .as_json()
         .as_str()
@@ -66,20 +81,20 @@ fn find_chapter(
     if !rc.evaluate(ctx, "@root/is_index")?.is_missing() {
         // Special case for index.md which may be a synthetic page.
         // Target::find won't match because there is no page with the path
-        // "index.md" (unless there really is an index.md in SUMMARY.md).
+        // "index.md" (unless there really is an index.md in SUMMARY.md), so
+        // look for the first chapter instead.
         match target {
             Target::Previous => return Ok(None),
-            Target::Next => match chapters
-                .iter()
-                .filter(|chapter| {
+            Target::Next => {
+                match summary_chapters
+                    .iter()
                     // Skip things like "spacer"
-                    chapter.contains_key("path")
-                })
-                .nth(1)
-            {
-                Some(chapter) => return Ok(Some(chapter.clone())),
-                None => return Ok(None),
-            },
+                    .find_map(|chapter| chapter.get("path"))
+                {
+                    Some(path) => base_path = path.clone(),
+                    None => return Ok(None),
+                }
+            }
         }
     }
 
@@ -112,6 +127,7 @@ fn render(
     rc: &mut RenderContext<'_, '_>,
     out: &mut dyn Output,
     chapter: &StringMap,
+    index_file_names: &[String],
 ) -> Result<(), RenderError> {
     trace!("Creating BTreeMap to inject in context");
 
@@ -123,10 +139,12 @@ fn render(
         .ok_or_else(|| RenderError::new("Type error for `path`, string expected"))?
         .replace("\"", "");
 
-    context.insert(
-        "path_to_root".to_owned(),
-        json!(utils::fs::path_to_root(&base_path)),
-    );
+    // Pages using a `<base>` tag set this to "" rather than the real path.
+    let path_to_root = match rc.evaluate(ctx, "@root/path_to_root")?.as_json().as_str() {
+        Some(path_to_root) => path_to_root.to_owned(),
+        None => utils::fs::path_to_root(&base_path),
+    };
+    context.insert("path_to_root".to_owned(), json!(path_to_root));
 
     chapter
         .get("name")
@@ -137,11 +155,13 @@ fn render(
         .get("path")
         .ok_or_else(|| RenderError::new("No path found for chapter in JSON data"))
         .and_then(|p| {
-            Path::new(p)
-                .with_extension("html")
+            output_path_for_source(Path::new(p), index_file_names)
                 .to_str()
                 .ok_or_else(|| RenderError::new("Link could not be converted to str"))
-                .map(|p| context.insert("link".to_owned(), json!(p.replace("\\", "/"))))
+                .map(|p| {
+                    let link = utils::percent_encode_url(&p.replace("\\", "/"));
+                    context.insert("link".to_owned(), json!(link))
+                })
         })?;
 
     trace!("Render template");
@@ -157,36 +177,49 @@ fn render(
     Ok(())
 }
 
-pub fn previous(
-    _h: &Helper<'_, '_>,
-    r: &Handlebars<'_>,
-    ctx: &Context,
-    rc: &mut RenderContext<'_, '_>,
-    out: &mut dyn Output,
-) -> Result<(), RenderError> {
-    trace!("previous (handlebars helper)");
+/// Handlebars helper for the link to the previous or next chapter.
+#[derive(Clone)]
+pub struct RenderNavigation {
+    target: Target,
+    /// See `Config::index_file_names`.
+    index_file_names: Vec<String>,
+}
 
-    if let Some(previous) = find_chapter(ctx, rc, Target::Previous)? {
-        render(_h, r, ctx, rc, out, &previous)?;
+impl RenderNavigation {
+    /// The `previous` helper.
+    pub fn previous(index_file_names: Vec<String>) -> RenderNavigation {
+        RenderNavigation {
+            target: Target::Previous,
+            index_file_names,
+        }
     }
 
-    Ok(())
+    /// The `next` helper.
+    pub fn next(index_file_names: Vec<String>) -> RenderNavigation {
+        RenderNavigation {
+            target: Target::Next,
+            index_file_names,
+        }
+    }
 }
 
-pub fn next(
-    _h: &Helper<'_, '_>,
-    r: &Handlebars<'_>,
-    ctx: &Context,
-    rc: &mut RenderContext<'_, '_>,
-    out: &mut dyn Output,
-) -> Result<(), RenderError> {
-    trace!("next (handlebars helper)");
+impl HelperDef for RenderNavigation {
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'reg, 'rc>,
+        r: &'reg Handlebars<'_>,
+        ctx: &'rc Context,
+        rc: &mut RenderContext<'reg, 'rc>,
+        out: &mut dyn Output,
+    ) -> Result<(), RenderError> {
+        trace!("previous/next (handlebars helper)");
+
+        if let Some(chapter) = find_chapter(ctx, rc, self.target)? {
+            render(h, r, ctx, rc, out, &chapter, &self.index_file_names)?;
+        }
 
-    if let Some(next) = find_chapter(ctx, rc, Target::Next)? {
-        render(_h, r, ctx, rc, out, &next)?;
+        Ok(())
     }
-
-    Ok(())
 }
 
 #[cfg(test)]
@@ -218,8 +251,8 @@ mod tests {
         });
 
         let mut h = Handlebars::new();
-        h.register_helper("previous", Box::new(previous));
-        h.register_helper("next", Box::new(next));
+        h.register_helper("previous", Box::new(RenderNavigation::previous(Vec::new())));
+        h.register_helper("next", Box::new(RenderNavigation::next(Vec::new())));
 
         assert_eq!(
             h.render_template(TEMPLATE, &data).unwrap(),
@@ -249,8 +282,8 @@ mod tests {
         });
 
         let mut h = Handlebars::new();
-        h.register_helper("previous", Box::new(previous));
-        h.register_helper("next", Box::new(next));
+        h.register_helper("previous", Box::new(RenderNavigation::previous(Vec::new())));
+        h.register_helper("next", Box::new(RenderNavigation::next(Vec::new())));
 
         assert_eq!(
             h.render_template(TEMPLATE, &data).unwrap(),
@@ -279,12 +312,57 @@ mod tests {
         });
 
         let mut h = Handlebars::new();
-        h.register_helper("previous", Box::new(previous));
-        h.register_helper("next", Box::new(next));
+        h.register_helper("previous", Box::new(RenderNavigation::previous(Vec::new())));
+        h.register_helper("next", Box::new(RenderNavigation::next(Vec::new())));
 
         assert_eq!(
             h.render_template(TEMPLATE, &data).unwrap(),
             "two: two.html|"
         );
     }
+
+    #[test]
+    fn test_reading_order() {
+        let data = json!({
+           "name": "two",
+           "path": "two.path",
+           "chapters": [
+              {
+                 "name": "one",
+                 "path": "one.path"
+              },
+              {
+                 "name": "two",
+                 "path": "two.path",
+              },
+              {
+                 "name": "three",
+                 "path": "three.path"
+              }
+           ],
+           "reading_order": [
+              {
+                 "name": "three",
+                 "path": "three.path"
+              },
+              {
+                 "name": "two",
+                 "path": "two.path",
+              },
+              {
+                 "name": "one",
+                 "path": "one.path"
+              }
+           ]
+        });
+
+        let mut h = Handlebars::new();
+        h.register_helper("previous", Box::new(RenderNavigation::previous(Vec::new())));
+        h.register_helper("next", Box::new(RenderNavigation::next(Vec::new())));
+
+        assert_eq!(
+            h.render_template(TEMPLATE, &data).unwrap(),
+            "three: three.html|one: one.html"
+        );
+    }
 }
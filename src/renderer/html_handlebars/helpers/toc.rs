@@ -2,15 +2,20 @@ use std::collections::BTreeMap;
 use std::io;
 use std::path::Path;
 
+use crate::book::output_path_for_source;
 use crate::utils;
 
 use handlebars::{Context, Handlebars, Helper, HelperDef, Output, RenderContext, RenderError};
-use pulldown_cmark::{html, Event, Parser};
+use pulldown_cmark::{html, Event, Parser, Tag};
 
 // Handlebars helper to construct TOC
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 pub struct RenderToc {
     pub no_section_label: bool,
+    /// Leave chapters nested deeper than this out.
+    pub max_depth: Option<usize>,
+    /// See `Config::index_file_names`.
+    pub index_file_names: Vec<String>,
 }
 
 impl HelperDef for RenderToc {
@@ -35,6 +40,11 @@ impl HelperDef for RenderToc {
             .as_str()
             .ok_or_else(|| RenderError::new("Type error for `path`, string expected"))?
             .replace("\"", "");
+        // Pages using a `<base>` tag set this to "" rather than the real path.
+        let path_to_root = match rc.evaluate(ctx, "@root/path_to_root")?.as_json().as_str() {
+            Some(path_to_root) => path_to_root.to_owned(),
+            None => utils::fs::path_to_root(&current_path),
+        };
 
         let current_section = rc
             .evaluate(ctx, "@root/section")?
@@ -71,6 +81,11 @@ impl HelperDef for RenderToc {
             } else {
                 ("", 1)
             };
+            let is_deepest = match self.max_depth {
+                Some(max_depth) if level > max_depth => continue,
+                Some(max_depth) => level == max_depth,
+                None => false,
+            };
 
             let is_expanded =
                 if !fold_enable || (!section.is_empty() && current_section.starts_with(section)) {
@@ -109,22 +124,26 @@ impl HelperDef for RenderToc {
             }
 
             // Link
-            let path_exists = if let Some(path) =
+            let path_exists = if let Some(url) = item.get("external_url") {
+                out.write("<a href=\"")?;
+                out.write(&handlebars::html_escape(url))?;
+                out.write("\">")?;
+                true
+            } else if let Some(path) =
                 item.get("path")
                     .and_then(|p| if p.is_empty() { None } else { Some(p) })
             {
                 out.write("<a href=\"")?;
 
-                let tmp = Path::new(item.get("path").expect("Error: path should be Some(_)"))
-                    .with_extension("html")
+                let tmp = output_path_for_source(Path::new(path), &self.index_file_names)
                     .to_str()
                     .unwrap()
                     // Hack for windows who tends to use `\` as separator instead of `/`
                     .replace("\\", "/");
 
                 // Add link
-                out.write(&utils::fs::path_to_root(&current_path))?;
-                out.write(&tmp)?;
+                out.write(&path_to_root)?;
+                out.write(&utils::percent_encode_url(&tmp))?;
                 out.write("\"")?;
 
                 if path == &current_path {
@@ -148,20 +167,7 @@ impl HelperDef for RenderToc {
             }
 
             if let Some(name) = item.get("name") {
-                // Render only inline code blocks
-
-                // filter all events that are not inline code blocks
-                let parser = Parser::new(name).filter(|event| match *event {
-                    Event::Code(_) | Event::Html(_) | Event::Text(_) => true,
-                    _ => false,
-                });
-
-                // render markdown to html
-                let mut markdown_parsed_name = String::with_capacity(name.len() * 3 / 2);
-                html::push_html(&mut markdown_parsed_name, parser);
-
-                // write to the handlebars template
-                write_escaped(out, &markdown_parsed_name)?;
+                write_name(out, name)?;
             }
 
             if path_exists {
@@ -173,7 +179,7 @@ impl HelperDef for RenderToc {
             // Render expand/collapse toggle
             if let Some(flag) = item.get("has_sub_items") {
                 let has_sub_items = flag.parse::<bool>().unwrap_or_default();
-                if fold_enable && has_sub_items {
+                if fold_enable && has_sub_items && !is_deepest {
                     out.write("<a class=\"toggle\"><div>❱</div></a>")?;
                 }
             }
@@ -206,6 +212,27 @@ fn write_li_open_tag(
     out.write(&li)
 }
 
+/// Render a chapter's name, keeping inline code, emphasis and strong
+/// emphasis. Links are replaced by their text, since the name is already
+/// inside a link.
+fn write_name(out: &mut dyn Output, name: &str) -> io::Result<()> {
+    for event in Parser::new(name) {
+        match event {
+            Event::Start(Tag::Emphasis) => out.write("<em>")?,
+            Event::End(Tag::Emphasis) => out.write("</em>")?,
+            Event::Start(Tag::Strong) => out.write("<strong>")?,
+            Event::End(Tag::Strong) => out.write("</strong>")?,
+            Event::Code(_) | Event::Html(_) | Event::Text(_) => {
+                let mut rendered = String::new();
+                html::push_html(&mut rendered, std::iter::once(event));
+                write_escaped(out, &rendered)?;
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
 fn write_escaped(out: &mut dyn Output, mut title: &str) -> io::Result<()> {
     let needs_escape: &[char] = &['<', '>'];
     while let Some(next) = title.find(needs_escape) {
@@ -5,19 +5,32 @@ use std::path::Path;
 use elasticlunr::Index;
 use pulldown_cmark::*;
 
-use crate::book::{Book, BookItem};
+use crate::book::{output_path_for, Book, BookItem};
 use crate::config::Search;
 use crate::errors::*;
 use crate::theme::searcher;
-use crate::utils;
+use crate::utils::{self, AnchorStyle};
 
 /// Creates all files required for search.
-pub fn create_files(search_config: &Search, destination: &Path, book: &Book) -> Result<()> {
+pub fn create_files(
+    search_config: &Search,
+    anchor_style: AnchorStyle,
+    destination: &Path,
+    book: &Book,
+    index_file_names: &[String],
+) -> Result<()> {
     let mut index = Index::new(&["title", "body", "breadcrumbs"]);
     let mut doc_urls = Vec::with_capacity(book.sections.len());
 
     for item in book.iter() {
-        render_item(&mut index, &search_config, &mut doc_urls, item)?;
+        render_item(
+            &mut index,
+            &search_config,
+            anchor_style,
+            &mut doc_urls,
+            item,
+            index_file_names,
+        )?;
     }
 
     let index = write_to_json(index, &search_config, doc_urls)?;
@@ -67,19 +80,18 @@ fn add_doc(
 fn render_item(
     index: &mut Index,
     search_config: &Search,
+    anchor_style: AnchorStyle,
     doc_urls: &mut Vec<String>,
     item: &BookItem,
+    index_file_names: &[String],
 ) -> Result<()> {
-    let chapter = match *item {
-        BookItem::Chapter(ref ch) if !ch.is_draft_chapter() => ch,
+    let (chapter, filepath) = match *item {
+        BookItem::Chapter(ref ch) => match output_path_for(ch, index_file_names) {
+            Some(filepath) => (ch, filepath),
+            None => return Ok(()),
+        },
         _ => return Ok(()),
     };
-
-    let chapter_path = chapter
-        .path
-        .as_ref()
-        .expect("Checked that path exists above");
-    let filepath = Path::new(&chapter_path).with_extension("html");
     let filepath = filepath
         .to_str()
         .with_context(|| "Could not convert HTML path to str")?;
@@ -120,7 +132,7 @@ fn render_item(
             }
             Event::End(Tag::Heading(i)) if i <= max_section_depth => {
                 in_heading = false;
-                section_id = Some(utils::id_from_content(&heading));
+                section_id = Some(utils::id_from_content_with_style(&heading, anchor_style));
                 breadcrumbs.push(heading.clone());
             }
             Event::Start(Tag::FootnoteDefinition(name)) => {
@@ -34,12 +34,8 @@ impl Renderer for MarkdownRenderer {
         trace!("markdown render");
         for item in book.iter() {
             if let BookItem::Chapter(ref ch) = *item {
-                if !ch.is_draft_chapter() {
-                    utils::fs::write_file(
-                        &ctx.destination,
-                        &ch.path.as_ref().expect("Checked path exists before"),
-                        ch.content.as_bytes(),
-                    )?;
+                if let Some(ref path) = ch.path {
+                    utils::fs::write_file(&ctx.destination, path, ch.content.as_bytes())?;
                 }
             }
         }
@@ -66,6 +66,14 @@ pub struct RenderContext {
     pub destination: PathBuf,
     #[serde(skip)]
     pub(crate) chapter_titles: HashMap<PathBuf, String>,
+    /// When [`MDBook::build_chapters()`] is rebuilding only some chapters,
+    /// their paths. Only the HTML renderer uses this, to leave the pages of
+    /// other chapters alone; it isn't passed on to other renderers, which
+    /// always build the whole book.
+    ///
+    /// [`MDBook::build_chapters()`]: ../book/struct.MDBook.html#method.build_chapters
+    #[serde(skip)]
+    pub(crate) changed_chapters: Option<Vec<PathBuf>>,
     #[serde(skip)]
     __non_exhaustive: (),
 }
@@ -84,13 +92,14 @@ impl RenderContext {
             root: root.into(),
             destination: destination.into(),
             chapter_titles: HashMap::new(),
+            changed_chapters: None,
             __non_exhaustive: (),
         }
     }
 
     /// Get the source directory's (absolute) path on disk.
     pub fn source_dir(&self) -> PathBuf {
-        self.root.join(&self.config.book.src)
+        self.config.source_dir(&self.root)
     }
 
     /// Load a `RenderContext` from its JSON representation.
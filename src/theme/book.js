@@ -23,6 +23,20 @@ function playground_text(playground) {
         ]);
     }
 
+    // Run requests use the `run-timeout-secs` and `run-retries` settings.
+    function fetch_with_retries(url, options, retries) {
+        let timeout = window.playground_run_timeout !== undefined
+            ? window.playground_run_timeout * 1000
+            : 6000;
+        return fetch_with_timeout(url, options, timeout)
+            .catch(error => {
+                if (retries > 0) {
+                    return fetch_with_retries(url, options, retries - 1);
+                }
+                throw error;
+            });
+    }
+
     var playgrounds = Array.from(document.querySelectorAll(".playground"));
     if (playgrounds.length > 0) {
         fetch_with_timeout("https://play.rust-lang.org/meta/crates", {
@@ -127,14 +141,14 @@ function playground_text(playground) {
 
         result_block.innerText = "Running...";
 
-        fetch_with_timeout("https://play.rust-lang.org/evaluate.json", {
+        fetch_with_retries("https://play.rust-lang.org/evaluate.json", {
             headers: {
                 'Content-Type': "application/json",
             },
             method: 'POST',
             mode: 'cors',
             body: JSON.stringify(params)
-        })
+        }, window.playground_run_retries || 0)
         .then(response => response.json())
         .then(response => {
             if (response.result.trim() === '') {
@@ -568,6 +582,11 @@ function playground_text(playground) {
 })();
 
 (function clipboard() {
+    if (typeof ClipboardJS === 'undefined') {
+        // Copy buttons are turned off.
+        return;
+    }
+
     var clipButtons = document.querySelectorAll('.clip-button');
 
     function hideTooltip(elem) {
@@ -0,0 +1,186 @@
+//! The SHA-256 digests of the builtin theme assets, keyed by their path in
+//! the theme directory. Update an entry with `sha256sum` whenever its asset
+//! changes; the `every_builtin_asset_is_pinned` test checks that they match.
+
+pub(super) static CHECKSUMS: &[(&str, &str)] = &[
+    (
+        "FontAwesome/css/font-awesome.min.css",
+        "799aeb25cc0373fdee0e1b1db7ad6c2f6a0e058dfadaa3379689f583213190bd",
+    ),
+    (
+        "FontAwesome/fonts/FontAwesome.otf",
+        "444dd4366615ffc4a16d012b2fa90137065d3ccb410fa6fd5e4ddd7b5e4ffcd5",
+    ),
+    (
+        "FontAwesome/fonts/fontawesome-webfont.eot",
+        "7bfcab6db99d5cfbf1705ca0536ddc78585432cc5fa41bbd7ad0f009033b2979",
+    ),
+    (
+        "FontAwesome/fonts/fontawesome-webfont.svg",
+        "ad6157926c1622ba4e1d03d478f1541368524bfc46f51e42fe0d945f7ef323e4",
+    ),
+    (
+        "FontAwesome/fonts/fontawesome-webfont.ttf",
+        "aa58f33f239a0fb02f5c7a6c45c043d7a9ac9a093335806694ecd6d4edc0d6a8",
+    ),
+    (
+        "FontAwesome/fonts/fontawesome-webfont.woff",
+        "ba0c59deb5450f5cb41b3f93609ee2d0d995415877ddfa223e8a8a7533474f07",
+    ),
+    (
+        "FontAwesome/fonts/fontawesome-webfont.woff2",
+        "2adefcbc041e7d18fcf2d417879dc5a09997aa64d675b7a3c4b6ce33da13f3fe",
+    ),
+    (
+        "ayu-highlight.css",
+        "c141251b5888ccc185c31976e6cb9234f7827ae6bc4975926cafc8bca5e8f83d",
+    ),
+    (
+        "book.js",
+        "a20ebc83f5e690e8393e8af8e118aa58ad320197c9ac1be3c267c5f6e208a00b",
+    ),
+    (
+        "clipboard.min.js",
+        "1626706afc88d95ebe1173b553ec732c6dc82a576989315fdf5e7779af738a44",
+    ),
+    (
+        "css/chrome.css",
+        "c98f91460c02f671a6553e7495c93fd06387aa7515f1cc0e9e7918706340c833",
+    ),
+    (
+        "css/general.css",
+        "d79cc67fd47f50feced5266e86d0a99c197ebe5868f320353ee693a04fd69068",
+    ),
+    (
+        "css/print.css",
+        "a4278dff9af38765eb9d344aa56dcc652ac79c73afc408385b62a4b611b89c14",
+    ),
+    (
+        "css/variables.css",
+        "fb92ac934e47e6d2d6abe9c2fd055aa7dc2f63e5a71e5caefbf85cf2b248d739",
+    ),
+    (
+        "favicon.png",
+        "8114d1fc74f4b5621ad9afde7746ed9cf7e420be317a6e29023d2298d58aa15b",
+    ),
+    (
+        "favicon.svg",
+        "de23e50b1c4dd6e052b3e21d444fcd4b13568b3840ac3c99d9be4e9263c0ef59",
+    ),
+    (
+        "fonts/OPEN-SANS-LICENSE.txt",
+        "cfc7749b96f63bd31c3c42b5c471bf756814053e847c10f3eb003417bc523d30",
+    ),
+    (
+        "fonts/SOURCE-CODE-PRO-LICENSE.txt",
+        "d1e6d465a83ba1a3be52db6484868cf5812ae9bbf91abdad3900ba0165afcf93",
+    ),
+    (
+        "fonts/fonts.css",
+        "2db113e6ebede8403c607db3dceb5acc53c247720d5955d22f7db56beb7139b6",
+    ),
+    (
+        "fonts/open-sans-v17-all-charsets-300.woff2",
+        "7736aa3596c468515c3209f2f9d68cfae96d94c05689bcc11a5dce426a6ee2e8",
+    ),
+    (
+        "fonts/open-sans-v17-all-charsets-300italic.woff2",
+        "2c7b95c08df0d228caec6d4bfed06da0f7ab6b76ea5cc3f75b5c6ae416bc571b",
+    ),
+    (
+        "fonts/open-sans-v17-all-charsets-600.woff2",
+        "486c67592731a0b36a89dba1fd0b97aeb73f236bbf60dbf28d7c6b5723c07989",
+    ),
+    (
+        "fonts/open-sans-v17-all-charsets-600italic.woff2",
+        "1a3e865977024f444834a75a1b33b89b93134c93007ae3d6e14f24e6c88d8dfb",
+    ),
+    (
+        "fonts/open-sans-v17-all-charsets-700.woff2",
+        "c22fe8c70c36f1d862903b772eaed864d3a8fa849473c9caff224fdb852428e4",
+    ),
+    (
+        "fonts/open-sans-v17-all-charsets-700italic.woff2",
+        "238ae9593944112bee8dd65f8ebc5f3d3862160a8a245fbe1ee3150bc9a2fd81",
+    ),
+    (
+        "fonts/open-sans-v17-all-charsets-800.woff2",
+        "3d2c812adf74deb36fead3ff8469800d3c0b23eb2c858ae49310291f89490146",
+    ),
+    (
+        "fonts/open-sans-v17-all-charsets-800italic.woff2",
+        "ba1521ec219db9bc5bfec0e3e7a897369d98b30d4e853ee4aa525322784428b8",
+    ),
+    (
+        "fonts/open-sans-v17-all-charsets-italic.woff2",
+        "6c9463f7096c0b9d610e095ed248ac1e8a8da7e92d17e9be544f3baced7b62b2",
+    ),
+    (
+        "fonts/open-sans-v17-all-charsets-regular.woff2",
+        "2e3b1d34ac67763ab50652da19305d4b3694c6b6e6bf35f4b98411ce4af646d2",
+    ),
+    (
+        "fonts/source-code-pro-v11-all-charsets-500.woff2",
+        "2bdd9410b0141db3cbbf4cfc3818cc6fad279e8e63940940e06cd6af76ccbfcf",
+    ),
+    (
+        "head.hbs",
+        "56b3ab3c6eabd4723d4794ecd0a7452aa8903c55a2106d60bceacc74d76311c3",
+    ),
+    (
+        "header.hbs",
+        "1fd27c9ccd016060dc4d6e77f12bf58b26e7c604aebe2577a67097f95a3de70a",
+    ),
+    (
+        "highlight.css",
+        "ddc0c59ce60e40756313a7e470a9440dd31558c416c5deb6944670b60b4af014",
+    ),
+    (
+        "highlight.js",
+        "5a2b5dadd60831dd1f82220223e2ab18e627061912cc89b5c450ab2c8f26ff90",
+    ),
+    (
+        "index.hbs",
+        "e073ca4f0aa3230013f65a2c29e110461d60654e23e7c1fcc24cb01e7b148c99",
+    ),
+    (
+        "playground_editor/ace.js",
+        "2a3cd908c9619862b52f621ce2a40f76b772eb51c17308b14bd26d1809af8f87",
+    ),
+    (
+        "playground_editor/editor.js",
+        "16ca416ca77428fe23cb8e18afbd3626a6a86723d6b6e189c47da95d9e9bdc31",
+    ),
+    (
+        "playground_editor/mode-rust.js",
+        "2c9d5c9af5ae32612aef1ca5653e3473ed40747d36ecb4a97719ff14707d8535",
+    ),
+    (
+        "playground_editor/theme-dawn.js",
+        "4493f9c88ed7185f7bb4195be77018d21cdc439a34bd4e5da64b566eb996fbe8",
+    ),
+    (
+        "playground_editor/theme-tomorrow_night.js",
+        "9dbe62a913ebe3fd9667f41f69c0301bacd963081c69abb0219e4acac4710f60",
+    ),
+    (
+        "redirect.hbs",
+        "c10b6e36dda1a4f222aa61cd1c6180a2f2937397379adba2c10b9b41e446709e",
+    ),
+    (
+        "searcher/elasticlunr.min.js",
+        "ef4e11c157b1e2e89782d30bd726f2d5ff7834ea5e26ad02474325f8b1f126c9",
+    ),
+    (
+        "searcher/mark.min.js",
+        "09e88c2cfaf23ea8a37b5681433eafea97033af632ecc948c8c1ee9944647743",
+    ),
+    (
+        "searcher/searcher.js",
+        "597b15cf5684c9293e4ecd86ceedbf88d367fe0283ec1874acd54f8b774b545b",
+    ),
+    (
+        "tomorrow-night.css",
+        "243cb61aa526cef79b3545b1c7f2b681747dd346867c8ceb36c69487acc390fb",
+    ),
+];
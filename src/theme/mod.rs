@@ -1,5 +1,7 @@
 #![allow(missing_docs)]
 
+mod checksums;
+
 pub mod playground_editor;
 
 pub mod fonts;
@@ -11,6 +13,9 @@ use std::fs::File;
 use std::io::Read;
 use std::path::Path;
 
+use sha2::{Digest, Sha256};
+
+use self::checksums::CHECKSUMS;
 use crate::errors::*;
 
 pub static INDEX: &[u8] = include_bytes!("index.hbs");
@@ -140,6 +145,71 @@ impl Theme {
 
         theme
     }
+
+    /// Check that the builtin assets this theme uses haven't been tampered
+    /// with, by comparing them against the SHA-256 digests they had when they
+    /// were added to mdBook.
+    ///
+    /// Assets overridden by the user's theme directory are skipped. Rendering
+    /// doesn't call this, since it hashes every asset; it's for tools which
+    /// want to check a theme before using it.
+    pub fn verify_integrity(&self) -> Result<()> {
+        // (file name, builtin contents, contents used by this theme)
+        type Asset<'a> = (&'a str, &'a [u8], Option<&'a [u8]>);
+        let assets: [Asset<'_>; 16] = [
+            ("index.hbs", INDEX, Some(&self.index)),
+            ("head.hbs", HEAD, Some(&self.head)),
+            ("redirect.hbs", REDIRECT, Some(&self.redirect)),
+            ("header.hbs", HEADER, Some(&self.header)),
+            ("css/chrome.css", CHROME_CSS, Some(&self.chrome_css)),
+            ("css/general.css", GENERAL_CSS, Some(&self.general_css)),
+            ("css/print.css", PRINT_CSS, Some(&self.print_css)),
+            (
+                "css/variables.css",
+                VARIABLES_CSS,
+                Some(&self.variables_css),
+            ),
+            ("favicon.png", FAVICON_PNG, self.favicon_png.as_deref()),
+            ("favicon.svg", FAVICON_SVG, self.favicon_svg.as_deref()),
+            ("book.js", JS, Some(&self.js)),
+            ("highlight.css", HIGHLIGHT_CSS, Some(&self.highlight_css)),
+            (
+                "tomorrow-night.css",
+                TOMORROW_NIGHT_CSS,
+                Some(&self.tomorrow_night_css),
+            ),
+            (
+                "ayu-highlight.css",
+                AYU_HIGHLIGHT_CSS,
+                Some(&self.ayu_highlight_css),
+            ),
+            ("highlight.js", HIGHLIGHT_JS, Some(&self.highlight_js)),
+            ("clipboard.min.js", CLIPBOARD_JS, Some(&self.clipboard_js)),
+        ];
+
+        for (name, builtin, current) in assets.iter() {
+            if *current != Some(*builtin) {
+                continue;
+            }
+
+            let expected = CHECKSUMS
+                .iter()
+                .find(|(file, _)| file == name)
+                .map(|(_, checksum)| *checksum)
+                .with_context(|| format!("No checksum recorded for {}", name))?;
+            let actual = format!("{:x}", Sha256::digest(builtin));
+            if actual != expected {
+                bail!(
+                    "The builtin {} doesn't match its checksum (expected {}, got {})",
+                    name,
+                    expected,
+                    actual
+                );
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl Default for Theme {
@@ -184,6 +254,7 @@ fn load_file_contents<P: AsRef<Path>>(filename: P, dest: &mut Vec<u8>) -> Result
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::ffi::OsStr;
     use std::fs;
     use std::path::PathBuf;
     use tempfile::Builder as TempFileBuilder;
@@ -253,6 +324,71 @@ mod tests {
         assert_eq!(got, empty);
     }
 
+    #[test]
+    fn builtin_assets_match_their_checksums() {
+        assert_eq!(
+            format!("{:x}", Sha256::digest(JS)),
+            CHECKSUMS
+                .iter()
+                .find(|(name, _)| *name == "book.js")
+                .unwrap()
+                .1
+        );
+        Theme::default().verify_integrity().unwrap();
+    }
+
+    #[test]
+    fn every_builtin_asset_is_pinned() {
+        fn check_dir(theme_dir: &Path, dir: &Path, seen: &mut usize) {
+            for entry in fs::read_dir(dir).unwrap() {
+                let path = entry.unwrap().path();
+                if path.is_dir() {
+                    check_dir(theme_dir, &path, seen);
+                    continue;
+                }
+                if path.extension() == Some(OsStr::new("rs")) {
+                    continue;
+                }
+
+                let name = path
+                    .strip_prefix(theme_dir)
+                    .unwrap()
+                    .to_str()
+                    .unwrap()
+                    .replace('\\', "/");
+                let actual = format!("{:x}", Sha256::digest(&fs::read(&path).unwrap()));
+                let expected = CHECKSUMS.iter().find(|(file, _)| *file == name);
+                assert_eq!(
+                    expected.map(|(_, checksum)| *checksum),
+                    Some(actual.as_str()),
+                    "src/theme/checksums.rs needs updating for {}",
+                    name
+                );
+                *seen += 1;
+            }
+        }
+
+        let theme_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/theme");
+        let mut seen = 0;
+        check_dir(&theme_dir, &theme_dir, &mut seen);
+        assert_eq!(
+            seen,
+            CHECKSUMS.len(),
+            "src/theme/checksums.rs lists a removed asset"
+        );
+    }
+
+    #[test]
+    fn overridden_assets_are_not_verified() {
+        let theme = Theme {
+            js: b"console.log('custom');".to_vec(),
+            favicon_png: None,
+            ..Default::default()
+        };
+
+        theme.verify_integrity().unwrap();
+    }
+
     #[test]
     fn favicon_override() {
         let temp = TempFileBuilder::new().prefix("mdbook-").tempdir().unwrap();
@@ -0,0 +1,165 @@
+//! Definition lists, which pulldown-cmark doesn't support, written the way
+//! PHP Markdown Extra and Pandoc do:
+//!
+//! ```markdown
+//! Term
+//! : The definition of the term.
+//! : Another definition.
+//!
+//! Another term
+//! : Its definition.
+//! ```
+//!
+//! They are turned into a `<dl>` HTML block before the markdown is parsed,
+//! with the terms and definitions rendered as inline markdown.
+
+use pulldown_cmark::{html, Event, Parser, Tag};
+
+/// Replace every definition list in `text` with the equivalent HTML.
+pub(crate) fn expand_definition_lists(text: &str) -> String {
+    let lines: Vec<&str> = text.split('\n').collect();
+    let mut out: Vec<String> = Vec::with_capacity(lines.len());
+    let mut fence: Option<&str> = None;
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+
+        if let Some(marker) = fence {
+            if line.trim_start().starts_with(marker) {
+                fence = None;
+            }
+            out.push(line.to_string());
+            i += 1;
+            continue;
+        }
+        if let Some(marker) = fence_marker(line) {
+            fence = Some(marker);
+            out.push(line.to_string());
+            i += 1;
+            continue;
+        }
+
+        let starts_paragraph = i == 0 || lines[i - 1].trim().is_empty();
+        if starts_paragraph && starts_entry(&lines, i) {
+            let (html, end) = definition_list(&lines, i);
+            out.push(html);
+            // Make sure whatever follows doesn't become part of the HTML block.
+            if end < lines.len() && !lines[end].trim().is_empty() {
+                out.push(String::new());
+            }
+            i = end;
+            continue;
+        }
+
+        out.push(line.to_string());
+        i += 1;
+    }
+
+    out.join("\n")
+}
+
+/// The `<dl>` for the list starting at line `start`, and the index of the
+/// first line after it.
+fn definition_list(lines: &[&str], start: usize) -> (String, usize) {
+    let mut html = String::from("<dl>\n");
+    let mut i = start;
+
+    loop {
+        html.push_str(&format!("<dt>{}</dt>\n", render_inline(lines[i].trim())));
+        i += 1;
+        while let Some(definition) = lines.get(i).and_then(|line| definition(line)) {
+            html.push_str(&format!("<dd>{}</dd>\n", render_inline(definition)));
+            i += 1;
+        }
+
+        // Entries may be separated by a blank line.
+        if starts_entry(lines, i) {
+            continue;
+        }
+        if lines.get(i).map(|line| line.trim().is_empty()) == Some(true)
+            && starts_entry(lines, i + 1)
+        {
+            i += 1;
+            continue;
+        }
+        break;
+    }
+
+    html.push_str("</dl>");
+    (html, i)
+}
+
+/// Does a term, followed by its first definition, start at line `i`?
+fn starts_entry(lines: &[&str], i: usize) -> bool {
+    let term = match lines.get(i) {
+        Some(term) => term,
+        None => return false,
+    };
+    let is_term = !term.trim().is_empty()
+        && !term.starts_with("    ")
+        && !term.starts_with('\t')
+        && definition(term).is_none()
+        && fence_marker(term).is_none();
+
+    is_term && lines.get(i + 1).and_then(|line| definition(line)).is_some()
+}
+
+/// The text of a `: definition` line.
+fn definition(line: &str) -> Option<&str> {
+    let rest = line.strip_prefix(':')?;
+    if rest.starts_with(' ') || rest.starts_with('\t') {
+        Some(rest.trim())
+    } else {
+        None
+    }
+}
+
+fn fence_marker(line: &str) -> Option<&'static str> {
+    let trimmed = line.trim_start();
+    if line.len() - trimmed.len() > 3 {
+        None
+    } else if trimmed.starts_with("```") {
+        Some("```")
+    } else if trimmed.starts_with("~~~") {
+        Some("~~~")
+    } else {
+        None
+    }
+}
+
+/// Render a line of markdown without wrapping it in a paragraph.
+fn render_inline(text: &str) -> String {
+    let events = Parser::new(text).filter(|event| {
+        !matches!(
+            event,
+            Event::Start(Tag::Paragraph) | Event::End(Tag::Paragraph)
+        )
+    });
+    let mut rendered = String::new();
+    html::push_html(&mut rendered, events);
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn terms_and_definitions_become_a_dl() {
+        let src = "Intro.\n\nApple\n: A *fruit*.\n: A company.\n\n`Rust`\n: A language.\n\nAfter.";
+        let got = expand_definition_lists(src);
+
+        assert_eq!(
+            got,
+            "Intro.\n\n<dl>\n<dt>Apple</dt>\n<dd>A <em>fruit</em>.</dd>\n<dd>A company.</dd>\n\
+             <dt><code>Rust</code></dt>\n<dd>A language.</dd>\n</dl>\n\nAfter."
+        );
+    }
+
+    #[test]
+    fn other_colons_are_left_alone() {
+        let src = "```text\nTerm\n: not a definition\n```\n\nA paragraph\nwith two lines\n: here\n\nkey:value\n";
+        assert_eq!(expand_definition_lists(src), src);
+    }
+}
@@ -0,0 +1,124 @@
+//! Keeping a record of the warnings and errors mdBook logs, so a build can
+//! fail if there were any warnings (`build.deny-warnings`) and tools can
+//! report them.
+//!
+//! mdBook logs through the [`log`] crate, so nothing is recorded unless the
+//! global logger is wrapped in a [`DiagnosticCollector`]. The `mdbook` binary
+//! always does this.
+
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use log::{Level, Log, Metadata, Record};
+
+/// A warning or error logged by mdBook.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    /// Either [`Level::Warn`] or [`Level::Error`].
+    pub level: Level,
+    /// The logged message.
+    pub message: String,
+    /// The file of the book the message is about, if the code logging it
+    /// knew.
+    pub file: Option<PathBuf>,
+    /// The line of `file` the message is about, starting at 1.
+    pub line: Option<usize>,
+}
+
+lazy_static! {
+    static ref DIAGNOSTICS: Mutex<Vec<Diagnostic>> = Mutex::new(Vec::new());
+}
+
+thread_local! {
+    /// The file and line given to `at()`, while its closure runs.
+    // A `const` initializer needs a newer compiler than mdBook supports.
+    #[allow(unknown_lints, clippy::missing_const_for_thread_local)]
+    static LOCATION: RefCell<Option<(PathBuf, Option<usize>)>> = RefCell::new(None);
+}
+
+/// A logger which keeps a copy of mdBook's own warnings and errors, even
+/// ones the inner logger filters out, and passes every record on to the
+/// inner logger.
+pub struct DiagnosticCollector<L> {
+    inner: L,
+}
+
+impl<L: Log> DiagnosticCollector<L> {
+    /// Wrap `inner`, which does the actual logging.
+    pub fn new(inner: L) -> DiagnosticCollector<L> {
+        DiagnosticCollector { inner }
+    }
+}
+
+impl<L: Log> Log for DiagnosticCollector<L> {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.level() <= Level::Warn || self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        if record.level() <= Level::Warn && record.target().starts_with("mdbook") {
+            let location = LOCATION.with(|location| location.borrow().clone());
+            let (file, line) = match location {
+                Some((file, line)) => (Some(file), line),
+                None => (None, None),
+            };
+            DIAGNOSTICS.lock().unwrap().push(Diagnostic {
+                level: record.level(),
+                message: record.args().to_string(),
+                file,
+                line,
+            });
+        }
+        self.inner.log(record);
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// How many warnings and errors have been recorded so far, to pass to
+/// [`since()`] later.
+pub fn count() -> usize {
+    DIAGNOSTICS.lock().unwrap().len()
+}
+
+/// The warnings and errors recorded after [`count()`] returned `start`.
+pub fn since(start: usize) -> Vec<Diagnostic> {
+    let diagnostics = DIAGNOSTICS.lock().unwrap();
+    diagnostics.get(start..).unwrap_or_default().to_vec()
+}
+
+/// Run `f`, recording anything it logs as being about `line` of `file`.
+pub(crate) fn at<R>(file: &Path, line: Option<usize>, f: impl FnOnce() -> R) -> R {
+    let previous =
+        LOCATION.with(|location| location.borrow_mut().replace((file.to_path_buf(), line)));
+    let result = f();
+    LOCATION.with(|location| *location.borrow_mut() = previous);
+    result
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    struct Discard;
+
+    impl Log for Discard {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            false
+        }
+        fn log(&self, _: &Record<'_>) {}
+        fn flush(&self) {}
+    }
+
+    /// Record mdBook's warnings from now on, for tests which check what was
+    /// logged, returning the [`count()`] to pass to [`since()`].
+    pub(crate) fn start_collecting() -> usize {
+        // Another test may have installed it already.
+        let _ = log::set_boxed_logger(Box::new(DiagnosticCollector::new(Discard)));
+        log::set_max_level(log::LevelFilter::Warn);
+        count()
+    }
+}
@@ -1,14 +1,18 @@
 #![allow(missing_docs)] // FIXME: Document this
 
+pub(crate) mod definition_list;
+pub mod diagnostics;
 pub mod fs;
 mod string;
 pub(crate) mod toml_ext;
+use crate::config::{Markdown, SmartPunctuation};
 use crate::errors::Error;
 use regex::Regex;
 
 use pulldown_cmark::{html, CodeBlockKind, CowStr, Event, Options, Parser, Tag};
 
 use std::borrow::Cow;
+use std::collections::HashMap;
 use std::fmt::Write;
 use std::path::Path;
 
@@ -25,16 +29,91 @@ pub fn collapse_whitespace(text: &str) -> Cow<'_, str> {
     RE.replace_all(text, " ")
 }
 
+/// Reduce a line of markdown, like a chapter name, to its plain text by
+/// unwrapping code spans and dropping emphasis, links and inline HTML.
+pub fn strip_inline_markdown(text: &str) -> String {
+    let mut plain = String::with_capacity(text.len());
+    for event in Parser::new(text) {
+        match event {
+            Event::Text(t) | Event::Code(t) => plain.push_str(&t),
+            Event::SoftBreak | Event::HardBreak => plain.push(' '),
+            _ => {}
+        }
+    }
+    plain
+}
+
+/// Whether a path from the config, such as an entry in `additional-css`, is
+/// an `http(s)://` URL to link to as-is rather than a file in the book.
+pub(crate) fn is_external_url(path: &Path) -> bool {
+    path.to_str()
+        .map(|p| p.starts_with("http://") || p.starts_with("https://"))
+        .unwrap_or(false)
+}
+
+/// Percent-encode a relative URL such as `My Chapter.html#Some Heading`,
+/// leaving the `/` separators and the `#` before the fragment intact.
+pub fn percent_encode_url(url: &str) -> String {
+    fn encode(out: &mut String, text: &str, keep_slashes: bool) {
+        for byte in text.bytes() {
+            match byte {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                    out.push(byte as char)
+                }
+                b'/' if keep_slashes => out.push('/'),
+                _ => write!(out, "%{:02X}", byte).unwrap(),
+            }
+        }
+    }
+
+    let mut encoded = String::with_capacity(url.len());
+    let mut parts = url.splitn(2, '#');
+    encode(&mut encoded, parts.next().unwrap_or_default(), true);
+    if let Some(fragment) = parts.next() {
+        encoded.push('#');
+        encode(&mut encoded, fragment, false);
+    }
+    encoded
+}
+
+/// How heading text is turned into an anchor `id`, see
+/// [`normalize_id_with_style`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AnchorStyle {
+    /// What whitespace is replaced with.
+    pub separator: char,
+    /// Whether ASCII letters are lowercased.
+    pub lowercase: bool,
+}
+
+impl Default for AnchorStyle {
+    fn default() -> AnchorStyle {
+        AnchorStyle {
+            separator: '-',
+            lowercase: true,
+        }
+    }
+}
+
 /// Convert the given string to a valid HTML element ID.
 /// The only restriction is that the ID must not contain any ASCII whitespace.
 pub fn normalize_id(content: &str) -> String {
+    normalize_id_with_style(content, AnchorStyle::default())
+}
+
+/// Like [`normalize_id`], but with a different separator or letter case.
+pub fn normalize_id_with_style(content: &str, style: AnchorStyle) -> String {
     content
         .chars()
         .filter_map(|ch| {
             if ch.is_alphanumeric() || ch == '_' || ch == '-' {
-                Some(ch.to_ascii_lowercase())
+                if style.lowercase {
+                    Some(ch.to_ascii_lowercase())
+                } else {
+                    Some(ch)
+                }
             } else if ch.is_whitespace() {
-                Some('-')
+                Some(style.separator)
             } else {
                 None
             }
@@ -45,6 +124,11 @@ pub fn normalize_id(content: &str) -> String {
 /// Generate an ID for use with anchors which is derived from a "normalised"
 /// string.
 pub fn id_from_content(content: &str) -> String {
+    id_from_content_with_style(content, AnchorStyle::default())
+}
+
+/// Like [`id_from_content`], but with a different separator or letter case.
+pub fn id_from_content_with_style(content: &str, style: AnchorStyle) -> String {
     let mut content = content.to_string();
 
     // Skip any tags or html-encoded stuff
@@ -68,7 +152,7 @@ pub fn id_from_content(content: &str) -> String {
     // Remove spaces and hashes indicating a header
     let trimmed = content.trim().trim_start_matches('#').trim();
 
-    normalize_id(trimmed)
+    normalize_id_with_style(trimmed, style)
 }
 
 /// Fix links to the correct location.
@@ -168,43 +252,139 @@ pub fn render_markdown(text: &str, curly_quotes: bool) -> String {
     render_markdown_with_path(text, curly_quotes, None)
 }
 
-pub fn new_cmark_parser(text: &str) -> Parser<'_> {
+/// The pulldown-cmark options for a set of enabled markdown extensions.
+pub fn markdown_options(markdown: &Markdown) -> Options {
     let mut opts = Options::empty();
-    opts.insert(Options::ENABLE_TABLES);
-    opts.insert(Options::ENABLE_FOOTNOTES);
-    opts.insert(Options::ENABLE_STRIKETHROUGH);
-    opts.insert(Options::ENABLE_TASKLISTS);
-    Parser::new_ext(text, opts)
+    opts.set(Options::ENABLE_TABLES, markdown.tables);
+    opts.set(Options::ENABLE_FOOTNOTES, markdown.footnotes);
+    opts.set(Options::ENABLE_STRIKETHROUGH, markdown.strikethrough);
+    opts.set(Options::ENABLE_TASKLISTS, markdown.tasklists);
+    opts
+}
+
+pub fn new_cmark_parser(text: &str) -> Parser<'_> {
+    Parser::new_ext(text, markdown_options(&Markdown::default()))
 }
 
 pub fn render_markdown_with_path(text: &str, curly_quotes: bool, path: Option<&Path>) -> String {
+    let smart_punctuation = SmartPunctuation {
+        quotes: curly_quotes,
+        ..Default::default()
+    };
+    render_markdown_with_options(
+        text,
+        smart_punctuation,
+        path,
+        markdown_options(&Markdown::default()),
+    )
+}
+
+/// Render markdown to HTML with a specific set of pulldown-cmark options, see
+/// [`markdown_options`], and with the given punctuation made typographic.
+pub fn render_markdown_with_options(
+    text: &str,
+    smart_punctuation: SmartPunctuation,
+    path: Option<&Path>,
+    options: Options,
+) -> String {
     let mut s = String::with_capacity(text.len() * 3 / 2);
-    let p = new_cmark_parser(text);
-    let mut converter = EventQuoteConverter::new(curly_quotes);
+    let p = Parser::new_ext(text, options);
+    let mut converter = EventQuoteConverter::new(smart_punctuation);
     let events = p
         .map(clean_codeblock_headers)
         .map(|event| adjust_links(event, path))
         .map(|event| converter.convert(event));
 
-    html::push_html(&mut s, events);
+    html::push_html(&mut s, add_footnote_backrefs(events.collect()).into_iter());
     s
 }
 
+/// Render footnote references and definitions the way pulldown-cmark does,
+/// but with an `id` on every reference and a "↩" link back to each of them
+/// at the end of the footnote.
+fn add_footnote_backrefs(events: Vec<Event<'_>>) -> Vec<Event<'_>> {
+    let mut reference_counts = HashMap::new();
+    for event in &events {
+        if let Event::FootnoteReference(name) = event {
+            *reference_counts.entry(name.clone()).or_insert(0) += 1;
+        }
+    }
+    if reference_counts.is_empty() {
+        return events;
+    }
+
+    let backref_id = |name: &str, n: usize| format!("fr-{}-{}", name, n);
+    let mut numbers = HashMap::new();
+    let mut references_seen = HashMap::new();
+    let mut out = Vec::with_capacity(events.len());
+
+    for event in events {
+        match event {
+            Event::FootnoteReference(name) => {
+                let len = numbers.len() + 1;
+                let number = *numbers.entry(name.clone()).or_insert(len);
+                let seen = references_seen.entry(name.clone()).or_insert(0);
+                *seen += 1;
+                out.push(Event::Html(CowStr::from(format!(
+                    r##"<sup class="footnote-reference" id="{}"><a href="#{}">{}</a></sup>"##,
+                    handlebars::html_escape(&backref_id(&name, *seen)),
+                    handlebars::html_escape(&name),
+                    number
+                ))));
+            }
+            Event::Start(Tag::FootnoteDefinition(name)) => {
+                let len = numbers.len() + 1;
+                let number = *numbers.entry(name.clone()).or_insert(len);
+                out.push(Event::Html(CowStr::from(format!(
+                    r#"<div class="footnote-definition" id="{}"><sup class="footnote-definition-label">{}</sup>"#,
+                    handlebars::html_escape(&name),
+                    number
+                ))));
+            }
+            Event::End(Tag::FootnoteDefinition(name)) => {
+                let count = reference_counts.get(&name).copied().unwrap_or(0);
+                let backrefs: String = (1..=count)
+                    .map(|n| {
+                        format!(
+                            r##" <a href="#{}" class="footnote-backref">↩</a>"##,
+                            handlebars::html_escape(&backref_id(&name, n))
+                        )
+                    })
+                    .collect();
+
+                // Keep the links inside the footnote's last paragraph.
+                let closing_paragraph = match out.last() {
+                    Some(Event::End(Tag::Paragraph)) => out.pop(),
+                    _ => None,
+                };
+                out.push(Event::Html(CowStr::from(backrefs)));
+                out.extend(closing_paragraph);
+                out.push(Event::Html(CowStr::from("</div>\n")));
+            }
+            _ => out.push(event),
+        }
+    }
+
+    out
+}
+
+/// Makes the punctuation in text outside of code typographic, which
+/// pulldown-cmark can't do itself.
 struct EventQuoteConverter {
-    enabled: bool,
+    smart_punctuation: SmartPunctuation,
     convert_text: bool,
 }
 
 impl EventQuoteConverter {
-    fn new(enabled: bool) -> Self {
+    fn new(smart_punctuation: SmartPunctuation) -> Self {
         EventQuoteConverter {
-            enabled,
+            smart_punctuation,
             convert_text: true,
         }
     }
 
     fn convert<'a>(&mut self, event: Event<'a>) -> Event<'a> {
-        if !self.enabled {
+        if self.smart_punctuation == SmartPunctuation::default() {
             return event;
         }
 
@@ -218,7 +398,17 @@ impl EventQuoteConverter {
                 event
             }
             Event::Text(ref text) if self.convert_text => {
-                Event::Text(CowStr::from(convert_quotes_to_curly(text)))
+                let mut text = text.to_string();
+                if self.smart_punctuation.quotes {
+                    text = convert_quotes_to_curly(&text);
+                }
+                if self.smart_punctuation.dashes {
+                    text = text.replace("---", "—").replace("--", "–");
+                }
+                if self.smart_punctuation.ellipses {
+                    text = text.replace("...", "…");
+                }
+                Event::Text(CowStr::from(text))
             }
             _ => event,
         }
@@ -243,6 +433,102 @@ fn clean_codeblock_headers(event: Event<'_>) -> Event<'_> {
     }
 }
 
+/// The text of a Rust code block, with and without its hidden lines.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HiddenLines {
+    /// The code shown to readers, without the hidden lines.
+    pub visible: String,
+    /// The complete code, as used when the block is copied or run.
+    pub full: String,
+}
+
+/// Split the contents of a fenced code block into its visible and full text.
+///
+/// Like rustdoc, a line starting with `# ` (or a lone `#`) is hidden but still
+/// part of the code, `##` escapes a line that should start with a `#`, and
+/// attributes such as `#[derive(...)]` or `#![allow(...)]` are left alone.
+/// Only blocks whose info string starts with `rust` have hidden lines, `None`
+/// is returned for everything else.
+pub fn split_hidden_lines(info: &str, content: &str) -> Option<HiddenLines> {
+    let lang = info.split(|c: char| c == ',' || c.is_whitespace()).next();
+    if lang != Some("rust") {
+        return None;
+    }
+
+    let mut visible = String::with_capacity(content.len());
+    let mut full = String::with_capacity(content.len());
+    for line in content.lines() {
+        let (hidden, text) = parse_hidden_line(line);
+        if !hidden {
+            visible.push_str(&text);
+            visible.push('\n');
+        }
+        full.push_str(&text);
+        full.push('\n');
+    }
+
+    Some(HiddenLines { visible, full })
+}
+
+/// Remove the fenced code blocks which are annotated with a `feature=<name>`
+/// in their info string (e.g. ```` ```rust,feature=advanced ````) when that
+/// feature isn't in `enabled`. A block naming several features is only kept
+/// if all of them are enabled, and blocks without any are always kept.
+pub fn remove_disabled_code_blocks<'a>(content: &'a str, enabled: &[String]) -> Cow<'a, str> {
+    let mut disabled = Vec::new();
+    for (event, range) in Parser::new(content).into_offset_iter() {
+        if let Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info))) = event {
+            let is_disabled = info
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter_map(|attr| attr.strip_prefix("feature="))
+                .any(|feature| !enabled.iter().any(|enabled| enabled == feature));
+            if is_disabled {
+                disabled.push(range);
+            }
+        }
+    }
+
+    if disabled.is_empty() {
+        return Cow::Borrowed(content);
+    }
+
+    let mut kept = String::with_capacity(content.len());
+    let mut previous_end = 0;
+    for range in disabled {
+        kept.push_str(&content[previous_end..range.start]);
+        // The block's range stops before the closing fence's line break.
+        previous_end = range.end;
+        if content[previous_end..].starts_with('\n') {
+            previous_end += 1;
+        }
+    }
+    kept.push_str(&content[previous_end..]);
+    Cow::Owned(kept)
+}
+
+/// Work out whether a line of Rust code should be hidden, returning the line's
+/// text with any hiding or escaping `#` removed.
+pub(crate) fn parse_hidden_line(line: &str) -> (bool, Cow<'_, str>) {
+    lazy_static! {
+        static ref BORING_LINES_REGEX: Regex = Regex::new(r"^(\s*)#(.?)(.*)$").unwrap();
+    }
+
+    if let Some(caps) = BORING_LINES_REGEX.captures(line) {
+        if &caps[2] == "#" {
+            return (false, format!("{}#{}", &caps[1], &caps[3]).into());
+        } else if &caps[2] != "!" && &caps[2] != "[" {
+            let text = if &caps[2] == " " {
+                format!("{}{}", &caps[1], &caps[3])
+            } else {
+                format!("{}{}{}", &caps[1], &caps[2], &caps[3])
+            };
+            return (true, text.into());
+        }
+    }
+
+    (false, line.into())
+}
+
 fn convert_quotes_to_curly(original_text: &str) -> String {
     // We'll consider the start to be "whitespace".
     let mut preceded_by_whitespace = true;
@@ -297,6 +583,63 @@ mod tests {
             );
         }
 
+        #[test]
+        fn markdown_extensions_can_be_disabled() {
+            use super::super::{markdown_options, render_markdown_with_options};
+            use crate::config::{Markdown, SmartPunctuation};
+
+            let input = "| a | b |\n|---|---|\n| 1 | 2 |\n";
+
+            let with_tables = markdown_options(&Markdown::default());
+            let got =
+                render_markdown_with_options(input, SmartPunctuation::default(), None, with_tables);
+            assert!(got.starts_with("<table>"));
+            assert!(got.contains("<td>1</td>"));
+
+            let without_tables = markdown_options(&Markdown {
+                tables: false,
+                ..Default::default()
+            });
+            let got = render_markdown_with_options(
+                input,
+                SmartPunctuation::default(),
+                None,
+                without_tables,
+            );
+            assert!(!got.contains("<table>"));
+            assert!(got.starts_with("<p>| a | b |"));
+        }
+
+        #[test]
+        fn punctuation_can_be_made_smart_separately() {
+            use super::super::{markdown_options, render_markdown_with_options};
+            use crate::config::{Markdown, SmartPunctuation};
+
+            let input = "'Wait' -- or --- \"stop\"... `a -- b`";
+            let render = |smart_punctuation| {
+                let options = markdown_options(&Markdown::default());
+                render_markdown_with_options(input, smart_punctuation, None, options)
+            };
+
+            let dashes = SmartPunctuation {
+                dashes: true,
+                ..Default::default()
+            };
+            assert_eq!(
+                render(dashes),
+                "<p>'Wait' – or — &quot;stop&quot;... <code>a -- b</code></p>\n"
+            );
+
+            let ellipses = SmartPunctuation {
+                ellipses: true,
+                ..Default::default()
+            };
+            assert_eq!(
+                render(ellipses),
+                "<p>'Wait' -- or --- &quot;stop&quot;… <code>a -- b</code></p>\n"
+            );
+        }
+
         #[test]
         fn it_can_adjust_markdown_links() {
             assert_eq!(
@@ -406,8 +749,54 @@ more text with spaces
         }
     }
 
+    mod footnote_backrefs {
+        use super::super::render_markdown;
+
+        #[test]
+        fn every_reference_gets_a_back_link() {
+            let input = "One[^note] and two[^note].\n\n[^note]: The note.\n";
+
+            let got = render_markdown(input, false);
+
+            assert!(got.contains(
+                r##"One<sup class="footnote-reference" id="fr-note-1"><a href="#note">1</a></sup>"##
+            ));
+            assert!(got.contains(
+                r##"two<sup class="footnote-reference" id="fr-note-2"><a href="#note">1</a></sup>"##
+            ));
+            assert!(got.contains(
+                "<p>The note. \
+                 <a href=\"#fr-note-1\" class=\"footnote-backref\">↩</a> \
+                 <a href=\"#fr-note-2\" class=\"footnote-backref\">↩</a></p>\n</div>\n"
+            ));
+            assert_eq!(got.matches("footnote-backref").count(), 2);
+        }
+
+        #[test]
+        fn unreferenced_footnotes_are_left_alone() {
+            let got = render_markdown("[^lonely]: Nobody links here.\n", false);
+
+            assert!(got.contains(r#"<div class="footnote-definition" id="lonely">"#));
+            assert!(!got.contains("footnote-backref"));
+        }
+    }
+
     mod html_munging {
-        use super::super::{id_from_content, normalize_id};
+        use super::super::{
+            id_from_content, id_from_content_with_style, normalize_id, percent_encode_url,
+            AnchorStyle,
+        };
+
+        #[test]
+        fn it_percent_encodes_urls() {
+            assert_eq!(percent_encode_url("My Chapter.html"), "My%20Chapter.html");
+            assert_eq!(
+                percent_encode_url("nested/100% done.html#a heading/part"),
+                "nested/100%25%20done.html#a%20heading%2Fpart"
+            );
+            assert_eq!(percent_encode_url("café.html"), "caf%C3%A9.html");
+            assert_eq!(percent_encode_url("plain/path.html"), "plain/path.html");
+        }
 
         #[test]
         fn it_generates_anchors() {
@@ -419,6 +808,27 @@ more text with spaces
             assert_eq!(id_from_content("## `Code` title"), "code-title");
         }
 
+        #[test]
+        fn it_generates_anchors_in_other_styles() {
+            let underscores = AnchorStyle {
+                separator: '_',
+                ..Default::default()
+            };
+            assert_eq!(
+                id_from_content_with_style("Hello World", underscores),
+                "hello_world"
+            );
+
+            let preserve_case = AnchorStyle {
+                lowercase: false,
+                ..Default::default()
+            };
+            assert_eq!(
+                id_from_content_with_style("Hello World", preserve_case),
+                "Hello-World"
+            );
+        }
+
         #[test]
         fn it_generates_anchors_from_non_ascii_initial() {
             assert_eq!(
@@ -451,6 +861,72 @@ more text with spaces
         }
     }
 
+    mod split_hidden_lines {
+        use super::super::{split_hidden_lines, HiddenLines};
+
+        #[test]
+        fn hidden_lines_are_only_in_the_full_text() {
+            let code =
+                "# use std::collections::HashMap;\nlet map: HashMap<u8, u8> = HashMap::new();\n";
+
+            let got = split_hidden_lines("rust", code).unwrap();
+
+            assert_eq!(
+                got,
+                HiddenLines {
+                    visible: "let map: HashMap<u8, u8> = HashMap::new();\n".to_string(),
+                    full: code.replacen("# ", "", 1),
+                }
+            );
+        }
+
+        #[test]
+        fn attributes_and_escaped_lines_stay_visible() {
+            let code = "#![allow(unused)]\n#[derive(Debug)]\nstruct Foo;\n##[doc = \"x\"]\n#\n";
+
+            let got = split_hidden_lines("rust,editable", code).unwrap();
+
+            let expected = "#![allow(unused)]\n#[derive(Debug)]\nstruct Foo;\n#[doc = \"x\"]\n";
+            assert_eq!(got.visible, expected);
+            assert_eq!(got.full, format!("{}\n", expected));
+        }
+
+        #[test]
+        fn only_rust_blocks_have_hidden_lines() {
+            assert!(split_hidden_lines("python", "# a comment\n").is_none());
+            assert!(split_hidden_lines("", "# a heading\n").is_none());
+            assert!(split_hidden_lines("rusty", "# a comment\n").is_none());
+        }
+    }
+
+    mod remove_disabled_code_blocks {
+        use super::super::remove_disabled_code_blocks;
+
+        const CONTENT: &str =
+            "Intro\n\n```rust,feature=advanced\nadvanced();\n```\n\n```rust\nbasic();\n```\n";
+
+        #[test]
+        fn blocks_for_disabled_features_are_removed() {
+            let got = remove_disabled_code_blocks(CONTENT, &[]);
+            assert_eq!(got, "Intro\n\n\n```rust\nbasic();\n```\n");
+        }
+
+        #[test]
+        fn blocks_for_enabled_features_are_kept() {
+            let enabled = vec![String::from("advanced")];
+            assert_eq!(remove_disabled_code_blocks(CONTENT, &enabled), CONTENT);
+        }
+
+        #[test]
+        fn every_feature_of_a_block_must_be_enabled() {
+            let content = "```text feature=a feature=b\nboth\n```\n";
+            let only_a = vec![String::from("a")];
+            assert_eq!(remove_disabled_code_blocks(content, &only_a), "");
+            let both = vec![String::from("a"), String::from("b")];
+            assert_eq!(remove_disabled_code_blocks(content, &both), content);
+        }
+    }
+
     mod convert_quotes_to_curly {
         use super::super::convert_quotes_to_curly;
 
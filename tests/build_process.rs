@@ -1,12 +1,15 @@
 mod dummy_book;
 
 use crate::dummy_book::DummyBook;
-use mdbook::book::Book;
+use mdbook::book::{Book, Summary};
 use mdbook::config::Config;
 use mdbook::errors::*;
 use mdbook::preprocess::{Preprocessor, PreprocessorContext};
 use mdbook::renderer::{RenderContext, Renderer};
+use mdbook::utils::diagnostics::{self, DiagnosticCollector};
 use mdbook::MDBook;
+use std::fs;
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 
 struct Spy(Arc<Mutex<Inner>>);
@@ -76,3 +79,346 @@ fn mdbook_runs_renderers() {
     let inner = spy.lock().unwrap();
     assert_eq!(inner.run_count, 1);
 }
+
+#[test]
+#[cfg(not(windows))]
+fn chapter_check_command_reports_failing_chapters() {
+    let temp = DummyBook::new().build().unwrap();
+    let conclusion = temp.path().join("src/conclusion.md");
+    let content = std::fs::read_to_string(&conclusion).unwrap();
+    std::fs::write(&conclusion, format!("{}\nTODO: finish this\n", content)).unwrap();
+
+    let mut cfg = Config::default();
+    cfg.set(
+        "build.chapter-check-command",
+        r#"sh -c 'if grep -q TODO "$0"; then echo "unfinished chapter" >&2; exit 1; fi' {file}"#,
+    )
+    .unwrap();
+
+    let md = MDBook::load_with_config(temp.path(), cfg).unwrap();
+    let err = format!("{:?}", md.build().unwrap_err());
+
+    assert!(err.contains("failed for 1 chapter(s)"), "{}", err);
+    assert!(err.contains("Conclusion"), "{}", err);
+    assert!(err.contains("unfinished chapter"), "{}", err);
+    assert!(!err.contains("Introduction"), "{}", err);
+}
+
+#[test]
+fn watch_paths_cover_the_book_sources_and_assets() {
+    let temp = DummyBook::new().build().unwrap();
+    let mut cfg = Config::default();
+    cfg.set("output.html.additional-css", vec!["custom.css"])
+        .unwrap();
+    cfg.set(
+        "output.html.additional-js",
+        vec!["custom.js", "https://example.com/remote.js"],
+    )
+    .unwrap();
+    let md = MDBook::load_with_config(temp.path(), cfg).unwrap();
+
+    let paths = md.watch_paths();
+    let root = temp.path();
+    for expected in &[
+        root.join("book.toml"),
+        root.join("src/SUMMARY.md"),
+        root.join("src/intro.md"),
+        root.join("src/first/nested.md"),
+        root.join("theme"),
+        root.join("custom.css"),
+        root.join("custom.js"),
+    ] {
+        assert!(
+            paths.contains(expected),
+            "{} is missing",
+            expected.display()
+        );
+    }
+    assert!(!paths.iter().any(|p| p.to_string_lossy().contains("://")));
+}
+
+#[test]
+fn changed_files_are_mapped_to_the_chapters_using_them() {
+    let temp = DummyBook::new().build().unwrap();
+    let md = MDBook::load(temp.path()).unwrap();
+    let src = temp.path().join("src");
+    let affected = |files: &[&str]| {
+        let files: Vec<_> = files.iter().map(|f| src.join(f)).collect();
+        md.chapters_affected_by(&files).unwrap()
+    };
+
+    assert_eq!(
+        affected(&["intro.md"]),
+        Some(vec![PathBuf::from("intro.md")])
+    );
+    assert_eq!(
+        affected(&["first/nested-test.rs", "second.md"]),
+        Some(vec![
+            PathBuf::from("first/nested.md"),
+            PathBuf::from("second.md")
+        ])
+    );
+    assert_eq!(
+        affected(&["example.rs"]),
+        Some(vec![PathBuf::from("second.md")])
+    );
+    assert_eq!(affected(&["SUMMARY.md"]), None);
+    assert_eq!(affected(&["not-a-chapter.png"]), None);
+}
+
+#[test]
+fn only_changed_chapters_are_rerendered() {
+    let temp = DummyBook::new().build().unwrap();
+    let md = MDBook::load(temp.path()).unwrap();
+    md.build().unwrap();
+
+    let book_dir = temp.path().join("book");
+    fs::write(book_dir.join("intro.html"), "stale").unwrap();
+    fs::write(book_dir.join("second.html"), "stale").unwrap();
+    fs::write(
+        temp.path().join("src/second.md"),
+        "# Second Chapter\n\nUpdated\n",
+    )
+    .unwrap();
+
+    let md = MDBook::load(temp.path()).unwrap();
+    md.build_chapters(&[PathBuf::from("second.md")]).unwrap();
+
+    assert_eq!(
+        fs::read_to_string(book_dir.join("intro.html")).unwrap(),
+        "stale"
+    );
+    let second = fs::read_to_string(book_dir.join("second.html")).unwrap();
+    assert!(second.contains("Updated"));
+}
+
+#[test]
+fn deny_warnings_fails_a_build_with_warnings() {
+    let temp = DummyBook::new().build().unwrap();
+    fs::write(temp.path().join("book.toml"), "title = \"Legacy\"\n").unwrap();
+
+    let build = |extra_args: &[&str]| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_mdbook"))
+            .arg("build")
+            .args(extra_args)
+            .arg(temp.path())
+            .output()
+            .unwrap()
+    };
+
+    assert!(build(&[]).status.success());
+
+    let output = build(&["--deny-warnings"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Warnings are denied"), "{}", stderr);
+    assert!(stderr.contains("legacy book.toml format"), "{}", stderr);
+}
+
+#[test]
+fn deny_warnings_in_book_toml_applies_to_the_library() {
+    struct Discard;
+    impl log::Log for Discard {
+        fn enabled(&self, _: &log::Metadata<'_>) -> bool {
+            false
+        }
+        fn log(&self, _: &log::Record<'_>) {}
+        fn flush(&self) {}
+    }
+    // Other tests may have installed it already.
+    let _ = log::set_boxed_logger(Box::new(DiagnosticCollector::new(Discard)));
+    log::set_max_level(log::LevelFilter::Warn);
+
+    let temp = tempfile::Builder::new().prefix("book").tempdir().unwrap();
+    fs::write(
+        temp.path().join("book.toml"),
+        "[build]\ndeny-warnings = true\n",
+    )
+    .unwrap();
+    fs::create_dir(temp.path().join("src")).unwrap();
+    fs::write(
+        temp.path().join("src/SUMMARY.md"),
+        "# Summary\n\n- [Intro](intro.md)\n",
+    )
+    .unwrap();
+    fs::write(
+        temp.path().join("src/intro.md"),
+        "{{#playpen main.rs}}\n\n{{#include missing.md}}\n",
+    )
+    .unwrap();
+    fs::write(temp.path().join("src/main.rs"), "fn main() {}\n").unwrap();
+
+    let start = diagnostics::count();
+    let mut md = MDBook::load(temp.path()).unwrap();
+    let err = format!("{:?}", md.build().unwrap_err());
+    assert!(err.contains("Warnings are denied"), "{}", err);
+    assert!(err.contains("{{#playground}}"), "{}", err);
+
+    let missing = diagnostics::since(start)
+        .into_iter()
+        .find(|d| d.message.contains("missing.md"))
+        .unwrap();
+    assert_eq!(missing.file, Some(temp.path().join("src/intro.md")));
+    assert_eq!(missing.line, Some(3));
+
+    let err = format!("{:?}", md.test(vec![]).unwrap_err());
+    assert!(err.contains("Warnings are denied"), "{}", err);
+}
+
+/// A book in English and French, each with an intro chapter saying hello.
+fn multilingual_book() -> tempfile::TempDir {
+    let temp = tempfile::Builder::new().prefix("book").tempdir().unwrap();
+    fs::write(
+        temp.path().join("book.toml"),
+        "[book]\nmultilingual = true\nlanguages = [\"en\", \"fr\"]\n",
+    )
+    .unwrap();
+    for (language, text) in &[("en", "Hello"), ("fr", "Bonjour")] {
+        let src = temp.path().join("src").join(language);
+        fs::create_dir_all(&src).unwrap();
+        fs::write(src.join("SUMMARY.md"), "# Summary\n\n- [Intro](intro.md)\n").unwrap();
+        fs::write(src.join("intro.md"), format!("# {}\n", text)).unwrap();
+    }
+    temp
+}
+
+#[test]
+fn multilingual_books_are_built_per_language() {
+    let temp = multilingual_book();
+    let root = temp.path();
+
+    let md = MDBook::load(root).unwrap();
+    assert_eq!(md.translations.len(), 2);
+    md.build().unwrap();
+
+    let en = fs::read_to_string(root.join("book/en/index.html")).unwrap();
+    assert!(en.contains("Hello"));
+    assert!(en.contains(r#"lang="en""#));
+    let fr = fs::read_to_string(root.join("book/fr/index.html")).unwrap();
+    assert!(fr.contains("Bonjour"));
+    assert!(fr.contains(r#"lang="fr""#));
+    assert!(!root.join("book/index.html").exists());
+
+    let watched = md.watch_paths();
+    assert!(watched.contains(&root.join("src/fr/SUMMARY.md")));
+    assert!(watched.contains(&root.join("src/fr/intro.md")));
+
+    let summary = Summary::default();
+    let err = match MDBook::load_with_config_and_summary(root, md.config.clone(), summary) {
+        Ok(_) => panic!("a multilingual book was loaded with one summary"),
+        Err(err) => err,
+    };
+    assert!(err.to_string().contains("multilingual"), "{}", err);
+}
+
+#[test]
+#[cfg(not(windows))]
+fn chapter_checks_cover_every_language() {
+    let temp = multilingual_book();
+    let mut md = MDBook::load(temp.path()).unwrap();
+    md.config
+        .set(
+            "build.chapter-check-command",
+            r#"sh -c 'grep -q Hello "$0"' {file}"#,
+        )
+        .unwrap();
+
+    let err = format!("{:?}", md.build().unwrap_err());
+
+    assert!(err.contains("failed for 1 chapter(s)"), "{}", err);
+    assert!(err.contains("src/fr/intro.md"), "{}", err);
+}
+
+#[test]
+fn log_records_can_be_printed_as_json() {
+    let temp = DummyBook::new().build().unwrap();
+
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_mdbook"))
+        .arg("build")
+        .arg("--log-format=json")
+        .arg(temp.path())
+        .env_remove("RUST_LOG")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let records: Vec<serde_json::Value> = stderr
+        .lines()
+        .map(|line| serde_json::from_str(line).expect(line))
+        .collect();
+    assert!(!records.is_empty());
+    for record in &records {
+        for field in &["timestamp", "level", "module", "message"] {
+            assert!(
+                record[field].is_string(),
+                "{} is missing: {}",
+                field,
+                record
+            );
+        }
+    }
+    assert!(records
+        .iter()
+        .any(|record| record["message"] == "Book building has started"));
+}
+
+#[test]
+fn diagnostics_can_be_printed_as_json() {
+    let temp = DummyBook::new().build().unwrap();
+    fs::write(
+        temp.path().join("book.toml"),
+        "[output.html.playpen]\neditable = true\n",
+    )
+    .unwrap();
+
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_mdbook"))
+        .arg("build")
+        .arg("--message-format=json")
+        .arg(temp.path())
+        .env_remove("RUST_LOG")
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let records: Vec<serde_json::Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).expect(line))
+        .collect();
+    assert!(
+        records.iter().any(|record| record["reason"] == "diagnostic"
+            && record["level"] == "warning"
+            && record["message"].as_str().unwrap().contains("playpen")),
+        "{}",
+        stdout
+    );
+    let summary = records.last().unwrap();
+    assert_eq!(summary["reason"], "build-finished");
+    assert_eq!(summary["success"], output.status.success());
+    assert!(summary["warnings"].as_u64().unwrap() >= 1);
+}
+
+#[test]
+fn quiet_only_logs_warnings_and_errors() {
+    let temp = DummyBook::new().build().unwrap();
+
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_mdbook"))
+        .arg("build")
+        .arg("--quiet")
+        .arg(temp.path())
+        .env_remove("RUST_LOG")
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    // The dummy book's recursive include always logs an error.
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    for line in stderr.lines() {
+        assert!(
+            line.contains("[WARN]") || line.contains("[ERROR]"),
+            "{}",
+            line
+        );
+    }
+    assert!(!stderr.contains("Book building has started"));
+}
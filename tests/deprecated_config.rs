@@ -0,0 +1,41 @@
+//! Deprecation warnings are only given once per process, so these tests have
+//! a test binary of their own, where nothing else has used a deprecated key
+//! before.
+
+use mdbook::config::Config;
+use mdbook::utils::diagnostics::{self, DiagnosticCollector};
+use std::str::FromStr;
+
+struct Discard;
+
+impl log::Log for Discard {
+    fn enabled(&self, _: &log::Metadata<'_>) -> bool {
+        false
+    }
+    fn log(&self, _: &log::Record<'_>) {}
+    fn flush(&self) {}
+}
+
+#[test]
+fn deprecated_keys_are_only_warned_about_once() {
+    log::set_boxed_logger(Box::new(DiagnosticCollector::new(Discard))).unwrap();
+    log::set_max_level(log::LevelFilter::Warn);
+    let start = diagnostics::count();
+
+    let mut cfg = Config::default();
+    cfg.set("output.html.playpen.editable", true).unwrap();
+    assert!(cfg.get("output.html.playpen.editable").is_some());
+    assert!(cfg.get("output.html.playpen.copyable").is_none());
+    Config::from_str("[output.html.playpen]\neditable = true\n").unwrap();
+
+    let warnings: Vec<_> = diagnostics::since(start)
+        .into_iter()
+        .filter(|d| d.message.contains("output.html.playpen"))
+        .collect();
+    assert_eq!(warnings.len(), 1, "{:?}", warnings);
+    assert_eq!(
+        warnings[0].message,
+        "The `output.html.playpen` config key is deprecated, please use \
+         `output.html.playground` instead"
+    );
+}
@@ -109,6 +109,58 @@ fn book_toml_isnt_required() {
     md.build().unwrap();
 }
 
+/// The generated `.gitignore` ignores the configured build directory.
+#[test]
+fn gitignore_uses_the_configured_build_dir() {
+    let temp = TempFileBuilder::new().prefix("mdbook").tempdir().unwrap();
+    let mut cfg = Config::default();
+    cfg.build.build_dir = PathBuf::from("out");
+
+    MDBook::init(temp.path())
+        .with_config(cfg)
+        .create_gitignore(true)
+        .build()
+        .unwrap();
+
+    let contents = fs::read_to_string(temp.path().join(".gitignore")).unwrap();
+    assert_eq!(contents, "out/\n");
+}
+
+/// `mdbook init --vcs hg` creates an `.hgignore` instead of a `.gitignore`,
+/// ignoring the build directory at the root of the repository.
+#[test]
+fn init_with_hgignore() {
+    let temp = TempFileBuilder::new().prefix("mdbook").tempdir().unwrap();
+
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_mdbook"))
+        .args(vec!["init", "--vcs", "hg", "--force", "--title", "Test"])
+        .arg(temp.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let contents = fs::read_to_string(temp.path().join(".hgignore")).unwrap();
+    assert_eq!(contents, "syntax: rootglob\nbook/**\n");
+    assert!(!temp.path().join(".gitignore").exists());
+}
+
+/// The generated `.hgignore` ignores the configured build directory.
+#[test]
+fn hgignore_uses_the_configured_build_dir() {
+    let temp = TempFileBuilder::new().prefix("mdbook").tempdir().unwrap();
+    let mut cfg = Config::default();
+    cfg.build.build_dir = PathBuf::from("out");
+
+    MDBook::init(temp.path())
+        .with_config(cfg)
+        .create_hgignore(true)
+        .build()
+        .unwrap();
+
+    let contents = fs::read_to_string(temp.path().join(".hgignore")).unwrap();
+    assert_eq!(contents, "syntax: rootglob\nout/**\n");
+}
+
 #[test]
 fn copy_theme() {
     let temp = TempFileBuilder::new().prefix("mdbook").tempdir().unwrap();
@@ -6,16 +6,21 @@ mod dummy_book;
 use crate::dummy_book::{assert_contains_strings, assert_doesnt_contain_strings, DummyBook};
 
 use anyhow::Context;
+use flate2::read::GzDecoder;
+use mdbook::book::{Book, BookItem, Chapter};
 use mdbook::config::Config;
 use mdbook::errors::*;
+use mdbook::preprocess::{Preprocessor, PreprocessorContext};
+use mdbook::theme;
 use mdbook::utils::fs::write_file;
 use mdbook::MDBook;
 use select::document::Document;
 use select::predicate::{Class, Name, Predicate};
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::ffi::OsStr;
-use std::fs;
-use std::io::Write;
+use std::fs::{self, File};
+use std::io::{Read, Write};
 use std::path::{Component, Path, PathBuf};
 use tempfile::Builder as TempFileBuilder;
 use walkdir::{DirEntry, WalkDir};
@@ -463,6 +468,476 @@ fn theme_dir_overrides_work_correctly() {
     dummy_book::assert_contains_strings(built_index, &["This is a modified index.hbs!"]);
 }
 
+#[test]
+fn custom_theme_only_needs_the_overridden_files() {
+    let temp = DummyBook::new().build().unwrap();
+    let custom_chrome = b"/* custom chrome */\n";
+    write_file(
+        &temp.path().join("custom-theme/css"),
+        "chrome.css",
+        custom_chrome,
+    )
+    .unwrap();
+
+    let mut md = MDBook::load(temp.path()).unwrap();
+    md.config.set("output.html.theme", "custom-theme").unwrap();
+    md.build().unwrap();
+
+    let book_dir = md.build_dir_for("html");
+    let read = |file: &str| fs::read(book_dir.join(file)).unwrap();
+    assert_eq!(read("css/chrome.css"), custom_chrome);
+    assert_eq!(read("css/general.css"), theme::GENERAL_CSS);
+    assert_eq!(read("css/variables.css"), theme::VARIABLES_CSS);
+    assert_eq!(read("book.js"), theme::JS);
+    assert_eq!(read("favicon.svg"), theme::FAVICON_SVG);
+    assert_contains_strings(book_dir.join("index.html"), &["<!DOCTYPE HTML>"]);
+}
+
+#[test]
+fn chapters_have_open_graph_tags() {
+    let temp = DummyBook::new().build().unwrap();
+    let mut md = MDBook::load(temp.path()).unwrap();
+    md.config
+        .set("output.html.site-url", "https://example.com/book/")
+        .unwrap();
+    md.config
+        .set("output.html.og-image", "images/preview.png")
+        .unwrap();
+    md.build().unwrap();
+
+    let first_index = temp.path().join("book/first/index.html");
+    assert_contains_strings(
+        &first_index,
+        &[
+            r#"<meta property="og:title" content="First Chapter">"#,
+            r#"<meta property="og:description" content="more text.">"#,
+            r#"<meta property="og:url" content="https://example.com/book/first/index.html">"#,
+            r#"<meta property="og:image" content="https://example.com/book/images/preview.png">"#,
+            r#"<meta name="twitter:card" content="summary_large_image">"#,
+        ],
+    );
+}
+
+#[test]
+fn open_graph_tags_without_a_site_url() {
+    let temp = DummyBook::new().build().unwrap();
+    let md = MDBook::load(temp.path()).unwrap();
+    md.build().unwrap();
+
+    let first_index = temp.path().join("book/first/index.html");
+    assert_contains_strings(
+        &first_index,
+        &[
+            r#"<meta property="og:title" content="First Chapter">"#,
+            r#"<meta name="twitter:card" content="summary">"#,
+        ],
+    );
+    assert_doesnt_contain_strings(&first_index, &["og:url", "og:image"]);
+}
+
+#[test]
+fn default_sidebar_visibility() {
+    let temp = DummyBook::new().build().unwrap();
+    let md = MDBook::load(temp.path()).unwrap();
+    md.build().unwrap();
+
+    let index = temp.path().join("book/index.html");
+    assert_contains_strings(&index, &[r#"class="sidebar-visible no-js"#]);
+
+    let mut md = MDBook::load(temp.path()).unwrap();
+    md.config
+        .set("output.html.default-sidebar", "hidden")
+        .unwrap();
+    md.build().unwrap();
+
+    assert_contains_strings(
+        &index,
+        &[
+            r#"class="sidebar-hidden no-js"#,
+            "sidebar = sidebar || 'hidden';",
+        ],
+    );
+}
+
+#[test]
+fn external_summary_links_are_rendered_in_the_sidebar() {
+    let temp = DummyBook::new().build().unwrap();
+    let summary = temp.path().join("src/SUMMARY.md");
+    let content = fs::read_to_string(&summary).unwrap();
+    fs::write(
+        &summary,
+        content.replace(
+            "[Conclusion](conclusion.md)",
+            "[API Reference](https://docs.rs/mdbook/?a&b)",
+        ),
+    )
+    .unwrap();
+
+    let md = MDBook::load(temp.path()).unwrap();
+    md.build().unwrap();
+
+    assert_contains_strings(
+        temp.path().join("book/index.html"),
+        &[r#"<a href="https://docs.rs/mdbook/?a&amp;b">API Reference</a>"#],
+    );
+    assert!(!temp.path().join("book/conclusion.html").exists());
+}
+
+#[test]
+fn external_summary_links_are_kept_without_drafts() {
+    let temp = DummyBook::new().build().unwrap();
+    let summary = temp.path().join("src/SUMMARY.md");
+    let content = fs::read_to_string(&summary).unwrap();
+    fs::write(
+        &summary,
+        content.replace(
+            "[Conclusion](conclusion.md)",
+            "[API Reference](https://docs.rs/mdbook/)\n\n[Unwritten]()",
+        ),
+    )
+    .unwrap();
+
+    let mut md = MDBook::load(temp.path()).unwrap();
+    md.config.set("build.include-drafts", false).unwrap();
+    let md = MDBook::load_with_config(temp.path(), md.config).unwrap();
+    md.build().unwrap();
+
+    let index = temp.path().join("book/index.html");
+    assert_contains_strings(
+        &index,
+        &[r#"<a href="https://docs.rs/mdbook/">API Reference</a>"#],
+    );
+    assert_doesnt_contain_strings(&index, &["Unwritten"]);
+}
+
+#[test]
+fn sidebar_depth_can_be_limited() {
+    let temp = DummyBook::new().build().unwrap();
+    let mut md = MDBook::load(temp.path()).unwrap();
+    md.config.set("output.html.toc-max-depth", 1).unwrap();
+    md.build().unwrap();
+
+    let index = temp.path().join("book/index.html");
+    assert_contains_strings(&index, &[r#"href="first/index.html""#]);
+    assert_doesnt_contain_strings(
+        &index,
+        &[r#"<ol class="section">"#, r#"href="first/nested.html""#],
+    );
+    assert!(temp.path().join("book/first/nested.html").exists());
+}
+
+#[test]
+fn chapter_links_are_percent_encoded() {
+    let temp = DummyBook::new().build().unwrap();
+    let summary = temp.path().join("src/SUMMARY.md");
+    let content = fs::read_to_string(&summary).unwrap();
+    fs::write(
+        &summary,
+        content.replace("[Conclusion](conclusion.md)", "[Conclusion](<The End.md>)"),
+    )
+    .unwrap();
+    fs::rename(
+        temp.path().join("src/conclusion.md"),
+        temp.path().join("src/The End.md"),
+    )
+    .unwrap();
+
+    let md = MDBook::load(temp.path()).unwrap();
+    md.build().unwrap();
+
+    assert!(temp.path().join("book/The End.html").exists());
+    assert_contains_strings(
+        temp.path().join("book/index.html"),
+        &[r#"<a href="The%20End.html">"#],
+    );
+    assert_contains_strings(
+        temp.path().join("book/second/nested.html"),
+        &[r#"href="../The%20End.html""#],
+    );
+}
+
+#[test]
+fn prev_next_links_skip_separators() {
+    let temp = DummyBook::new().build().unwrap();
+    fs::write(
+        temp.path().join("src/SUMMARY.md"),
+        "# Summary\n\n[Introduction](intro.md)\n\n---\n\n\
+         - [First Chapter](first/index.md)\n\
+         - [Second Chapter](second.md)\n\n---\n\n\
+         [Conclusion](conclusion.md)\n",
+    )
+    .unwrap();
+
+    let md = MDBook::load(temp.path()).unwrap();
+    md.build().unwrap();
+
+    let chain = [
+        (None, "intro.html", Some("first/index.html")),
+        (Some("intro.html"), "first/index.html", Some("second.html")),
+        (
+            Some("first/index.html"),
+            "second.html",
+            Some("conclusion.html"),
+        ),
+        (Some("second.html"), "conclusion.html", None),
+    ];
+    for (prev, page, next) in &chain {
+        let path_to_root = if page.contains('/') { "../" } else { "" };
+        let html = fs::read_to_string(temp.path().join("book").join(page)).unwrap();
+
+        match prev {
+            Some(prev) => assert!(
+                html.contains(&format!(r#"rel="prev" href="{}{}""#, path_to_root, prev)),
+                "{} should link back to {}",
+                page,
+                prev
+            ),
+            None => assert!(!html.contains(r#"rel="prev""#), "{}", page),
+        }
+        match next {
+            Some(next) => assert!(
+                html.contains(&format!(r#"rel="next" href="{}{}""#, path_to_root, next)),
+                "{} should link on to {}",
+                page,
+                next
+            ),
+            None => assert!(!html.contains(r#"rel="next""#), "{}", page),
+        }
+    }
+}
+
+#[test]
+fn custom_favicon_from_config() {
+    let temp = DummyBook::new().build().unwrap();
+    let custom_png = b"\x89PNG not really a png";
+    fs::create_dir(temp.path().join("assets")).unwrap();
+    fs::write(temp.path().join("assets/icon.png"), custom_png).unwrap();
+
+    let mut md = MDBook::load(temp.path()).unwrap();
+    md.config
+        .set("output.html.favicon-png", "assets/icon.png")
+        .unwrap();
+    md.build().unwrap();
+
+    let favicon = fs::read(temp.path().join("book/favicon.png")).unwrap();
+    assert_eq!(favicon, custom_png);
+    assert!(!temp.path().join("book/favicon.svg").exists());
+    assert_contains_strings(
+        temp.path().join("book/index.html"),
+        &[r#"<link rel="shortcut icon" href="favicon.png">"#],
+    );
+    assert_doesnt_contain_strings(temp.path().join("book/index.html"), &["favicon.svg"]);
+}
+
+#[test]
+fn missing_custom_favicon_is_an_error() {
+    let temp = DummyBook::new().build().unwrap();
+
+    let mut md = MDBook::load(temp.path()).unwrap();
+    md.config
+        .set("output.html.favicon-svg", "assets/missing.svg")
+        .unwrap();
+
+    assert!(md.build().is_err());
+}
+
+#[test]
+fn raw_search_index_has_the_requested_fields() {
+    let temp = DummyBook::new().build().unwrap();
+    let mut md = MDBook::load(temp.path()).unwrap();
+    md.config
+        .set("output.html.search-index.enable", true)
+        .unwrap();
+    md.config
+        .set(
+            "output.html.search-index.fields",
+            vec!["title", "path", "section"],
+        )
+        .unwrap();
+    md.build().unwrap();
+
+    let index = fs::read_to_string(temp.path().join("book/search-index.json")).unwrap();
+    let index: serde_json::Value = serde_json::from_str(&index).unwrap();
+    let entries = index.as_array().unwrap();
+
+    let chapters: Vec<_> = md
+        .book
+        .iter()
+        .filter_map(|item| match item {
+            BookItem::Chapter(ch) if ch.path.is_some() => Some(ch),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(entries.len(), chapters.len());
+    for entry in entries {
+        let entry = entry.as_object().unwrap();
+        assert_eq!(entry.len(), 3);
+        assert!(entry.contains_key("section"));
+        assert!(chapters
+            .iter()
+            .any(|ch| entry["title"] == serde_json::json!(ch.name)));
+    }
+
+    let nested = entries
+        .iter()
+        .find(|entry| entry["path"] == "first/nested.html")
+        .unwrap();
+    assert_eq!(nested["title"], "Nested Chapter");
+    assert_eq!(nested["section"], "1.1.");
+    assert_eq!(entries[0]["section"], serde_json::Value::Null);
+}
+
+#[test]
+fn raw_search_index_is_opt_in() {
+    let temp = DummyBook::new().build().unwrap();
+    let md = MDBook::load(temp.path()).unwrap();
+    md.build().unwrap();
+
+    assert!(!temp.path().join("book/search-index.json").exists());
+}
+
+#[test]
+fn additional_css_and_js_urls_are_linked_not_copied() {
+    let temp = DummyBook::new().build().unwrap();
+    let mut md = MDBook::load(temp.path()).unwrap();
+    md.config
+        .set(
+            "output.html.additional-css",
+            vec!["https://example.com/style.css"],
+        )
+        .unwrap();
+    md.config
+        .set(
+            "output.html.additional-js",
+            vec!["http://example.com/app.js"],
+        )
+        .unwrap();
+    md.build().unwrap();
+
+    let index_html = temp.path().join("book/index.html");
+    assert_contains_strings(
+        &index_html,
+        &[
+            r#"<link rel="stylesheet" href="https://example.com/style.css">"#,
+            r#"<script type="text/javascript" src="http://example.com/app.js"></script>"#,
+        ],
+    );
+    assert_doesnt_contain_strings(&index_html, &["./https:", "./http:"]);
+    assert!(!temp.path().join("book/https:").exists());
+}
+
+#[test]
+fn precompress_writes_gz_files_for_text_assets() {
+    let temp = DummyBook::new().build().unwrap();
+    let mut md = MDBook::load(temp.path()).unwrap();
+    md.config.set("output.html.precompress", true).unwrap();
+    md.build().unwrap();
+
+    let book = temp.path().join("book");
+    assert!(book.join("css/chrome.css.gz").exists());
+    let gz = File::open(book.join("index.html.gz")).unwrap();
+    let mut decompressed = String::new();
+    GzDecoder::new(gz)
+        .read_to_string(&mut decompressed)
+        .unwrap();
+    assert_eq!(
+        decompressed,
+        fs::read_to_string(book.join("index.html")).unwrap()
+    );
+    assert!(!book
+        .join("fonts/open-sans-v17-all-charsets-300.woff2.gz")
+        .exists());
+    assert!(!book
+        .join("FontAwesome/fonts/fontawesome-webfont.woff.gz")
+        .exists());
+}
+
+#[test]
+fn precompress_is_opt_in() {
+    let temp = DummyBook::new().build().unwrap();
+    let md = MDBook::load(temp.path()).unwrap();
+    md.build().unwrap();
+
+    assert!(!temp.path().join("book/index.html.gz").exists());
+}
+
+#[test]
+fn additional_static_files_are_copied() {
+    let temp = DummyBook::new().build().unwrap();
+    fs::create_dir_all(temp.path().join("img/icons")).unwrap();
+    fs::write(temp.path().join("img/logo.png"), b"not really a png").unwrap();
+    fs::write(temp.path().join("img/icons/a.svg"), "<svg></svg>").unwrap();
+
+    let mut md = MDBook::load(temp.path()).unwrap();
+    md.config
+        .set("output.html.additional-static", vec!["img/logo.png"])
+        .unwrap();
+    md.build().unwrap();
+
+    let logo = fs::read(temp.path().join("book/img/logo.png")).unwrap();
+    assert_eq!(logo, b"not really a png");
+    assert!(!temp.path().join("book/img/icons").exists());
+
+    md.config
+        .set("output.html.additional-static", vec!["img/icons"])
+        .unwrap();
+    md.build().unwrap();
+    assert!(temp.path().join("book/img/icons/a.svg").exists());
+
+    md.config
+        .set("output.html.additional-static-prefix", "assets")
+        .unwrap();
+    md.build().unwrap();
+    assert!(temp.path().join("book/assets/img/icons/a.svg").exists());
+    assert!(!temp.path().join("book/img/icons").exists());
+}
+
+#[test]
+fn number_in_title_prefixes_numbered_chapters() {
+    let temp = DummyBook::new().build().unwrap();
+    let mut md = MDBook::load(temp.path()).unwrap();
+    md.config.set("output.html.number-in-title", true).unwrap();
+    md.build().unwrap();
+
+    let nested = temp.path().join("book/first/nested.html");
+    assert_contains_strings(&nested, &["<title>1.1. Nested Chapter</title>"]);
+    let intro = temp.path().join("book/intro.html");
+    assert_contains_strings(&intro, &["<title>Introduction</title>"]);
+}
+
+#[test]
+fn default_theme_is_rendered_as_a_class() {
+    let temp = DummyBook::new().build().unwrap();
+    let mut md = MDBook::load(temp.path()).unwrap();
+    md.config.set("output.html.default-theme", "ayu").unwrap();
+    md.build().unwrap();
+
+    let index = temp.path().join("book/index.html");
+    assert_contains_strings(
+        &index,
+        &[r#"<html lang="en" class="sidebar-visible no-js ayu">"#],
+    );
+}
+
+#[test]
+fn code_blocks_for_disabled_features_are_omitted() {
+    let temp = DummyBook::new().build().unwrap();
+    let source = temp.path().join("src/intro.md");
+    let mut content = fs::read_to_string(&source).unwrap();
+    content.push_str("\n```rust,feature=advanced\nadvanced_only();\n```\n");
+    fs::write(&source, content).unwrap();
+
+    let md = MDBook::load(temp.path()).unwrap();
+    md.build().unwrap();
+    let intro = temp.path().join("book/intro.html");
+    assert_doesnt_contain_strings(&intro, &["advanced_only"]);
+
+    let mut md = MDBook::load(temp.path()).unwrap();
+    md.config.set("build.features", vec!["advanced"]).unwrap();
+    md.build().unwrap();
+    assert_contains_strings(&intro, &["advanced_only"]);
+}
+
 #[test]
 fn no_index_for_print_html() {
     let temp = DummyBook::new().build().unwrap();
@@ -476,6 +951,331 @@ fn no_index_for_print_html() {
     assert_doesnt_contain_strings(index_html, &[r##"noindex"##]);
 }
 
+#[test]
+fn chapter_content_is_wrapped_in_chapter_classes() {
+    let temp = DummyBook::new().build().unwrap();
+    let nested = temp.path().join("src/first/nested.md");
+    let content = fs::read_to_string(&nested).unwrap();
+    fs::write(
+        &nested,
+        format!("+++\nclass = [\"wide\", \"appendix\"]\n+++\n{}", content),
+    )
+    .unwrap();
+    let md = MDBook::load(temp.path()).unwrap();
+    md.build().unwrap();
+
+    assert_contains_strings(
+        temp.path().join("book/first/nested.html"),
+        &[r#"<div class="chapter chapter-first-nested depth-1 wide appendix">"#],
+    );
+    assert_contains_strings(
+        temp.path().join("book/intro.html"),
+        &[r#"<div class="chapter chapter-intro depth-0">"#],
+    );
+}
+
+#[test]
+fn inline_css_is_embedded_in_the_head() {
+    let temp = DummyBook::new().build().unwrap();
+    fs::write(temp.path().join("tiny.css"), ".tiny { color: red; }\n").unwrap();
+    let mut cfg = Config::default();
+    cfg.set("output.html.inline-css", vec!["tiny.css"]).unwrap();
+    let md = MDBook::load_with_config(temp.path(), cfg).unwrap();
+    md.build().unwrap();
+
+    let intro = temp.path().join("book/intro.html");
+    assert_contains_strings(&intro, &["<style>\n.tiny { color: red; }\n"]);
+    assert_doesnt_contain_strings(&intro, &["tiny.css"]);
+    assert!(!temp.path().join("book/tiny.css").exists());
+}
+
+#[test]
+fn head_include_is_added_to_every_page() {
+    let temp = DummyBook::new().build().unwrap();
+    let fragment = r#"<meta name="robots" content="noindex">"#;
+    fs::write(temp.path().join("head.html"), fragment).unwrap();
+    let mut cfg = Config::default();
+    cfg.set("output.html.head-include", "head.html").unwrap();
+    let md = MDBook::load_with_config(temp.path(), cfg).unwrap();
+    md.build().unwrap();
+
+    assert_contains_strings(temp.path().join("book/intro.html"), &[fragment]);
+    assert_contains_strings(temp.path().join("book/first/nested.html"), &[fragment]);
+}
+
+#[test]
+fn anchor_ids_follow_the_configured_style() {
+    let temp = DummyBook::new().build().unwrap();
+    let mut cfg = Config::default();
+    cfg.set("output.html.anchor-separator", "_").unwrap();
+    cfg.set("output.html.anchor-lowercase", false).unwrap();
+    let md = MDBook::load_with_config(temp.path(), cfg).unwrap();
+    md.build().unwrap();
+
+    assert_contains_strings(
+        temp.path().join("book/first/nested.html"),
+        &[r##"<h1 id="Nested_Chapter"><a class="header" href="#Nested_Chapter">"##],
+    );
+}
+
+#[test]
+fn absolute_links_are_prefixed_with_the_site_base_path() {
+    let temp = DummyBook::new().build().unwrap();
+    let intro = temp.path().join("src/intro.md");
+    let content = fs::read_to_string(&intro).unwrap();
+    fs::write(
+        &intro,
+        format!(
+            "{}\n[Home](/index.html) [Rust](https://www.rust-lang.org/)\n",
+            content
+        ),
+    )
+    .unwrap();
+    let mut cfg = Config::default();
+    cfg.set("output.html.site-base-path", "/docs/").unwrap();
+    let md = MDBook::load_with_config(temp.path(), cfg).unwrap();
+    md.build().unwrap();
+
+    assert_contains_strings(
+        temp.path().join("book/intro.html"),
+        &[
+            r#"<a href="/docs/index.html">Home</a>"#,
+            r#"<a href="https://www.rust-lang.org/">Rust</a>"#,
+        ],
+    );
+}
+
+#[test]
+fn heading_anchor_symbols_are_configurable() {
+    let temp = DummyBook::new().build().unwrap();
+    let md = MDBook::load(temp.path()).unwrap();
+    md.build().unwrap();
+
+    let intro = temp.path().join("book/intro.html");
+    assert_contains_strings(
+        &intro,
+        &[
+            r##"<h1 id="introduction"><a class="header" href="#introduction">Introduction</a></h1>"##,
+        ],
+    );
+    assert_doesnt_contain_strings(&intro, &["header-anchor"]);
+
+    let mut cfg = Config::default();
+    cfg.set("output.html.heading-anchor", "¶").unwrap();
+    let md = MDBook::load_with_config(temp.path(), cfg).unwrap();
+    md.build().unwrap();
+
+    assert_contains_strings(
+        &intro,
+        &[
+            r##"<h1 id="introduction"><a class="header" href="#introduction">Introduction</a><a class="header-anchor" href="#introduction" aria-hidden="true">¶</a></h1>"##,
+        ],
+    );
+}
+
+#[test]
+fn print_page_can_be_renamed_and_retitled() {
+    let temp = DummyBook::new().build().unwrap();
+    let mut cfg = Config::default();
+    cfg.set("output.html.print.output-name", "all-in-one.html")
+        .unwrap();
+    cfg.set("output.html.print.title", "Everything").unwrap();
+    let md = MDBook::load_with_config(temp.path(), cfg).unwrap();
+    md.build().unwrap();
+
+    assert!(!temp.path().join("book/print.html").exists());
+    let print_html = temp.path().join("book/all-in-one.html");
+    assert_contains_strings(
+        &print_html,
+        &["<title>Everything</title>", r#"href="all-in-one.html""#],
+    );
+    assert_contains_strings(
+        temp.path().join("book/first/index.html"),
+        &[r#"href="../all-in-one.html""#],
+    );
+}
+
+#[test]
+fn chapters_can_turn_off_the_playground() {
+    let temp = DummyBook::new().build().unwrap();
+    let second = temp.path().join("src/second.md");
+    let content = fs::read_to_string(&second).unwrap();
+    fs::write(
+        &second,
+        format!("+++\nplayground = false\n+++\n{}", content),
+    )
+    .unwrap();
+
+    let mut md = MDBook::load(temp.path()).unwrap();
+    md.config
+        .set("output.html.playground.editable", true)
+        .unwrap();
+    md.build().unwrap();
+
+    let second_html = temp.path().join("book/second.html");
+    assert_contains_strings(&second_html, &["language-rust noplayground"]);
+    assert_doesnt_contain_strings(&second_html, &[r#"<pre class="playground">"#]);
+
+    let nested_html = temp.path().join("book/first/nested.html");
+    assert_contains_strings(&nested_html, &[r#"<pre class="playground">"#]);
+}
+
+#[test]
+fn turning_off_the_playground_overrides_editable_blocks() {
+    let temp = DummyBook::new().build().unwrap();
+    fs::write(
+        temp.path().join("src/second.md"),
+        "+++\nplayground = false\n+++\n# Second\n\n```rust,editable\nfn main() {}\n```\n",
+    )
+    .unwrap();
+
+    let md = MDBook::load(temp.path()).unwrap();
+    md.build().unwrap();
+
+    let second_html = temp.path().join("book/second.html");
+    assert_contains_strings(
+        &second_html,
+        &[r#"<code class="language-rust noplayground">"#],
+    );
+    assert_doesnt_contain_strings(&second_html, &[r#"<pre class="playground">"#, "editable"]);
+}
+
+#[test]
+fn no_index_chapters_ask_robots_not_to_index_them() {
+    let temp = DummyBook::new().build().unwrap();
+    let conclusion = temp.path().join("src/conclusion.md");
+    let content = fs::read_to_string(&conclusion).unwrap();
+    fs::write(
+        &conclusion,
+        format!("+++\nno-index = true\n+++\n{}", content),
+    )
+    .unwrap();
+
+    let md = MDBook::load(temp.path()).unwrap();
+    md.build().unwrap();
+
+    let conclusion_html = temp.path().join("book/conclusion.html");
+    assert_contains_strings(
+        &conclusion_html,
+        &[r#"<meta name="robots" content="noindex" />"#],
+    );
+    assert_doesnt_contain_strings(&conclusion_html, &["no-index = true"]);
+
+    let intro_html = temp.path().join("book/intro.html");
+    assert_doesnt_contain_strings(intro_html, &["noindex"]);
+}
+
+#[test]
+fn chapters_can_use_a_custom_template() {
+    let temp = DummyBook::new().build().unwrap();
+    let conclusion = temp.path().join("src/conclusion.md");
+    let content = fs::read_to_string(&conclusion).unwrap();
+    fs::write(
+        &conclusion,
+        format!("+++\ntemplate = \"landing\"\n+++\n{}", content),
+    )
+    .unwrap();
+    write_file(
+        &temp.path().join("theme"),
+        "landing.hbs",
+        b"<p class=\"landing\">{{ chapter_title }}</p>{{{ content }}}",
+    )
+    .unwrap();
+
+    let md = MDBook::load(temp.path()).unwrap();
+    md.build().unwrap();
+
+    let conclusion_html = temp.path().join("book/conclusion.html");
+    assert_contains_strings(&conclusion_html, &[r#"<p class="landing">Conclusion</p>"#]);
+    assert_doesnt_contain_strings(&conclusion_html, &["<!DOCTYPE HTML>"]);
+    let intro_html = temp.path().join("book/intro.html");
+    assert_contains_strings(&intro_html, &["<!DOCTYPE HTML>"]);
+}
+
+#[test]
+fn unknown_chapter_templates_are_an_error() {
+    let temp = DummyBook::new().build().unwrap();
+    let conclusion = temp.path().join("src/conclusion.md");
+    let content = fs::read_to_string(&conclusion).unwrap();
+    fs::write(
+        &conclusion,
+        format!("+++\ntemplate = \"missing\"\n+++\n{}", content),
+    )
+    .unwrap();
+
+    let md = MDBook::load(temp.path()).unwrap();
+    let err = md.build().unwrap_err();
+
+    let got = format!("{:?}", err);
+    assert!(
+        got.contains("\"missing\" template used by chapter \"Conclusion\""),
+        "{}",
+        got
+    );
+}
+
+#[test]
+fn prev_next_links_follow_the_reading_order() {
+    let temp = DummyBook::new().build().unwrap();
+    write_file(
+        temp.path(),
+        "reading-order.txt",
+        b"# A quicker path through the book\nconclusion.md\nintro.md\n",
+    )
+    .unwrap();
+    let mut md = MDBook::load(temp.path()).unwrap();
+    md.config
+        .set("build.reading-order", "reading-order.txt")
+        .unwrap();
+    md.build().unwrap();
+
+    let conclusion = temp.path().join("book/conclusion.html");
+    assert_contains_strings(&conclusion, &[r#"rel="next" href="intro.html""#]);
+    assert_doesnt_contain_strings(&conclusion, &[r#"rel="prev""#]);
+
+    // Chapters which aren't listed come afterwards, in SUMMARY.md order
+    let intro = temp.path().join("book/intro.html");
+    assert_contains_strings(
+        &intro,
+        &[
+            r#"rel="prev" href="conclusion.html""#,
+            r#"rel="next" href="index.html""#,
+        ],
+    );
+
+    // The table of contents isn't affected
+    let toc = temp.path().join("book/index.html");
+    assert_contains_strings(
+        &toc,
+        &[r#"<li class="chapter-item expanded affix "><a href="intro.html">Introduction</a></li>"#],
+    );
+}
+
+#[test]
+fn code_line_anchors_are_opt_in() {
+    let temp = DummyBook::new().build().unwrap();
+    let md = MDBook::load(temp.path()).unwrap();
+    md.build().unwrap();
+
+    let nested = temp.path().join("book/first/nested.html");
+    assert_doesnt_contain_strings(&nested, &["code-line-anchor"]);
+
+    let mut md = MDBook::load(temp.path()).unwrap();
+    md.config
+        .set("output.html.code-line-anchors", true)
+        .unwrap();
+    md.build().unwrap();
+
+    assert_contains_strings(
+        &nested,
+        &[
+            r##"<span class="code-line" id="first-nested-2-L1"><a class="code-line-anchor" href="#first-nested-2-L1" data-line="1" aria-hidden="true"></a>"##,
+            r#"id="first-nested-2-L2""#,
+            r#"id="first-nested-3-L1""#,
+        ],
+    );
+}
+
 #[test]
 fn markdown_options() {
     let temp = DummyBook::new().build().unwrap();
@@ -495,10 +1295,11 @@ fn markdown_options() {
     assert_contains_strings(
         &path,
         &[
-            r##"<sup class="footnote-reference"><a href="#1">1</a></sup>"##,
-            r##"<sup class="footnote-reference"><a href="#word">2</a></sup>"##,
+            r##"<sup class="footnote-reference" id="fr-1-1"><a href="#1">1</a></sup>"##,
+            r##"<sup class="footnote-reference" id="fr-word-1"><a href="#word">2</a></sup>"##,
             r##"<div class="footnote-definition" id="1"><sup class="footnote-definition-label">1</sup>"##,
             r##"<div class="footnote-definition" id="word"><sup class="footnote-definition-label">2</sup>"##,
+            r##"<a href="#fr-word-1" class="footnote-backref">↩</a>"##,
         ],
     );
     assert_contains_strings(&path, &["<del>strikethrough example</del>"]);
@@ -512,6 +1313,30 @@ fn markdown_options() {
     );
 }
 
+#[test]
+fn definition_lists_can_be_enabled() {
+    let temp = DummyBook::new().build().unwrap();
+    let markdown = temp.path().join("src/first/markdown.md");
+    let mut content = fs::read_to_string(&markdown).unwrap();
+    content.push_str("\n\nmdBook\n: A tool for making *books*.\n");
+    fs::write(&markdown, content).unwrap();
+
+    let md = MDBook::load(temp.path()).unwrap();
+    md.build().unwrap();
+    let path = temp.path().join("book/first/markdown.html");
+    assert_doesnt_contain_strings(&path, &["<dl>"]);
+
+    let mut md = MDBook::load(temp.path()).unwrap();
+    md.config
+        .set("output.html.markdown.definition-lists", true)
+        .unwrap();
+    md.build().unwrap();
+    assert_contains_strings(
+        &path,
+        &["<dl>\n<dt>mdBook</dt>\n<dd>A tool for making <em>books</em>.</dd>\n</dl>"],
+    );
+}
+
 #[test]
 fn redirects_are_emitted_correctly() {
     let temp = DummyBook::new().build().unwrap();
@@ -541,6 +1366,215 @@ fn redirects_are_emitted_correctly() {
     }
 }
 
+#[test]
+fn builtin_scripts_can_be_bundled() {
+    let temp = DummyBook::new().build().unwrap();
+    let mut md = MDBook::load(temp.path()).unwrap();
+    md.config.set("output.html.bundle-js", true).unwrap();
+    md.build().unwrap();
+
+    let book_dir = md.build_dir_for("html");
+    let bundles: Vec<_> = fs::read_dir(&book_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .filter(|name| name.starts_with("bundle-") && name.ends_with(".js"))
+        .collect();
+    assert_eq!(bundles.len(), 1);
+    assert!(!book_dir.join("book.js").exists());
+    assert!(!book_dir.join("clipboard.min.js").exists());
+
+    let bundle = fs::read(book_dir.join(&bundles[0])).unwrap();
+    let digest = format!("{:x}", Sha256::digest(&bundle));
+    assert_eq!(bundles[0], format!("bundle-{}.js", &digest[..16]));
+    let clipboard_end = theme::CLIPBOARD_JS.len();
+    assert_eq!(&bundle[..clipboard_end], theme::CLIPBOARD_JS);
+    assert!(bundle[clipboard_end..].starts_with(b"\n;\n"));
+    assert!(bundle
+        .windows(theme::JS.len())
+        .any(|window| window == theme::JS));
+
+    let index_html = book_dir.join("index.html");
+    assert_contains_strings(&index_html, &[&format!(r#"src="{}""#, bundles[0])]);
+    assert_doesnt_contain_strings(&index_html, &["book.js", "clipboard.min.js"]);
+}
+
+#[test]
+fn copy_buttons_can_be_turned_off() {
+    let temp = DummyBook::new().build().unwrap();
+    let mut md = MDBook::load(temp.path()).unwrap();
+    md.config.set("output.html.copy-buttons", false).unwrap();
+    md.build().unwrap();
+
+    let book_dir = md.build_dir_for("html");
+    assert!(!book_dir.join("clipboard.min.js").exists());
+    assert!(book_dir.join("book.js").exists());
+    assert_doesnt_contain_strings(
+        book_dir.join("index.html"),
+        &["clipboard.min.js", "window.playground_copyable"],
+    );
+}
+
+#[test]
+fn custom_fonts_are_copied_with_a_font_face_rule() {
+    let temp = DummyBook::new().build().unwrap();
+    fs::write(temp.path().join("brand.woff2"), b"not really a font").unwrap();
+    let book_toml = r#"
+        [book]
+        title = "Custom fonts"
+
+        [output.html]
+        copy-fonts = false
+
+        [[output.html.custom-fonts]]
+        family = "Brand"
+        weight = 700
+        path = "brand.woff2"
+        "#;
+    write_file(temp.path(), "book.toml", book_toml.as_bytes()).unwrap();
+    let md = MDBook::load(temp.path()).unwrap();
+    md.build().unwrap();
+
+    let fonts_dir = md.build_dir_for("html").join("fonts");
+    let copied: Vec<_> = fs::read_dir(&fonts_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .filter(|name| name != "fonts.css")
+        .collect();
+    assert_eq!(copied.len(), 1, "{:?}", copied);
+    let font_file = &copied[0];
+    let digest = format!("{:x}", Sha256::digest(b"not really a font"));
+    assert_eq!(*font_file, format!("brand-{}.woff2", &digest[..16]));
+    assert_eq!(
+        fs::read(fonts_dir.join(font_file)).unwrap(),
+        b"not really a font"
+    );
+
+    let fonts_css = fs::read_to_string(fonts_dir.join("fonts.css")).unwrap();
+    assert!(fonts_css.contains("font-family: 'Brand';"), "{}", fonts_css);
+    assert!(fonts_css.contains("font-weight: 700;"), "{}", fonts_css);
+    assert!(
+        fonts_css.contains(&format!("url('{}') format('woff2')", font_file)),
+        "{}",
+        fonts_css
+    );
+    assert!(!fonts_css.contains("Open Sans"));
+    assert_contains_strings(
+        md.build_dir_for("html").join("index.html"),
+        &["fonts/fonts.css"],
+    );
+}
+
+#[test]
+fn index_can_redirect_to_the_first_chapter() {
+    let temp = DummyBook::new().build().unwrap();
+    let summary = "# Summary\n\n- [Coming Soon]()\n- [Introduction](intro.md)\n";
+    write_file(&temp.path().join("src"), "SUMMARY.md", summary.as_bytes()).unwrap();
+
+    let mut md = MDBook::load(temp.path()).unwrap();
+    md.build().unwrap();
+    let index_html = md.build_dir_for("html").join("index.html");
+    assert!(!index_html.exists());
+
+    md.config.set("output.html.generate-index", true).unwrap();
+    md.build().unwrap();
+    assert_contains_strings(&index_html, &["URL='intro.html'"]);
+}
+
+#[test]
+fn index_redirect_uses_the_page_a_readme_is_rendered_to() {
+    let temp = DummyBook::new().build().unwrap();
+    let summary = "# Summary\n\n- [Coming Soon]()\n- [Guide](guide/README.md)\n";
+    write_file(&temp.path().join("src"), "SUMMARY.md", summary.as_bytes()).unwrap();
+    write_file(&temp.path().join("src/guide"), "README.md", b"# Guide\n").unwrap();
+    // The index preprocessor is enabled, but not for the HTML renderer.
+    let book_toml = r#"
+        [build]
+        use-default-preprocessors = false
+
+        [preprocessor.index]
+        renderers = ["markdown"]
+
+        [output.html]
+        generate-index = true
+        "#;
+    write_file(temp.path(), "book.toml", book_toml.as_bytes()).unwrap();
+
+    let md = MDBook::load(temp.path()).unwrap();
+    md.build().unwrap();
+
+    let book_dir = md.build_dir_for("html");
+    assert!(book_dir.join("guide/index.html").exists());
+    assert_contains_strings(book_dir.join("index.html"), &["URL='guide/index.html'"]);
+}
+
+#[test]
+fn sidebar_links_agree_with_pages_when_readme_is_not_renamed() {
+    // The index preprocessor is enabled, but not for the HTML renderer, so
+    // the renderer still sees README.md.
+    let temp = DummyBook::new().build().unwrap();
+    let book_toml = r#"
+        [book]
+        title = "Readme"
+
+        [build]
+        use-default-preprocessors = false
+
+        [preprocessor.index]
+        renderers = ["markdown"]
+        "#;
+    write_file(temp.path(), "book.toml", book_toml.as_bytes()).unwrap();
+    let md = MDBook::load(temp.path()).unwrap();
+    md.build().unwrap();
+
+    let book_dir = md.build_dir_for("html");
+    assert!(!book_dir.join("README.html").exists());
+    let intro = book_dir.join("intro.html");
+    assert_contains_strings(&intro, &[r#"<a href="index.html">"#]);
+    assert_doesnt_contain_strings(&intro, &["README.html"]);
+}
+
+#[test]
+fn pages_can_use_a_base_href() {
+    let temp = DummyBook::new().build().unwrap();
+    let mut md = MDBook::load(temp.path()).unwrap();
+    md.config.set("output.html.use-base-href", true).unwrap();
+    md.build().unwrap();
+
+    let nested = md.build_dir_for("html").join("first/nested.html");
+    assert_contains_strings(
+        &nested,
+        &[
+            r#"<base href="../">"#,
+            r#"href="css/general.css""#,
+            r#"<a href="first/nested.html" class="active">"#,
+            r#"href="first/nested.html#some-section""#,
+        ],
+    );
+    assert_doesnt_contain_strings(
+        &nested,
+        &[r#"href="../css"#, r#"href="../second.html"#, r##"href="#"##],
+    );
+
+    let index = md.build_dir_for("html").join("index.html");
+    assert_contains_strings(&index, &[r#"<base href="./">"#]);
+}
+
+#[test]
+fn katex_can_replace_mathjax() {
+    let temp = DummyBook::new().build().unwrap();
+    let mut md = MDBook::load(temp.path()).unwrap();
+    md.config.set("output.html.mathjax-support", true).unwrap();
+    md.config.set("output.html.math-engine", "katex").unwrap();
+    md.build().unwrap();
+
+    let index_html = temp.path().join("book/index.html");
+    assert_contains_strings(
+        &index_html,
+        &["katex.min.css", "katex.min.js", "auto-render.min.js"],
+    );
+    assert_doesnt_contain_strings(&index_html, &["MathJax.js"]);
+}
+
 #[test]
 fn edit_url_has_default_src_dir_edit_url() {
     let temp = DummyBook::new().build().unwrap();
@@ -592,6 +1626,80 @@ fn edit_url_has_configured_src_dir_edit_url() {
     );
 }
 
+#[test]
+fn edit_url_uses_the_chapter_source_path() {
+    struct AddVirtualChapter;
+
+    impl Preprocessor for AddVirtualChapter {
+        fn name(&self) -> &str {
+            "virtual-chapter"
+        }
+
+        fn run(&self, _ctx: &PreprocessorContext, mut book: Book) -> Result<Book> {
+            let mut chapter = Chapter::new("Generated", String::new(), "generated.md", vec![]);
+            chapter.source_path = None;
+            book.push_item(chapter);
+            Ok(book)
+        }
+    }
+
+    let temp = DummyBook::new().build().unwrap();
+    let mut md = MDBook::load(temp.path()).unwrap();
+    md.config
+        .set(
+            "output.html.edit-url-template",
+            "https://github.com/me/book/edit/main/{path}",
+        )
+        .unwrap();
+    md.with_preprocessor(AddVirtualChapter);
+    md.build().unwrap();
+
+    assert_contains_strings(
+        temp.path().join("book/first/nested.html"),
+        &[
+            r#"href="https://github.com/me/book/edit/main/src/first/nested.md" title="Suggest an edit""#,
+        ],
+    );
+    assert!(temp.path().join("book/generated.html").exists());
+    assert_doesnt_contain_strings(
+        temp.path().join("book/generated.html"),
+        &["Suggest an edit"],
+    );
+}
+
+#[test]
+fn chapter_names_keep_their_emphasis_in_the_sidebar() {
+    struct EmphasizeConclusion;
+
+    impl Preprocessor for EmphasizeConclusion {
+        fn name(&self) -> &str {
+            "emphasize-conclusion"
+        }
+
+        fn run(&self, _ctx: &PreprocessorContext, mut book: Book) -> Result<Book> {
+            book.for_each_mut(|item| {
+                if let BookItem::Chapter(ch) = item {
+                    if ch.name == "Conclusion" {
+                        ch.name =
+                            String::from("**Important** [Conclusion](conclusion.md) *at last*");
+                    }
+                }
+            });
+            Ok(book)
+        }
+    }
+
+    let temp = DummyBook::new().build().unwrap();
+    let mut md = MDBook::load(temp.path()).unwrap();
+    md.with_preprocessor(EmphasizeConclusion);
+    md.build().unwrap();
+
+    assert_contains_strings(
+        temp.path().join("book/index.html"),
+        &["<strong>Important</strong> Conclusion <em>at last</em></a>"],
+    );
+}
+
 fn remove_absolute_components(path: &Path) -> impl Iterator<Item = Component> + '_ {
     path.components().skip_while(|c| match c {
         Component::Prefix(_) | Component::RootDir => true,